@@ -0,0 +1,40 @@
+//! Records what the About dialog shows about the build.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let iced = lock
+        .split("[[package]]")
+        .find(|package| package.contains("name = \"iced\"\n"))
+        .and_then(|package| package.split("version = \"").nth(1))
+        .and_then(|version| version.split('"').next())
+        .unwrap_or("unknown");
+
+    println!("cargo:rustc-env=ICED_VERSION={iced}");
+
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+
+    let (year, month, day) = civil_date(days as i64);
+
+    println!("cargo:rustc-env=BUILD_DATE={year:04}-{month:02}-{day:02}");
+}
+
+/// The year, month and day of a number of days since 1970-01-01.
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    // Counting from 0000-03-01 puts leap days at the end of the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
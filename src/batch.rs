@@ -0,0 +1,203 @@
+//! Running the save hooks over files without opening a window, as with
+//! `text-editor --apply trim,final-newline file.rs`, like a pre-commit hook
+//! would.
+
+use std::path::{Path, PathBuf};
+
+use crate::{config, encoding, hex, save, session};
+
+/// The hooks to apply and the files to apply them to, from the arguments
+/// after the name of the program, if they ask for a batch.
+pub fn parse(arguments: &[String]) -> Option<Result<(Vec<save::Hook>, Vec<PathBuf>), String>> {
+    let (flag, rest) = arguments.split_first()?;
+
+    if flag != "--apply" {
+        return None;
+    }
+
+    let Some((list, paths)) = rest.split_first() else {
+        return Some(Err(usage()));
+    };
+
+    let hooks = list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| save::Hook::named(name).ok_or_else(|| format!("Unknown hook `{name}`")))
+        .collect::<Result<Vec<_>, _>>();
+
+    Some(match hooks {
+        Ok(hooks) if hooks.is_empty() || paths.is_empty() => Err(usage()),
+        Ok(hooks) => Ok((hooks, paths.iter().map(PathBuf::from).collect())),
+        Err(error) => Err(format!("{error}\n{}", usage())),
+    })
+}
+
+/// Applies `hooks` to every file of `paths`, in the order saving runs them,
+/// and writes back the ones they change.
+///
+/// Each file is read, transformed and written as the editor would save it,
+/// with its settings and those of its project. What went wrong with a file
+/// is reported without stopping at it, and the result is whether every
+/// file went well.
+pub fn run(config: &config::Config, hooks: &[save::Hook], paths: &[PathBuf]) -> bool {
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread().build() else {
+        eprintln!("Could not start writing files");
+        return false;
+    };
+
+    let session = session::Session::load();
+    let mut is_ok = true;
+
+    for path in paths {
+        let config = config::Config {
+            on_save: hooks.to_vec(),
+            ..config.for_file(path)
+        };
+
+        match runtime.block_on(apply(&config, &session, path)) {
+            Ok(true) => println!("{}: changed", path.display()),
+            Ok(false) => {}
+            Err(error) => {
+                eprintln!("{}: {error}", path.display());
+                is_ok = false;
+            }
+        }
+    }
+
+    is_ok
+}
+
+/// Runs the hooks of `config` over the file at `path`, and whether that
+/// changed it.
+async fn apply(
+    config: &config::Config,
+    session: &session::Session,
+    path: &Path,
+) -> Result<bool, String> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    let marked = encoding::Encoding::from_bom(&bytes);
+
+    if marked.is_none() && hex::is_binary(&bytes) {
+        return Err("Binary, left alone".to_owned());
+    }
+
+    let encoding = marked.unwrap_or_default();
+    let decoded = encoding.decode(&bytes)?;
+    let line_ending = encoding::LineEnding::detect(&decoded);
+    let text = encoding::normalize(&decoded);
+
+    let transformed = save::run(config, text.clone(), &extension(config, session, path))?;
+
+    if transformed == text {
+        return Ok(false);
+    }
+
+    let bytes = encoding.encode(&line_ending.apply(&transformed), encoding.starts(&bytes))?;
+
+    save::write(path, &bytes)
+        .await
+        .map_err(|error| std::io::Error::from(error).to_string())?;
+
+    Ok(true)
+}
+
+/// The extension of the language of the file at `path`, as the editor
+/// picks it.
+fn extension(config: &config::Config, session: &session::Session, path: &Path) -> String {
+    let picked = session.document(&session::key(path)).language;
+
+    let associated = || {
+        path.file_name()
+            .and_then(|name| config.association(name.to_str()?))
+            .map(str::to_owned)
+    };
+
+    picked
+        .or_else(associated)
+        .or_else(|| path.extension()?.to_str().map(str::to_owned))
+        .unwrap_or_else(|| "rs".to_owned())
+}
+
+fn usage() -> String {
+    let names: Vec<_> = save::Hook::ALL.into_iter().map(save::Hook::name).collect();
+
+    format!(
+        "Usage: text-editor --apply <hook>[,<hook>...] <file>...\nThe hooks are {}",
+        names.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|argument| (*argument).to_owned()).collect()
+    }
+
+    #[test]
+    fn parse_leaves_other_arguments_alone() {
+        assert!(parse(&arguments(&[])).is_none());
+        assert!(parse(&arguments(&["file.rs"])).is_none());
+    }
+
+    #[test]
+    fn parse_reads_hooks_and_files() {
+        let (hooks, paths) = parse(&arguments(&["--apply", "trim, final-newline", "a.rs", "b.rs"]))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            hooks,
+            [save::Hook::TrimTrailingWhitespace, save::Hook::EnsureFinalNewline]
+        );
+        assert_eq!(paths, [PathBuf::from("a.rs"), PathBuf::from("b.rs")]);
+    }
+
+    #[test]
+    fn parse_refuses_unknown_hooks() {
+        let error = parse(&arguments(&["--apply", "trim,tidy", "a.rs"]))
+            .unwrap()
+            .unwrap_err();
+
+        assert!(error.starts_with("Unknown hook `tidy`"));
+    }
+
+    #[test]
+    fn parse_needs_hooks_and_files() {
+        assert_eq!(parse(&arguments(&["--apply"])), Some(Err(usage())));
+        assert_eq!(parse(&arguments(&["--apply", " , ", "a.rs"])), Some(Err(usage())));
+        assert_eq!(parse(&arguments(&["--apply", "trim"])), Some(Err(usage())));
+    }
+
+    #[test]
+    fn apply_rewrites_the_file() {
+        let path =
+            std::env::temp_dir().join(format!("text-editor-batch-{}.txt", std::process::id()));
+        std::fs::write(&path, "one  \ntwo\t").unwrap();
+
+        let (hooks, _) = parse(&arguments(&["--apply", "trim,final-newline", "file"]))
+            .unwrap()
+            .unwrap();
+        let config = config::Config {
+            on_save: hooks,
+            ..config::Config::default()
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let session = session::Session::default();
+
+        let changed = runtime.block_on(apply(&config, &session, &path));
+        let text = std::fs::read_to_string(&path);
+        let unchanged = runtime.block_on(apply(&config, &session, &path));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(changed, Ok(true));
+        assert_eq!(text.unwrap(), "one\ntwo\n");
+        assert_eq!(unchanged, Ok(false));
+    }
+}
@@ -0,0 +1,282 @@
+//! Arithmetic on selected text.
+//!
+//! Expressions support `+ - * / ^`, parentheses, postfix percentages, the
+//! constants `pi` and `e`, and a handful of functions like `sqrt` or `max`.
+
+/// Evaluates `expression`, or explains where it went wrong.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+
+    let value = parser.sum()?;
+
+    match parser.peek() {
+        Some(token) => Err(format!("Unexpected {token}")),
+        None if !value.is_finite() => Err("The result is not a number".to_owned()),
+        None => Ok(value),
+    }
+}
+
+/// Formats a result without a fractional part when it has none, and without
+/// the noise of floating point rounding otherwise.
+pub fn format(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.0}")
+    } else {
+        let rounded = format!("{value:.10}");
+
+        rounded
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Name(name) => write!(f, "{name}"),
+            Self::Operator(operator) => write!(f, "{operator}"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                let _ = chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    match c {
+                        '0'..='9' | '.' => number.push(c),
+                        // Digit group separators
+                        '_' => {}
+                        _ => break,
+                    }
+
+                    let _ = chars.next();
+                }
+
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("Invalid number {number}"))?;
+
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() => {
+                let mut name = String::new();
+
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric()) {
+                    name.push(c);
+                    let _ = chars.next();
+                }
+
+                tokens.push(Token::Name(name.to_lowercase()));
+            }
+            '+' | '-' | '*' | '/' | '^' | '%' | '(' | ')' | ',' => {
+                tokens.push(Token::Operator(c));
+                let _ = chars.next();
+            }
+            '×' => {
+                tokens.push(Token::Operator('*'));
+                let _ = chars.next();
+            }
+            '÷' => {
+                tokens.push(Token::Operator('/'));
+                let _ = chars.next();
+            }
+            c => return Err(format!("Unexpected {c}")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, operator: char) -> bool {
+        let found = self.peek() == Some(&Token::Operator(operator));
+
+        if found {
+            self.position += 1;
+        }
+
+        found
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_owned());
+                }
+
+                value /= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.postfix()?;
+
+        if self.eat('^') {
+            // Right associative, and binding tighter than a leading minus
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn postfix(&mut self) -> Result<f64, String> {
+        let mut value = self.atom()?;
+
+        while self.eat('%') {
+            value /= 100.0;
+        }
+
+        Ok(value)
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(Token::Operator('(')) => {
+                let value = self.sum()?;
+
+                if self.eat(')') {
+                    Ok(value)
+                } else {
+                    Err("Missing )".to_owned())
+                }
+            }
+            Some(Token::Name(name)) => match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ => {
+                    let arguments = self.arguments(&name)?;
+
+                    call(&name, &arguments)
+                }
+            },
+            Some(token) => Err(format!("Unexpected {token}")),
+            None => Err("Incomplete expression".to_owned()),
+        }
+    }
+
+    /// The arguments of a function call, separated by commas.
+    fn arguments(&mut self, name: &str) -> Result<Vec<f64>, String> {
+        if !self.eat('(') {
+            return Err(format!("Unknown name {name}"));
+        }
+
+        let mut arguments = vec![self.sum()?];
+
+        while self.eat(',') {
+            arguments.push(self.sum()?);
+        }
+
+        if self.eat(')') {
+            Ok(arguments)
+        } else {
+            Err("Missing )".to_owned())
+        }
+    }
+}
+
+fn call(name: &str, arguments: &[f64]) -> Result<f64, String> {
+    let unary = |f: fn(f64) -> f64| match arguments {
+        [x] => Ok(f(*x)),
+        _ => Err(format!("{name} takes one argument")),
+    };
+
+    match name {
+        "sqrt" => unary(f64::sqrt),
+        "abs" => unary(f64::abs),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "ln" => unary(f64::ln),
+        "log" => unary(f64::log10),
+        "exp" => unary(f64::exp),
+        "round" => unary(f64::round),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "min" => arguments
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .ok_or_else(|| "min takes arguments".to_owned()),
+        "max" => arguments
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .ok_or_else(|| "max takes arguments".to_owned()),
+        _ => Err(format!("Unknown function {name}")),
+    }
+}
@@ -0,0 +1,54 @@
+//! Comparing the document with another file, line by line and side by side.
+
+use similar::{DiffTag, TextDiff};
+
+/// A line of each side, shown next to each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// The number and text of the line of the document, counting from 0.
+    pub left: Option<(usize, String)>,
+    /// The number and text of the line of the other file.
+    pub right: Option<(usize, String)>,
+    pub is_changed: bool,
+}
+
+/// The lines of `left` and `right`, lined up so that the ones they share
+/// face each other.
+pub fn side_by_side(left: &str, right: &str) -> Vec<Row> {
+    let diff = TextDiff::from_lines(left, right);
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+
+    let line = |lines: &[&str], number: usize| {
+        lines
+            .get(number)
+            .map(|text| (number, (*text).to_owned()))
+    };
+
+    let mut rows = Vec::new();
+
+    for operation in diff.ops() {
+        let (tag, old, new) = operation.as_tag_tuple();
+
+        if tag == DiffTag::Equal {
+            rows.extend(old.zip(new).map(|(old, new)| Row {
+                left: line(&left, old),
+                right: line(&right, new),
+                is_changed: false,
+            }));
+
+            continue;
+        }
+
+        // Lines replacing others face them, and the rest face nothing
+        for offset in 0..old.len().max(new.len()) {
+            rows.push(Row {
+                left: (offset < old.len()).then(|| line(&left, old.start + offset)).flatten(),
+                right: (offset < new.len()).then(|| line(&right, new.start + offset)).flatten(),
+                is_changed: true,
+            });
+        }
+    }
+
+    rows
+}
@@ -0,0 +1,37 @@
+//! Completion of the identifiers that already appear in a buffer.
+
+use std::collections::HashMap;
+
+pub fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The identifiers of `text` that extend `prefix`, nearest to `line` first.
+pub fn words(text: &str, prefix: &str, line: usize, limit: usize) -> Vec<String> {
+    let mut distances: HashMap<&str, usize> = HashMap::new();
+
+    for (number, content) in text.split('\n').enumerate() {
+        let distance = number.abs_diff(line);
+
+        for word in content.split(|c: char| !is_identifier(c)) {
+            if word.len() > prefix.len()
+                && word.starts_with(prefix)
+                && !word.starts_with(|c: char| c.is_ascii_digit())
+            {
+                distances
+                    .entry(word)
+                    .and_modify(|nearest| *nearest = (*nearest).min(distance))
+                    .or_insert(distance);
+            }
+        }
+    }
+
+    let mut words: Vec<_> = distances.into_iter().collect();
+    words.sort_by_key(|(word, distance)| (*distance, *word));
+
+    words
+        .into_iter()
+        .take(limit)
+        .map(|(word, _)| word.to_owned())
+        .collect()
+}
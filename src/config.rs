@@ -0,0 +1,748 @@
+//! User configuration, read from `config.toml` in the platform config directory.
+//!
+//! A project can carry its own settings in a `.texteditor.toml` at its root,
+//! which apply over these to the files inside it, as far as they are among
+//! the harmless ones a project may set.
+
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{find, indent, language, save, surround, todo};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// The language servers to launch, by file extension.
+    pub language_servers: BTreeMap<String, Server>,
+    /// The commands that run files, by file extension, with `{file}`
+    /// standing for the path of the file.
+    pub run_commands: BTreeMap<String, String>,
+    pub colors: Colors,
+    /// The extension of the language files are highlighted as, by
+    /// patterns of their names like `*.gradle` or `Dockerfile`.
+    pub associations: BTreeMap<String, String>,
+    /// The file extensions highlighted with tree-sitter rather than the
+    /// syntax theme, out of those it has a grammar for.
+    pub tree_sitter: Vec<String>,
+    /// The themes of the languages that look unlike the rest, by
+    /// extension, named as the theme picker shows them.
+    pub themes: BTreeMap<String, String>,
+    /// The height of a line, relative to the text size.
+    pub line_height: f32,
+    /// The font family of the editor, if not the default monospace one.
+    pub font: Option<String>,
+    /// The column to soft-wrap at, instead of the edge of the window.
+    pub wrap_column: Option<usize>,
+    /// How many characters lines can have before the rest is flagged, if
+    /// their length is limited.
+    pub max_line_length: Option<usize>,
+    /// Whether to edit with Vim keybindings.
+    pub vim: bool,
+    /// Whether to edit with Emacs keybindings.
+    pub emacs: bool,
+    /// Whether Tab indents with spaces in files that do not show how they
+    /// indent.
+    pub insert_spaces: bool,
+    /// How many spaces an indentation level takes when indenting with them.
+    pub tab_width: usize,
+    /// The file extensions whose pastes are re-indented to fit the cursor.
+    pub reindent_on_paste: Vec<String>,
+    /// Whether to turn smart quotes, dashes and non-breaking spaces into
+    /// ASCII when pasting.
+    pub normalize_on_paste: bool,
+    /// The delimiters to surround the selection with, by the character
+    /// typed to pick them.
+    pub surround: BTreeMap<String, (String, String)>,
+    /// What to do to the text before saving it.
+    pub on_save: Vec<save::Hook>,
+    /// The keywords that mark what is left to do in comments, like `TODO`,
+    /// with their colors as `#rrggbb`.
+    pub todo_markers: BTreeMap<String, String>,
+    /// How many blank lines in a row collapsing blank lines leaves.
+    pub max_blank_lines: u8,
+    /// How big a file can be, in MiB, to be opened whole rather than shown
+    /// a page at a time, read-only.
+    pub max_buffer_mib: u16,
+    /// Whether to copy the previous version of a file aside before
+    /// overwriting it.
+    pub keep_backup: bool,
+    pub backup: Backup,
+    /// When to ask before actions that are hard to take back.
+    pub confirm: Confirm,
+    /// Whether to ask before saving as a file that already exists, even if
+    /// the dialog asked already.
+    pub ask_before_overwrite: bool,
+    /// Whether saving creates the directories of the path that do not
+    /// exist yet.
+    pub create_directories: bool,
+    /// Whether the scratch buffer keeps its text between runs.
+    pub keep_scratch: bool,
+    /// Whether new files that were never saved are copied aside now and
+    /// then, to offer them back if the editor goes away.
+    pub recover_new_files: bool,
+    /// Whether the middle button pastes the text last selected, like the
+    /// primary selection of Unix desktops.
+    pub middle_click_paste: bool,
+    /// Whether a fourth click in a row selects the paragraph.
+    pub quadruple_click_paragraph: bool,
+    /// What to do when the open file changes on disk.
+    pub reload: Reload,
+    /// Whether watching the file and scrolling smoothly wait while the
+    /// window is unfocused, unlike the snapshots of unsaved work.
+    pub pause_when_unfocused: bool,
+    /// Where the open and save dialogs start.
+    pub dialog_directory: DialogDirectory,
+    /// The directory of [`DialogDirectory::Fixed`].
+    pub fixed_directory: Option<PathBuf>,
+    /// The kinds of files the open dialog can be narrowed to, after all
+    /// files.
+    pub file_filters: Vec<FileFilter>,
+    /// Whether scrolling with the wheel and by pages glides instead of
+    /// jumping.
+    pub smooth_scrolling: bool,
+    /// The share of the distance left that smooth scrolling covers each
+    /// frame.
+    pub scroll_speed: f32,
+    /// How many lines a notch of the mouse wheel scrolls by.
+    pub wheel_lines: u8,
+    /// How many lines to keep in view above and below the cursor.
+    pub scroll_off: u8,
+    /// The fewest milliseconds between recoloring long documents from the
+    /// top while typing, or 0 to recolor on every keystroke.
+    pub highlight_interval: u64,
+    /// How the find bar matched when it was last used.
+    pub find: find::Options,
+    /// The project whose settings apply over the others, if any.
+    #[serde(skip)]
+    pub project: Option<Project>,
+}
+
+/// The name of the file of settings at the root of a project.
+pub const PROJECT_FILE: &str = ".texteditor.toml";
+
+/// The settings a project can set, which only change how its files are
+/// edited and saved.
+///
+/// Anything that runs programs, like language servers and run commands, or
+/// writes elsewhere, like backups, is left out: a project may come from
+/// anyone, and opening one of its files must not run what it names.
+const PROJECT_KEYS: [&str; 12] = [
+    "associations",
+    "wrap_column",
+    "max_line_length",
+    "insert_spaces",
+    "tab_width",
+    "reindent_on_paste",
+    "normalize_on_paste",
+    "surround",
+    "on_save",
+    "todo_markers",
+    "max_blank_lines",
+    "tree_sitter",
+];
+
+/// Settings of a project, merged over the others for the files inside it.
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// The directory with the file of settings.
+    pub root: PathBuf,
+    settings: toml::Table,
+    /// All the settings before merging the ones of the project.
+    global: toml::Table,
+    /// The settings the project overrides, as they were once merged.
+    merged: toml::Table,
+}
+
+/// The line heights that stay readable.
+pub const LINE_HEIGHTS: RangeInclusive<f32> = 1.0..=2.5;
+
+/// The speeds of smooth scrolling, from barely moving to instant.
+pub const SCROLL_SPEEDS: RangeInclusive<f32> = 0.1..=1.0;
+
+/// The lines per notch of the wheel that can be chosen.
+pub const WHEEL_LINES: RangeInclusive<u8> = 1..=20;
+
+/// The blank lines in a row that collapsing them can leave.
+pub const MAX_BLANK_LINES: RangeInclusive<u8> = 1..=5;
+
+/// The sizes in MiB that files can be opened whole up to.
+pub const MAX_BUFFER_MIB: RangeInclusive<u16> = 16..=1024;
+
+/// The margins around the cursor that can be chosen.
+pub const SCROLL_OFFS: RangeInclusive<u8> = 0..=10;
+
+impl Default for Config {
+    fn default() -> Self {
+        let server = |command: &str, args: &[&str], language: &str| Server {
+            command: command.to_owned(),
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            language: language.to_owned(),
+        };
+
+        Self {
+            language_servers: BTreeMap::from([
+                ("rs".to_owned(), server("rust-analyzer", &[], "rust")),
+                ("py".to_owned(), server("pylsp", &[], "python")),
+                ("go".to_owned(), server("gopls", &[], "go")),
+                ("c".to_owned(), server("clangd", &[], "c")),
+                ("h".to_owned(), server("clangd", &[], "c")),
+                ("cpp".to_owned(), server("clangd", &[], "cpp")),
+                (
+                    "js".to_owned(),
+                    server("typescript-language-server", &["--stdio"], "javascript"),
+                ),
+                (
+                    "ts".to_owned(),
+                    server("typescript-language-server", &["--stdio"], "typescript"),
+                ),
+            ]),
+            run_commands: [
+                ("rs", "cargo run"),
+                ("py", "python3 {file}"),
+                ("js", "node {file}"),
+                ("ts", "deno run {file}"),
+                ("go", "go run {file}"),
+                ("rb", "ruby {file}"),
+                ("sh", "sh {file}"),
+            ]
+            .into_iter()
+            .map(|(extension, command)| (extension.to_owned(), command.to_owned()))
+            .collect(),
+            colors: Colors::default(),
+            associations: BTreeMap::new(),
+            tree_sitter: Vec::new(),
+            themes: BTreeMap::new(),
+            line_height: 1.2,
+            font: None,
+            wrap_column: None,
+            max_line_length: None,
+            vim: false,
+            emacs: false,
+            insert_spaces: false,
+            tab_width: 4,
+            reindent_on_paste: ["rs", "c", "h", "cpp", "go", "java", "js", "ts", "json", "py"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            normalize_on_paste: false,
+            surround: surround::defaults(),
+            on_save: Vec::new(),
+            todo_markers: todo::defaults(),
+            max_blank_lines: 2,
+            max_buffer_mib: 64,
+            keep_backup: false,
+            backup: Backup::default(),
+            confirm: Confirm::default(),
+            ask_before_overwrite: true,
+            create_directories: true,
+            keep_scratch: true,
+            recover_new_files: true,
+            middle_click_paste: cfg!(unix) && !cfg!(target_os = "macos"),
+            quadruple_click_paragraph: true,
+            reload: Reload::default(),
+            pause_when_unfocused: true,
+            dialog_directory: DialogDirectory::default(),
+            fixed_directory: None,
+            file_filters: FileFilter::defaults(),
+            smooth_scrolling: true,
+            scroll_speed: 0.3,
+            wheel_lines: 4,
+            scroll_off: 3,
+            highlight_interval: 200,
+            find: find::Options::default(),
+            project: None,
+        }
+    }
+}
+
+impl Config {
+    /// The size in bytes of the biggest file opened whole.
+    pub fn max_buffer_size(&self) -> u64 {
+        u64::from(self.max_buffer_mib) * 1024 * 1024
+    }
+
+    /// How to indent files that do not show how they indent.
+    pub fn indent(&self) -> indent::Style {
+        if self.insert_spaces {
+            indent::Style::Spaces(self.tab_width)
+        } else {
+            indent::Style::Tabs
+        }
+    }
+
+    /// The extension of the language associated with files named
+    /// `file_name`, preferring whole names to patterns.
+    pub fn association(&self, file_name: &str) -> Option<&str> {
+        self.associations
+            .get(file_name)
+            .or_else(|| {
+                self.associations
+                    .iter()
+                    .find(|(pattern, _)| matches(pattern, file_name))
+                    .map(|(_, extension)| extension)
+            })
+            .map(String::as_str)
+    }
+
+    /// Reads the configuration file, falling back to the defaults.
+    pub fn load() -> Self {
+        let mut config: Self = path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+
+        config.clamp();
+        config
+    }
+
+    /// The settings for the file at `path`, with those of the project it is
+    /// in merged over the global ones.
+    ///
+    /// Tables like `associations` are merged key by key, and a project file
+    /// that cannot be read is ignored.
+    pub fn for_file(&self, path: &Path) -> Self {
+        let global = self.global();
+
+        let Some((root, settings)) = project(path) else {
+            return global;
+        };
+
+        let Ok(toml::Value::Table(table)) = toml::Value::try_from(&global) else {
+            return global;
+        };
+
+        let mut merged = table.clone();
+        merge(&mut merged, &settings);
+
+        let overridden = settings
+            .keys()
+            .filter_map(|key| Some((key.clone(), merged.get(key)?.clone())))
+            .collect();
+
+        match toml::Value::Table(merged).try_into::<Self>() {
+            Ok(mut config) => {
+                config.clamp();
+                config.project = Some(Project {
+                    root,
+                    settings,
+                    global: table,
+                    merged: overridden,
+                });
+                config
+            }
+            Err(_) => global,
+        }
+    }
+
+    /// These settings without the ones of the project, each setting it
+    /// overrides back to what it was before, unless it was changed since.
+    pub fn global(&self) -> Self {
+        let Some(project) = &self.project else {
+            return self.clone();
+        };
+
+        let Ok(toml::Value::Table(mut table)) = toml::Value::try_from(self) else {
+            return self.clone();
+        };
+
+        for key in project.settings.keys() {
+            // A setting changed in the editor is a change of the global one
+            if table.get(key) != project.merged.get(key) {
+                continue;
+            }
+
+            match project.global.get(key) {
+                Some(value) => table.insert(key.clone(), value.clone()),
+                None => table.remove(key),
+            };
+        }
+
+        toml::Value::Table(table)
+            .try_into()
+            .unwrap_or_else(|_| self.clone())
+    }
+
+    /// Saves the global settings, leaving out the ones of the project.
+    pub fn save(&self) -> Result<(), io::ErrorKind> {
+        let path = path().ok_or(io::ErrorKind::NotFound)?;
+        let text =
+            toml::to_string_pretty(&self.global()).map_err(|_| io::ErrorKind::InvalidData)?;
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|error| error.kind())?;
+        }
+
+        std::fs::write(path, text).map_err(|error| error.kind())
+    }
+
+    /// Brings the settings that have limits within them.
+    fn clamp(&mut self) {
+        self.line_height = clamp_line_height(self.line_height);
+        self.tab_width = self.tab_width.max(1);
+        self.scroll_speed = clamp_scroll_speed(self.scroll_speed);
+        self.wheel_lines = self
+            .wheel_lines
+            .clamp(*WHEEL_LINES.start(), *WHEEL_LINES.end());
+        self.scroll_off = self.scroll_off.min(*SCROLL_OFFS.end());
+        self.max_blank_lines = self
+            .max_blank_lines
+            .clamp(*MAX_BLANK_LINES.start(), *MAX_BLANK_LINES.end());
+        self.max_buffer_mib = self
+            .max_buffer_mib
+            .clamp(*MAX_BUFFER_MIB.start(), *MAX_BUFFER_MIB.end());
+    }
+}
+
+/// The highlight colors, as `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Colors {
+    pub find_match: String,
+    pub current_match: String,
+    /// Empty to use the selection color of the theme.
+    pub selection: String,
+    pub referred_lines: String,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            find_match: "#e5c07b".to_owned(),
+            current_match: "#ff8c1a".to_owned(),
+            selection: String::new(),
+            referred_lines: "#61afef".to_owned(),
+        }
+    }
+}
+
+/// One of the configurable highlight colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    FindMatch,
+    CurrentMatch,
+    Selection,
+    /// The lines opened to as a range, like `file.rs:10-20`.
+    ReferredLines,
+}
+
+impl Highlight {
+    pub const ALL: [Self; 4] = [
+        Self::FindMatch,
+        Self::CurrentMatch,
+        Self::Selection,
+        Self::ReferredLines,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::FindMatch => "Find matches",
+            Self::CurrentMatch => "Current match",
+            Self::Selection => "Selection",
+            Self::ReferredLines => "Referred lines",
+        }
+    }
+}
+
+impl Colors {
+    pub fn get(&self, highlight: Highlight) -> &str {
+        match highlight {
+            Highlight::FindMatch => &self.find_match,
+            Highlight::CurrentMatch => &self.current_match,
+            Highlight::Selection => &self.selection,
+            Highlight::ReferredLines => &self.referred_lines,
+        }
+    }
+
+    pub fn set(&mut self, highlight: Highlight, value: String) {
+        match highlight {
+            Highlight::FindMatch => self.find_match = value,
+            Highlight::CurrentMatch => self.current_match = value,
+            Highlight::Selection => self.selection = value,
+            Highlight::ReferredLines => self.referred_lines = value,
+        }
+    }
+
+    /// The color of `highlight`, falling back to the default when the
+    /// configured one is not a valid color.
+    pub fn color(&self, highlight: Highlight) -> Option<Color> {
+        let value = self.get(highlight);
+
+        if value.is_empty() && highlight == Highlight::Selection {
+            return None;
+        }
+
+        parse_color(value).or_else(|| parse_color(Colors::default().get(highlight)))
+    }
+
+    /// Whether the configured value of `highlight` can be used.
+    pub fn is_valid(&self, highlight: Highlight) -> bool {
+        let value = self.get(highlight);
+
+        (value.is_empty() && highlight == Highlight::Selection) || parse_color(value).is_some()
+    }
+}
+
+/// Parses a `#rrggbb` or `#rrggbbaa` color.
+pub fn parse_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#')?;
+
+    if !matches!(digits.len(), 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |index: usize| u8::from_str_radix(&digits[index..index + 2], 16).ok();
+    let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+
+    Some(Color::from_rgba8(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        f32::from(alpha) / 255.0,
+    ))
+}
+
+/// How backups are named.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Backup {
+    /// Appended to the name of the file, like `main.rs.bak`.
+    pub suffix: String,
+    /// Whether to keep every backup, as `main.rs.bak.1`, `main.rs.bak.2`
+    /// and so on, instead of only the last one.
+    pub numbered: bool,
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self {
+            suffix: ".bak".to_owned(),
+            numbered: false,
+        }
+    }
+}
+
+/// How big actions that are hard to take back get before asking first,
+/// with `None` to never ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Confirm {
+    /// How many lines replacing in files changes.
+    pub replaced_lines: Option<usize>,
+    /// How many lines deleting the selection takes away.
+    pub deleted_lines: Option<usize>,
+}
+
+impl Confirm {
+    /// Whether an action of `size` has to be confirmed, for its threshold.
+    pub fn asks(threshold: Option<usize>, size: usize) -> bool {
+        threshold.is_some_and(|threshold| size >= threshold)
+    }
+}
+
+impl Default for Confirm {
+    fn default() -> Self {
+        Self {
+            replaced_lines: Some(1),
+            deleted_lines: Some(500),
+        }
+    }
+}
+
+/// A kind of files the open dialog can be narrowed to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileFilter {
+    pub name: String,
+    /// The extensions of the files, without the dot.
+    pub extensions: Vec<String>,
+}
+
+impl FileFilter {
+    /// Text files, source code, and the files of each known language.
+    fn defaults() -> Vec<Self> {
+        let languages = language::Language::ALL
+            .iter()
+            .filter(|language| language.extension != "txt");
+
+        let text = Self {
+            name: "Text files".to_owned(),
+            extensions: ["txt", "md", "log", "csv", "tsv", "ini", "cfg", "conf"]
+                .map(str::to_owned)
+                .to_vec(),
+        };
+
+        let source = Self {
+            name: "Source code".to_owned(),
+            extensions: languages
+                .clone()
+                .map(|language| language.extension.to_owned())
+                .collect(),
+        };
+
+        [text, source]
+            .into_iter()
+            .chain(languages.map(|language| Self {
+                name: language.name.to_owned(),
+                extensions: vec![language.extension.to_owned()],
+            }))
+            .collect()
+    }
+}
+
+/// What to do when another program changes the open file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reload {
+    Always,
+    /// Ask whether to reload, even without unsaved changes.
+    Prompt,
+    /// Only say that it changed.
+    Never,
+    /// Reload when there is nothing to lose, and ask otherwise.
+    #[default]
+    WhenClean,
+}
+
+impl Reload {
+    pub const ALL: &'static [Self] = &[Self::WhenClean, Self::Always, Self::Prompt, Self::Never];
+}
+
+impl fmt::Display for Reload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Always => "Always reload",
+            Self::Prompt => "Always ask",
+            Self::Never => "Never reload",
+            Self::WhenClean => "Reload unless there are unsaved changes",
+        })
+    }
+}
+
+/// Where the open and save dialogs start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogDirectory {
+    /// The directory of the open file, or else the one used last.
+    #[default]
+    File,
+    /// The directory of the file opened or saved last.
+    LastUsed,
+    /// The directory set in the configuration.
+    Fixed,
+}
+
+impl DialogDirectory {
+    pub const ALL: &'static [Self] = &[Self::File, Self::LastUsed, Self::Fixed];
+}
+
+impl fmt::Display for DialogDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "The folder of the open file",
+            Self::LastUsed => "The folder used last",
+            Self::Fixed => "A fixed folder",
+        })
+    }
+}
+
+/// How to launch the language server of a language.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Server {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The LSP language identifier, e.g. `rust`.
+    pub language: String,
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` stands for any
+/// characters and `?` for one.
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Where the last star was, and how much of the name it took
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The root of the nearest project around the file at `path`, and its
+/// settings.
+fn project(path: &Path) -> Option<(PathBuf, toml::Table)> {
+    let root = path
+        .ancestors()
+        .skip(1)
+        .find(|directory| directory.join(PROJECT_FILE).is_file())?;
+
+    let text = std::fs::read_to_string(root.join(PROJECT_FILE)).ok()?;
+    let mut settings: toml::Table = toml::from_str(&text).ok()?;
+
+    settings.retain(|key, _| PROJECT_KEYS.contains(&key.as_str()));
+
+    Some((root.to_path_buf(), settings))
+}
+
+/// Puts the values of `other` in `table`, merging the tables both have.
+fn merge(table: &mut toml::Table, other: &toml::Table) {
+    for (key, value) in other {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(other)) => merge(table, other),
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join("text-editor").join("config.toml"))
+}
+
+pub fn clamp_line_height(line_height: f32) -> f32 {
+    if line_height.is_nan() {
+        Config::default().line_height
+    } else {
+        line_height.clamp(*LINE_HEIGHTS.start(), *LINE_HEIGHTS.end())
+    }
+}
+
+pub fn clamp_scroll_speed(scroll_speed: f32) -> f32 {
+    if scroll_speed.is_nan() {
+        Config::default().scroll_speed
+    } else {
+        scroll_speed.clamp(*SCROLL_SPEEDS.start(), *SCROLL_SPEEDS.end())
+    }
+}
@@ -0,0 +1,92 @@
+//! Merge conflicts left in files by git, and resolving them.
+
+/// The lines of the markers of a conflict, counting from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    /// The `<<<<<<<` line, which the current side follows.
+    pub start: usize,
+    /// The `|||||||` line of the common ancestor, in the diff3 style.
+    pub base: Option<usize>,
+    /// The `=======` line, which the incoming side follows.
+    pub middle: usize,
+    /// The `>>>>>>>` line.
+    pub end: usize,
+}
+
+/// Which side of a conflict to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Current,
+    Incoming,
+    Both,
+}
+
+impl Conflict {
+    /// Whether `line` is one of the lines of the conflict.
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+
+    /// The lines of its markers.
+    pub fn markers(&self) -> impl Iterator<Item = usize> {
+        [Some(self.start), self.base, Some(self.middle), Some(self.end)]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// The conflicts of `text`, top to bottom.
+pub fn find(text: &str) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut start = None;
+    let mut base = None;
+    let mut middle = None;
+
+    for (number, line) in text.split('\n').enumerate() {
+        if line.starts_with("<<<<<<<") {
+            start = Some(number);
+            base = None;
+            middle = None;
+        } else if line.starts_with("|||||||") && start.is_some() && middle.is_none() {
+            base = Some(number);
+        } else if line.starts_with("=======") && start.is_some() && middle.is_none() {
+            middle = Some(number);
+        } else if line.starts_with(">>>>>>>") {
+            if let (Some(start), Some(middle)) = (start, middle) {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    middle,
+                    end: number,
+                });
+            }
+
+            start = None;
+            middle = None;
+        }
+    }
+
+    conflicts
+}
+
+/// `text` with `conflict` replaced by the side or sides of `choice`.
+pub fn resolve(text: &str, conflict: Conflict, choice: Choice) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    let current = &lines[conflict.start + 1..conflict.base.unwrap_or(conflict.middle)];
+    let incoming = &lines[conflict.middle + 1..conflict.end];
+
+    let kept = match choice {
+        Choice::Current => current.to_vec(),
+        Choice::Incoming => incoming.to_vec(),
+        Choice::Both => [current, incoming].concat(),
+    };
+
+    lines[..conflict.start]
+        .iter()
+        .chain(&kept)
+        .chain(&lines[conflict.end + 1..])
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,135 @@
+//! Fields of comma and tab separated files, and lining them up.
+//!
+//! Lines are split on their own, so a quoted field spanning several lines
+//! is read as fields of each of them.
+
+use std::ops::Range;
+
+/// What separates the columns of the aligned view, whatever the delimiter.
+///
+/// Tabs would jump to tab stops and break the alignment.
+pub const SEPARATOR: &str = " │ ";
+
+/// The field separator of files with `extension`, if they are tabular.
+pub fn delimiter(extension: &str) -> Option<char> {
+    match extension {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// The byte ranges of the fields of `line`, without the separators.
+///
+/// Separators inside double quotes belong to the field.
+pub fn fields(line: &str, delimiter: char) -> Vec<Range<usize>> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut is_quoted = false;
+
+    for (offset, c) in line.char_indices() {
+        match c {
+            // A doubled quote inside quotes is an escaped one, which
+            // toggling twice takes care of
+            '"' => is_quoted = !is_quoted,
+            c if c == delimiter && !is_quoted => {
+                fields.push(start..offset);
+                start = offset + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    fields.push(start..line.len());
+    fields
+}
+
+/// The index of the field at the byte `column` of `line`.
+pub fn field_at(line: &str, column: usize, delimiter: char) -> usize {
+    fields(line, delimiter)
+        .iter()
+        .position(|field| column <= field.end)
+        .unwrap_or(0)
+}
+
+/// Pads every field of `text` so that the columns line up, separated by
+/// [`SEPARATOR`].
+pub fn align(text: &str, delimiter: char) -> String {
+    let lines: Vec<Vec<&str>> = text
+        .split('\n')
+        .map(|line| {
+            fields(line, delimiter)
+                .into_iter()
+                .map(|field| &line[field])
+                .collect()
+        })
+        .collect();
+
+    let mut widths = Vec::new();
+
+    for fields in &lines {
+        for (index, field) in fields.iter().enumerate() {
+            let width = field.chars().count();
+
+            match widths.get_mut(index) {
+                Some(widest) if *widest < width => *widest = width,
+                Some(_) => {}
+                None => widths.push(width),
+            }
+        }
+    }
+
+    lines
+        .iter()
+        .map(|fields| {
+            let last = fields.len() - 1;
+
+            fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    // Trailing padding would only be noise
+                    if index == last {
+                        (*field).to_owned()
+                    } else {
+                        format!("{field:width$}", width = widths[index])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(SEPARATOR)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The byte ranges of the fields of an aligned line, padding included.
+pub fn aligned_fields(line: &str) -> Vec<Range<usize>> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+
+    for (offset, _) in line.match_indices(SEPARATOR) {
+        fields.push(start..offset);
+        start = offset + SEPARATOR.len();
+    }
+
+    fields.push(start..line.len());
+    fields
+}
+
+/// Maps a byte column of an aligned line to the same spot of its raw line.
+pub fn raw_column(aligned: &str, raw: &str, column: usize, delimiter: char) -> usize {
+    let aligned_fields = aligned_fields(aligned);
+    let index = aligned_fields
+        .iter()
+        .position(|field| column <= field.end)
+        .unwrap_or(0);
+
+    match fields(raw, delimiter).get(index) {
+        Some(field) => {
+            let offset = column.saturating_sub(aligned_fields[index].start);
+
+            field.start + offset.min(field.len())
+        }
+        None => raw.len(),
+    }
+}
@@ -0,0 +1,96 @@
+//! Cursor placement helpers.
+//!
+//! The `text_editor` widget only understands relative motions, so jumping to
+//! an absolute position means walking there one motion at a time.
+
+use iced::widget::text_editor::{Action, Content, Motion};
+
+/// Moves the cursor of `content` to the given line and byte column.
+pub fn move_to(content: &mut Content, line: usize, column: usize) {
+    travel(content, line, column, Action::Move);
+}
+
+/// Extends the selection of `content` to the given line and byte column.
+pub fn select_to(content: &mut Content, line: usize, column: usize) {
+    travel(content, line, column, Action::Select);
+}
+
+/// The byte column of the character at index `character` of `line`.
+pub fn column(line: &str, character: usize) -> usize {
+    line.char_indices()
+        .nth(character)
+        .map_or(line.len(), |(column, _)| column)
+}
+
+/// The line of `after` that line `line` of `before` most likely became,
+/// like once a formatter rewrote the file.
+///
+/// That is the nearest line with the same text, then the nearest with the
+/// same text once trimmed, as when only its indentation changed, and
+/// otherwise the line with the same number.
+pub fn follow_line(before: &str, after: &str, line: usize) -> usize {
+    let lines: Vec<&str> = after.split('\n').collect();
+    let last = lines.len() - 1;
+
+    let Some(text) = before.split('\n').nth(line) else {
+        return line.min(last);
+    };
+
+    let nearest = |matches: &dyn Fn(&str) -> bool| {
+        (0..lines.len())
+            .filter(|candidate| matches(lines[*candidate]))
+            .min_by_key(|candidate| candidate.abs_diff(line))
+    };
+
+    nearest(&|candidate| candidate == text)
+        .or_else(|| {
+            // Blank lines are everywhere, so they say nothing once trimmed
+            (!text.trim().is_empty())
+                .then(|| nearest(&|candidate| candidate.trim() == text.trim()))
+                .flatten()
+        })
+        .unwrap_or(line.min(last))
+}
+
+fn travel(content: &mut Content, line: usize, column: usize, action: fn(Motion) -> Action) {
+    let line = line.min(content.line_count().saturating_sub(1));
+    let column = content
+        .line(line)
+        .map(|text| floor_char_boundary(&text, column))
+        .unwrap_or(0);
+
+    step_while(content, action(Motion::Down), |(current, _)| current < line);
+    step_while(content, action(Motion::Up), |(current, _)| current > line);
+    step_while(content, action(Motion::Right), |(current, index)| {
+        current == line && index < column
+    });
+    step_while(content, action(Motion::Left), |(current, index)| {
+        current == line && index > column
+    });
+}
+
+fn step_while(content: &mut Content, action: Action, condition: impl Fn((usize, usize)) -> bool) {
+    let mut position = content.cursor_position();
+
+    while condition(position) {
+        content.edit(action.clone());
+
+        let next = content.cursor_position();
+
+        if next == position {
+            break;
+        }
+
+        position = next;
+    }
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
@@ -0,0 +1,168 @@
+//! Compiler diagnostics from `cargo check`.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use iced::{subscription, Subscription};
+use serde::Deserialize;
+
+use crate::EditorError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// The zero-based line of the primary span.
+    pub line: usize,
+    /// The zero-based character columns of the primary span on that line.
+    pub columns: (usize, usize),
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    /// Like `src/main.rs:3:5: error: message`, counting from 1.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+
+        write!(
+            f,
+            "{}:{}:{}: {level}: {}",
+            self.path.display(),
+            self.line + 1,
+            self.columns.0 + 1,
+            self.message
+        )
+    }
+}
+
+/// The outcome of a `cargo check` run.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub generation: u64,
+    pub result: Result<Vec<Diagnostic>, EditorError>,
+}
+
+/// The directory of the closest `Cargo.toml` above `path`.
+pub fn project_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|directory| directory.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Runs `cargo check` in `root`.
+///
+/// Dropping the subscription, e.g. because a newer `generation` replaced
+/// it, kills the running process.
+pub fn check(generation: u64, root: PathBuf) -> Subscription<Report> {
+    enum State {
+        Ready(PathBuf),
+        Finished,
+    }
+
+    subscription::unfold(
+        ("cargo-check", generation),
+        State::Ready(root),
+        move |state| async move {
+            match state {
+                State::Ready(root) => (
+                    Report {
+                        generation,
+                        result: run(&root).await,
+                    },
+                    State::Finished,
+                ),
+                State::Finished => iced::futures::future::pending().await,
+            }
+        },
+    )
+}
+
+async fn run(root: &Path) -> Result<Vec<Diagnostic>, EditorError> {
+    let output = tokio::process::Command::new("cargo")
+        .args(["check", "--message-format=json"])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|error| EditorError::IO(error.kind()))?;
+
+    let mut diagnostics: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|message| message.reason == "compiler-message")
+        .filter_map(|message| diagnostic(root, message.message?))
+        .collect();
+
+    diagnostics.sort();
+    diagnostics.dedup();
+
+    Ok(diagnostics)
+}
+
+fn diagnostic(root: &Path, message: CompilerMessage) -> Option<Diagnostic> {
+    let level = match message.level.as_str() {
+        "error" => Level::Error,
+        "warning" => Level::Warning,
+        _ => return None,
+    };
+
+    let span = message.spans.into_iter().find(|span| span.is_primary)?;
+
+    // Paths are relative to the workspace root, which may be further up
+    let path = root
+        .ancestors()
+        .map(|directory| directory.join(&span.file_name))
+        .find(|path| path.is_file())?;
+
+    let end = if span.line_end > span.line_start {
+        usize::MAX
+    } else {
+        span.column_end.saturating_sub(1)
+    };
+
+    Some(Diagnostic {
+        path: path.canonicalize().unwrap_or(path),
+        line: span.line_start.saturating_sub(1),
+        columns: (span.column_start.saturating_sub(1), end),
+        level,
+        message: message.message,
+    })
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: PathBuf,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+}
@@ -0,0 +1,144 @@
+//! Stubs of doc comments for Rust functions, from their signatures.
+
+/// The lines of the doc comment of the function whose signature starts at
+/// `line` of `lines`, indented like it, and the line to put them before.
+///
+/// The comment goes above the attributes of the function, and is `None`
+/// when there is no `fn` on the line.
+pub fn stub(lines: &[&str], line: usize) -> Option<(usize, Vec<String>)> {
+    let first = lines.get(line)?;
+
+    if !is_function(first) {
+        return None;
+    }
+
+    let signature: String = lines[line..]
+        .iter()
+        .scan(false, |is_done, line| {
+            if *is_done {
+                return None;
+            }
+
+            *is_done = line.contains(['{', ';']);
+            Some(*line)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let (arguments, returns) = parse(&signature)?;
+
+    let indentation = crate::indent::of(first);
+    let above = lines[..line]
+        .iter()
+        .rev()
+        .take_while(|line| line.trim_start().starts_with("#["))
+        .count();
+
+    let mut comment = vec![format!("{indentation}/// ")];
+
+    if !arguments.is_empty() {
+        comment.push(format!("{indentation}///"));
+        comment.push(format!("{indentation}/// # Arguments"));
+        comment.push(format!("{indentation}///"));
+
+        for argument in arguments {
+            comment.push(format!("{indentation}/// * `{argument}` - "));
+        }
+    }
+
+    if returns {
+        comment.push(format!("{indentation}///"));
+        comment.push(format!("{indentation}/// # Returns"));
+        comment.push(format!("{indentation}///"));
+        comment.push(format!("{indentation}/// "));
+    }
+
+    Some((line - above, comment))
+}
+
+/// Whether `line` starts a function, after its qualifiers.
+fn is_function(line: &str) -> bool {
+    line.split_whitespace()
+        .skip_while(|word| {
+            word.starts_with("pub")
+                || matches!(*word, "const" | "async" | "unsafe" | "extern" | "default")
+                || word.starts_with('"')
+        })
+        .next()
+        .is_some_and(|word| word == "fn")
+}
+
+/// The names of the arguments of a signature, leaving out `self`, and
+/// whether it returns anything.
+fn parse(signature: &str) -> Option<(Vec<String>, bool)> {
+    let after_name = &signature[signature.find("fn ")? + 3..];
+    let mut open = after_name.find(['<', '('])?;
+
+    // Generics may hold parentheses themselves, like `F: Fn(u8)`
+    if after_name[open..].starts_with('<') {
+        open = closing(after_name, open)? + 1;
+        open += after_name[open..].find('(')?;
+    }
+
+    let close = closing(after_name, open)?;
+    let arguments = split(&after_name[open + 1..close])
+        .into_iter()
+        .filter_map(|argument| {
+            let (pattern, _) = argument.split_once(':')?;
+            let pattern = pattern.trim();
+            let name = pattern.strip_prefix("mut ").unwrap_or(pattern).trim();
+
+            (name != "self").then(|| name.to_owned())
+        })
+        .collect();
+
+    let rest = after_name[close + 1..].trim_start();
+    let returns = rest.starts_with("->") && !rest[2..].trim_start().starts_with("()");
+
+    Some((arguments, returns))
+}
+
+/// The byte of `text` with the bracket closing the one at `open`.
+fn closing(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+
+    for (offset, c) in text[open..].char_indices() {
+        match c {
+            '(' | '[' | '<' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            // Not the arrow of a return type
+            '>' if !text[..open + offset].ends_with('-') => depth -= 1,
+            _ => continue,
+        }
+
+        if depth == 0 {
+            return Some(open + offset);
+        }
+    }
+
+    None
+}
+
+/// The arguments of a list, split at the commas outside of brackets.
+fn split(list: &str) -> Vec<&str> {
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (offset, c) in list.char_indices() {
+        match c {
+            '(' | '[' | '<' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '>' if !list[..offset].ends_with('-') => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(&list[start..offset]);
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+
+    arguments.push(&list[start..]);
+    arguments.retain(|argument| !argument.trim().is_empty());
+    arguments
+}
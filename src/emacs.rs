@@ -0,0 +1,143 @@
+//! Emacs keybindings, translated into the actions and messages of the editor.
+
+use std::sync::Arc;
+
+use iced::keyboard::{KeyCode, Modifiers};
+use iced::widget::text_editor::{Action, Content, Edit, Motion};
+
+use crate::{Message, Output};
+
+#[derive(Debug, Default)]
+pub struct State {
+    /// Whether `C-x` was pressed and waits for the rest of the command.
+    prefix: bool,
+    /// The text that `C-y` yanks back.
+    kill: String,
+    /// Where the cursor was left by the last kill, so that a kill from
+    /// the same spot adds to it instead of replacing it.
+    last_kill: Option<(usize, usize)>,
+}
+
+impl State {
+    pub fn is_waiting(&self) -> bool {
+        self.prefix
+    }
+
+    /// Handles a key pressed with Control or Alt.
+    ///
+    /// Returns `None` if the key is not bound in Emacs.
+    pub fn key(
+        &mut self,
+        key_code: KeyCode,
+        modifiers: Modifiers,
+        content: &Content,
+    ) -> Option<Vec<Output>> {
+        if is_modifier(key_code) {
+            return Some(Vec::new());
+        }
+
+        if std::mem::take(&mut self.prefix) {
+            return match key_code {
+                KeyCode::S if modifiers.control() => Some(vec![Output::Message(Message::Save)]),
+                KeyCode::F if modifiers.control() => Some(vec![Output::Message(Message::Open)]),
+                _ => Some(Vec::new()),
+            };
+        }
+
+        let outputs = if modifiers.alt() {
+            match key_code {
+                KeyCode::F => vec![motion(Motion::WordRight)],
+                KeyCode::B => vec![motion(Motion::WordLeft)],
+                KeyCode::Comma if modifiers.shift() => vec![motion(Motion::DocumentStart)],
+                KeyCode::Period if modifiers.shift() => vec![motion(Motion::DocumentEnd)],
+                _ => return None,
+            }
+        } else {
+            match key_code {
+                KeyCode::A => vec![motion(Motion::Home)],
+                KeyCode::E => vec![motion(Motion::End)],
+                KeyCode::F => vec![motion(Motion::Right)],
+                KeyCode::B => vec![motion(Motion::Left)],
+                KeyCode::N => vec![motion(Motion::Down)],
+                KeyCode::P => vec![motion(Motion::Up)],
+                KeyCode::D => vec![edit(Edit::Delete)],
+                KeyCode::K => self.kill_line(content),
+                KeyCode::Y => self.yank(),
+                KeyCode::S => vec![Output::Message(Message::OpenFind)],
+                KeyCode::G => vec![Output::Message(Message::Escape)],
+                KeyCode::X => {
+                    self.prefix = true;
+                    Vec::new()
+                }
+                _ => return None,
+            }
+        };
+
+        Some(outputs)
+    }
+
+    /// Kills the rest of the line, or the line break if there is no rest.
+    fn kill_line(&mut self, content: &Content) -> Vec<Output> {
+        let position = content.cursor_position();
+        let (line, column) = position;
+
+        let rest = content
+            .line(line)
+            .and_then(|text| text.get(column..).map(str::to_owned))
+            .unwrap_or_default();
+
+        if self.last_kill != Some(position) {
+            self.kill.clear();
+        }
+
+        self.last_kill = Some(position);
+
+        if rest.is_empty() {
+            if line + 1 >= content.line_count() {
+                return Vec::new();
+            }
+
+            self.kill.push('\n');
+            vec![edit(Edit::Delete)]
+        } else {
+            self.kill.push_str(&rest);
+            vec![select(Motion::End), edit(Edit::Delete)]
+        }
+    }
+
+    fn yank(&mut self) -> Vec<Output> {
+        self.last_kill = None;
+
+        if self.kill.is_empty() {
+            Vec::new()
+        } else {
+            vec![edit(Edit::Paste(Arc::new(self.kill.clone())))]
+        }
+    }
+}
+
+fn is_modifier(key_code: KeyCode) -> bool {
+    matches!(
+        key_code,
+        KeyCode::LControl
+            | KeyCode::RControl
+            | KeyCode::LAlt
+            | KeyCode::RAlt
+            | KeyCode::LShift
+            | KeyCode::RShift
+            | KeyCode::LWin
+            | KeyCode::RWin
+    )
+}
+
+fn motion(motion: Motion) -> Output {
+    Output::Action(Action::Move(motion))
+}
+
+fn select(motion: Motion) -> Output {
+    Output::Action(Action::Select(motion))
+}
+
+fn edit(edit: Edit) -> Output {
+    Output::Action(Action::Edit(edit))
+}
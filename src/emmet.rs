@@ -0,0 +1,172 @@
+//! Expansion of Emmet abbreviations into markup.
+//!
+//! Only a subset is understood: tag names, `#id`, `.class`, children
+//! with `>`, siblings with `+` and repetition with `*`.
+
+const INDENTATION: &str = "    ";
+
+/// Elements that have no closing tag in HTML.
+pub const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+    "track", "wbr",
+];
+
+pub fn is_markup(extension: &str) -> bool {
+    matches!(extension, "html" | "htm" | "xhtml" | "xml" | "svg")
+}
+
+/// Whether `c` can be part of an abbreviation.
+pub fn is_abbreviation(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '#' | '.' | '>' | '+' | '*')
+}
+
+/// Expands `abbreviation`, indenting every line after the first one by
+/// `indentation`.
+pub fn expand(abbreviation: &str, indentation: &str, is_html: bool) -> Option<String> {
+    let mut parser = Parser {
+        input: abbreviation,
+        position: 0,
+    };
+
+    let elements = parser.sequence()?;
+
+    if parser.position < abbreviation.len() {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+
+    for element in &elements {
+        render(element, 0, is_html, &mut lines);
+    }
+
+    Some(lines.join(&format!("\n{indentation}")))
+}
+
+#[derive(Debug)]
+struct Element {
+    tag: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    count: usize,
+    children: Vec<Element>,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Parses elements separated by `+`, each with its `>` children.
+    ///
+    /// A child sequence runs to the end, so `a>b+c` nests both `b` and
+    /// `c` inside `a`.
+    fn sequence(&mut self) -> Option<Vec<Element>> {
+        let mut elements = Vec::new();
+
+        loop {
+            let mut element = self.element()?;
+
+            match self.peek() {
+                Some('>') => {
+                    self.position += 1;
+                    element.children = self.sequence()?;
+                    elements.push(element);
+
+                    return Some(elements);
+                }
+                Some('+') => {
+                    self.position += 1;
+                    elements.push(element);
+                }
+                _ => {
+                    elements.push(element);
+
+                    return Some(elements);
+                }
+            }
+        }
+    }
+
+    fn element(&mut self) -> Option<Element> {
+        let tag = self.name();
+        let mut is_named = !tag.is_empty();
+
+        let mut element = Element {
+            tag: if is_named { tag } else { "div".to_owned() },
+            id: None,
+            classes: Vec::new(),
+            count: 1,
+            children: Vec::new(),
+        };
+
+        while let Some(c) = self.peek() {
+            match c {
+                '#' | '.' => {
+                    self.position += 1;
+                    let name = Some(self.name()).filter(|name| !name.is_empty())?;
+
+                    if c == '#' {
+                        element.id = Some(name);
+                    } else {
+                        element.classes.push(name);
+                    }
+
+                    is_named = true;
+                }
+                '*' => {
+                    self.position += 1;
+                    element.count = self.name().parse().ok().filter(|count| *count > 0)?;
+                }
+                _ => break,
+            }
+        }
+
+        is_named.then_some(element)
+    }
+
+    fn name(&mut self) -> String {
+        let rest = &self.input[self.position..];
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | ':')))
+            .unwrap_or(rest.len());
+
+        self.position += length;
+        rest[..length].to_owned()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+}
+
+fn render(element: &Element, depth: usize, is_html: bool, lines: &mut Vec<String>) {
+    let indentation = INDENTATION.repeat(depth);
+
+    let mut open = format!("<{}", element.tag);
+
+    if let Some(id) = &element.id {
+        open.push_str(&format!(" id=\"{id}\""));
+    }
+
+    if !element.classes.is_empty() {
+        open.push_str(&format!(" class=\"{}\"", element.classes.join(" ")));
+    }
+
+    for _ in 0..element.count {
+        if is_html && VOID.contains(&element.tag.as_str()) {
+            lines.push(format!("{indentation}{open}>"));
+        } else if element.children.is_empty() {
+            lines.push(format!("{indentation}{open}></{}>", element.tag));
+        } else {
+            lines.push(format!("{indentation}{open}>"));
+
+            for child in &element.children {
+                render(child, depth + 1, is_html, lines);
+            }
+
+            lines.push(format!("{indentation}</{}>", element.tag));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! How the text of a file is stored as bytes: its character encoding and
+//! its line endings.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding(&'static encoding_rs::Encoding);
+
+/// The encodings offered to reopen and save files with.
+///
+/// The encodings of `encoding_rs` are statics, which constants cannot hold.
+pub static ALL: [Encoding; 13] = [
+    Encoding(encoding_rs::UTF_8),
+    Encoding(encoding_rs::UTF_16LE),
+    Encoding(encoding_rs::UTF_16BE),
+    Encoding(encoding_rs::WINDOWS_1252),
+    Encoding(encoding_rs::ISO_8859_2),
+    Encoding(encoding_rs::ISO_8859_15),
+    Encoding(encoding_rs::KOI8_R),
+    Encoding(encoding_rs::WINDOWS_1251),
+    Encoding(encoding_rs::SHIFT_JIS),
+    Encoding(encoding_rs::EUC_JP),
+    Encoding(encoding_rs::GBK),
+    Encoding(encoding_rs::BIG5),
+    Encoding(encoding_rs::EUC_KR),
+];
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self(encoding_rs::UTF_8)
+    }
+}
+
+impl Encoding {
+    /// The encoding announced by the byte order mark `bytes` start with.
+    pub fn from_bom(bytes: &[u8]) -> Option<Self> {
+        encoding_rs::Encoding::for_bom(bytes).map(|(encoding, _)| Self(encoding))
+    }
+
+    /// Whether files in the encoding may start with a byte order mark.
+    pub fn has_bom(self) -> bool {
+        self.is_utf_16() || self.0 == encoding_rs::UTF_8
+    }
+
+    /// Whether the encoding is UTF-16, which readers can only tell by the
+    /// byte order mark.
+    pub fn is_utf_16(self) -> bool {
+        self.0 == encoding_rs::UTF_16LE || self.0 == encoding_rs::UTF_16BE
+    }
+
+    /// Whether `bytes` start with the byte order mark of the encoding.
+    pub fn starts(self, bytes: &[u8]) -> bool {
+        Self::from_bom(bytes) == Some(self)
+    }
+
+    /// Decodes `bytes`, without a byte order mark, refusing to lose any.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        let (text, had_errors) = self.0.decode_with_bom_removal(bytes);
+
+        if had_errors {
+            Err(format!("The file is not valid {self}"))
+        } else {
+            Ok(text.into_owned())
+        }
+    }
+
+    /// Decodes `bytes`, without a byte order mark, with U+FFFD in place of
+    /// what is not valid in the encoding.
+    pub fn decode_lossy(self, bytes: &[u8]) -> String {
+        self.0.decode_with_bom_removal(bytes).0.into_owned()
+    }
+
+    /// Encodes `text`, after a byte order mark if `bom` and the encoding has
+    /// one, or explains which characters the encoding lacks.
+    pub fn encode(self, text: &str, bom: bool) -> Result<Vec<u8>, String> {
+        let bom = bom && self.has_bom();
+
+        // The encoders of the web only ever produce UTF-8 for UTF-16
+        let utf_16 = |to_bytes: fn(u16) -> [u8; 2]| -> Vec<u8> {
+            bom.then_some('\u{FEFF}')
+                .into_iter()
+                .chain(text.chars())
+                .collect::<String>()
+                .encode_utf16()
+                .flat_map(to_bytes)
+                .collect()
+        };
+
+        if self.0 == encoding_rs::UTF_16LE {
+            return Ok(utf_16(u16::to_le_bytes));
+        }
+
+        if self.0 == encoding_rs::UTF_16BE {
+            return Ok(utf_16(u16::to_be_bytes));
+        }
+
+        let (bytes, _, had_errors) = self.0.encode(text);
+
+        if had_errors {
+            let missing = text
+                .chars()
+                .find(|c| self.0.encode(c.encode_utf8(&mut [0; 4])).2)
+                .unwrap_or_default();
+
+            Err(format!("Not saved: {missing:?} cannot be written in {self}"))
+        } else if bom {
+            Ok([&[0xEF, 0xBB, 0xBF][..], &bytes].concat())
+        } else {
+            Ok(bytes.into_owned())
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub const ALL: &'static [Self] = &[Self::Lf, Self::Crlf];
+
+    /// The line ending most lines of `text` end with.
+    pub fn detect(text: &str) -> Self {
+        let lines = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+
+        if lines > 0 && crlf * 2 >= lines {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    /// Ends every line of `text`, whose lines end with `\n`, with this.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Self::Lf => text.to_owned(),
+            Self::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        })
+    }
+}
+
+/// Ends every line of `text` with `\n`, the way the editor keeps it.
+pub fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
@@ -0,0 +1,203 @@
+//! Finding text in the open document, and the queries found before.
+
+use std::{borrow::Cow, collections::VecDeque, ops::Range};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// The most queries remembered.
+const HISTORY_CAPACITY: usize = 50;
+
+#[derive(Debug, Default)]
+pub struct State {
+    pub query: String,
+    pub options: Options,
+    /// The occurrences of the query in the displayed text.
+    pub matches: Vec<Match>,
+    /// Why the query is not a valid regular expression, if it is not.
+    pub error: Option<String>,
+}
+
+/// How the query of the find bar matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Options {
+    pub match_case: bool,
+    /// Whether it only matches whole words.
+    pub whole_word: bool,
+    /// Whether it is a regular expression instead of plain text.
+    pub regex: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            match_case: true,
+            whole_word: false,
+            regex: false,
+        }
+    }
+}
+
+impl Options {
+    fn regex(self, query: &str) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            Cow::Borrowed(query)
+        } else {
+            Cow::Owned(regex::escape(query))
+        };
+
+        let pattern = if self.whole_word {
+            Cow::Owned(format!(r"\b(?:{pattern})\b"))
+        } else {
+            pattern
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.match_case)
+            .build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub line: usize,
+    /// The bytes of the line that matched.
+    pub range: Range<usize>,
+}
+
+impl State {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn search(&mut self, text: &str) {
+        self.error = None;
+        self.matches = Vec::new();
+
+        if self.query.is_empty() {
+            return;
+        }
+
+        match self.options.regex(&self.query) {
+            Ok(regex) => self.matches = matches(text, &regex),
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+
+    /// The match the cursor is on, if any.
+    pub fn current(&self, (line, column): (usize, usize)) -> Option<usize> {
+        self.matches.iter().position(|found| {
+            found.line == line && found.range.start <= column && column <= found.range.end
+        })
+    }
+
+    /// The first match after the cursor, wrapping around the end.
+    pub fn next(&self, (line, column): (usize, usize)) -> Option<&Match> {
+        self.matches
+            .iter()
+            .find(|found| (found.line, found.range.start) >= (line, column))
+            .or_else(|| self.matches.first())
+    }
+
+    /// The last match before the cursor, wrapping around the start.
+    pub fn previous(&self, (line, column): (usize, usize)) -> Option<&Match> {
+        self.matches
+            .iter()
+            .rfind(|found| (found.line, found.range.end) < (line, column))
+            .or_else(|| self.matches.last())
+    }
+}
+
+fn matches(text: &str, regex: &Regex) -> Vec<Match> {
+    text.split('\n')
+        .enumerate()
+        .flat_map(|(line, content)| {
+            regex
+                .find_iter(content)
+                .filter(|found| !found.is_empty())
+                .map(move |found| Match {
+                    line,
+                    range: found.range(),
+                })
+        })
+        .collect()
+}
+
+/// The queries used before, the latest first, browsed like a shell history.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: VecDeque<String>,
+    /// The entry being shown, if browsing.
+    position: Option<usize>,
+    /// What was typed before browsing, shown again past the latest entry.
+    draft: String,
+}
+
+impl History {
+    pub fn new(entries: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            entries: entries.into_iter().take(HISTORY_CAPACITY).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Remembers `query` as the latest one, returning whether the history
+    /// changed.
+    pub fn record(&mut self, query: &str) -> bool {
+        self.position = None;
+
+        if query.is_empty() || self.entries.front().is_some_and(|latest| latest == query) {
+            return false;
+        }
+
+        self.entries.retain(|entry| entry != query);
+        self.entries.push_front(query.to_owned());
+        self.entries.truncate(HISTORY_CAPACITY);
+
+        true
+    }
+
+    /// The entry before the one shown, keeping `current` to come back to
+    /// when starting to browse.
+    pub fn older(&mut self, current: &str) -> Option<String> {
+        let position = match self.position {
+            Some(position) => position + 1,
+            None => {
+                self.draft = current.to_owned();
+                0
+            }
+        };
+
+        let entry = self.entries.get(position)?.clone();
+        self.position = Some(position);
+
+        Some(entry)
+    }
+
+    /// The entry after the one shown, or what was typed past the latest.
+    pub fn newer(&mut self) -> Option<String> {
+        match self.position? {
+            0 => {
+                self.position = None;
+                Some(std::mem::take(&mut self.draft))
+            }
+            position => {
+                self.position = Some(position - 1);
+                self.entries.get(position - 1).cloned()
+            }
+        }
+    }
+
+    /// Stops browsing, as when the query is typed over.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+}
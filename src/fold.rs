@@ -0,0 +1,300 @@
+//! Foldable regions and the lines that folding them hides.
+//!
+//! Folded lines are taken out of the displayed buffer entirely and kept
+//! here, anchored to the line that stays visible, until they are unfolded.
+
+use std::ops::RangeInclusive;
+
+/// A block of lines that collapses into its first one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// The line that stays visible.
+    pub start: usize,
+    /// The last line that gets hidden.
+    pub end: usize,
+}
+
+impl Region {
+    pub fn contains(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+/// Computes the foldable regions of `text`, ordered by their first line.
+///
+/// Whitespace-sensitive languages fold by indentation; everything else
+/// folds by bracket pairs.
+pub fn regions(text: &str, extension: &str) -> Vec<Region> {
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    let mut regions = if uses_indentation(extension) {
+        by_indentation(&lines)
+    } else {
+        by_brackets(&lines)
+    };
+
+    regions.sort_by_key(|region| (region.start, std::cmp::Reverse(region.end)));
+    regions.dedup_by_key(|region| region.start);
+    regions
+}
+
+/// The regions that are not nested inside any other one.
+pub fn outermost(regions: &[Region]) -> Vec<Region> {
+    let mut outermost: Vec<Region> = Vec::new();
+
+    for region in regions {
+        if outermost.last().is_none_or(|last| region.start > last.end) {
+            outermost.push(*region);
+        }
+    }
+
+    outermost
+}
+
+fn uses_indentation(extension: &str) -> bool {
+    matches!(extension, "py" | "pyw" | "yaml" | "yml" | "nim" | "coffee")
+}
+
+fn by_brackets(lines: &[&str]) -> Vec<Region> {
+    let mut open = Vec::new();
+    let mut regions = Vec::new();
+
+    for (number, line) in lines.iter().enumerate() {
+        for (_, bracket) in brackets(line) {
+            match bracket {
+                '{' | '[' | '(' => open.push(number),
+                _ => {
+                    if let Some(start) = open.pop() {
+                        if number > start + 1 {
+                            regions.push(Region {
+                                start,
+                                end: number - 1,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    regions
+}
+
+/// The brackets of a line with their byte offsets, skipping string
+/// literals and line comments.
+pub fn brackets(line: &str) -> Vec<(usize, char)> {
+    let mut brackets = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    let mut in_string = false;
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                let _ = chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek().map(|(_, c)| *c) == Some('/') => break,
+            '{' | '}' | '[' | ']' | '(' | ')' if !in_string => brackets.push((offset, c)),
+            _ => {}
+        }
+    }
+
+    brackets
+}
+
+fn by_indentation(lines: &[&str]) -> Vec<Region> {
+    let levels: Vec<Option<usize>> = lines
+        .iter()
+        .map(|line| (!line.trim().is_empty()).then(|| indentation(line)))
+        .collect();
+
+    let mut regions = Vec::new();
+
+    for (start, level) in levels.iter().enumerate() {
+        let Some(level) = level else {
+            continue;
+        };
+
+        let mut end = start;
+
+        for (number, other) in levels.iter().enumerate().skip(start + 1) {
+            match other {
+                Some(other) if other <= level => break,
+                Some(_) => end = number,
+                None => {}
+            }
+        }
+
+        if end > start {
+            regions.push(Region { start, end });
+        }
+    }
+
+    regions
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// The folded regions of a document.
+#[derive(Debug, Default)]
+pub struct Folds {
+    folded: Vec<Fold>,
+}
+
+#[derive(Debug)]
+struct Fold {
+    line: usize,
+    hidden: Vec<String>,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.folded.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.folded.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.folded.clear();
+    }
+
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.position(line).is_some()
+    }
+
+    /// The displayed lines in `lines` that have folded lines below them.
+    pub fn headers(&self, lines: RangeInclusive<usize>) -> Vec<usize> {
+        self.folded
+            .iter()
+            .map(|fold| fold.line)
+            .filter(|line| lines.contains(line))
+            .collect()
+    }
+
+    /// Hides the lines of `region` from the displayed `text`.
+    ///
+    /// Folds already inside the region are merged into the new one.
+    pub fn fold(&mut self, text: &str, region: Region) -> String {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let end = region.end.min(lines.len().saturating_sub(1));
+
+        if end <= region.start {
+            return text.to_owned();
+        }
+
+        let mut hidden = Vec::new();
+
+        for (number, line) in lines.iter().enumerate().take(end + 1).skip(region.start + 1) {
+            hidden.push((*line).to_owned());
+
+            if let Some(index) = self.position(number) {
+                hidden.extend(self.folded.remove(index).hidden);
+            }
+        }
+
+        if let Some(index) = self.position(region.start) {
+            let mut inner = self.folded.remove(index).hidden;
+            inner.append(&mut hidden);
+            hidden = inner;
+        }
+
+        self.shift(end, -((end - region.start) as isize));
+        self.folded.push(Fold {
+            line: region.start,
+            hidden,
+        });
+        self.folded.sort_by_key(|fold| fold.line);
+
+        let mut visible = lines[..=region.start].to_vec();
+        visible.extend_from_slice(&lines[end + 1..]);
+        visible.join("\n")
+    }
+
+    /// Shows the lines folded below `line` again.
+    ///
+    /// Returns the new displayed text and how many lines were revealed.
+    pub fn unfold(&mut self, text: &str, line: usize) -> (String, usize) {
+        let Some(index) = self.position(line) else {
+            return (text.to_owned(), 0);
+        };
+
+        let fold = self.folded.remove(index);
+        let revealed = fold.hidden.len();
+
+        self.shift(line, revealed as isize);
+
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        let at = (line + 1).min(lines.len());
+        lines.splice(at..at, fold.hidden.iter().map(String::as_str));
+
+        (lines.join("\n"), revealed)
+    }
+
+    /// Reconstructs the full text of the document from the displayed `text`.
+    pub fn expand(&self, text: &str) -> String {
+        if self.folded.is_empty() {
+            return text.to_owned();
+        }
+
+        let mut lines = Vec::new();
+
+        for (number, line) in text.split('\n').enumerate() {
+            lines.push(line);
+
+            if let Some(index) = self.position(number) {
+                lines.extend(self.folded[index].hidden.iter().map(String::as_str));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// Maps a displayed line to its line in the full text.
+    pub fn real_line(&self, line: usize) -> usize {
+        line + self
+            .folded
+            .iter()
+            .take_while(|fold| fold.line < line)
+            .map(|fold| fold.hidden.len())
+            .sum::<usize>()
+    }
+
+    /// Maps a line of the full text to its displayed line, if it is visible.
+    pub fn display_line(&self, line: usize) -> Option<usize> {
+        let mut hidden = 0;
+
+        for fold in &self.folded {
+            let header = fold.line + hidden;
+
+            if line <= header {
+                break;
+            }
+
+            if line <= header + fold.hidden.len() {
+                return None;
+            }
+
+            hidden += fold.hidden.len();
+        }
+
+        Some(line - hidden)
+    }
+
+    /// Moves the folds below `line` by `delta` displayed lines.
+    pub fn shift(&mut self, line: usize, delta: isize) {
+        for fold in self.folded.iter_mut().filter(|fold| fold.line > line) {
+            fold.line = fold.line.saturating_add_signed(delta);
+        }
+    }
+
+    fn position(&self, line: usize) -> Option<usize> {
+        self.folded.iter().position(|fold| fold.line == line)
+    }
+}
@@ -0,0 +1,83 @@
+//! The monospace fonts installed on the system.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use font_kit::{family_name::FamilyName, properties::Properties, source::SystemSource};
+use iced::Font;
+
+/// The names of the families picked so far, which fonts refer to forever.
+static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// The data of the regular face of `family`, if it is installed.
+pub fn data(family: &str) -> Option<Vec<u8>> {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::Title(family.to_owned())], &Properties::new())
+        .ok()?
+        .load()
+        .ok()?;
+
+    // The best match may well be some other family
+    if font.family_name() != family {
+        return None;
+    }
+
+    font.copy_font_data().map(|data| data.to_vec())
+}
+
+pub async fn load(family: String) -> Option<Vec<u8>> {
+    tokio::task::spawn_blocking(move || data(&family))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// The families whose regular face is monospaced, sorted by name.
+pub async fn monospace() -> Vec<String> {
+    tokio::task::spawn_blocking(|| {
+        let source = SystemSource::new();
+
+        let mut families: Vec<String> = source
+            .all_families()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|family| {
+                source
+                    .select_family_by_name(family)
+                    .ok()
+                    .and_then(|handle| handle.fonts().first()?.load().ok())
+                    .is_some_and(|font| font.is_monospace())
+            })
+            .collect();
+
+        families.sort();
+        families.dedup();
+        families
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// The font of `family`, or the default monospace one.
+pub fn font(family: Option<&str>) -> Font {
+    match family {
+        Some(family) => Font::with_name(name(family)),
+        None => Font::MONOSPACE,
+    }
+}
+
+/// The name of `family` that lives forever, as font names have to, leaked
+/// the first time the family is picked only.
+fn name(family: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match names.get(family) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(family.to_owned().into_boxed_str());
+            names.insert(name);
+
+            name
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Highlighting with tree-sitter, which parses the whole document instead
+//! of matching each line on its own, for the languages it has a grammar
+//! of.
+//!
+//! The document is parsed once and then again from the tree of the last
+//! parse as it is edited, and only the lines the edit could have changed
+//! are highlighted again.
+
+use std::ops::Range;
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::{Arc, OnceLock};
+
+use iced::Color;
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, QueryCursor, Tree};
+
+use crate::themes::Palette;
+
+/// The extensions of the languages with a grammar.
+pub const EXTENSIONS: [&str; 4] = ["rs", "js", "ts", "py"];
+
+/// The captures of the queries that get a color, each standing for the
+/// ones named after it, like `function.macro` for `function`.
+const CAPTURES: [&str; 13] = [
+    "attribute",
+    "comment",
+    "constant",
+    "constructor",
+    "escape",
+    "function",
+    "keyword",
+    "label",
+    "number",
+    "operator",
+    "string",
+    "type",
+    "variable.builtin",
+];
+
+/// A language and its highlighting queries, with the capture each query
+/// capture is colored as.
+struct Grammar {
+    language: Language,
+    query: Query,
+    captures: Vec<Option<usize>>,
+}
+
+/// Compiling the queries of a grammar takes a while, so it happens once,
+/// the first time a document in its language is highlighted.
+#[allow(clippy::declare_interior_mutable_const)]
+const UNCOMPILED: OnceLock<Option<Grammar>> = OnceLock::new();
+static GRAMMARS: [OnceLock<Option<Grammar>>; EXTENSIONS.len()] = [UNCOMPILED; EXTENSIONS.len()];
+
+/// The version the next spans get, so that two never share one.
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+/// Whether tree-sitter has a grammar for files with `extension`.
+pub fn has_grammar(extension: &str) -> bool {
+    EXTENSIONS.contains(&extension)
+}
+
+/// The spans of each line of a document, as byte ranges and the capture
+/// they are colored as.
+///
+/// The lines an edit left alone are shared with the spans from before it.
+#[derive(Debug, Clone, Default)]
+pub struct Spans {
+    version: u64,
+    lines: Vec<Arc<[(Range<usize>, usize)]>>,
+}
+
+/// Spans never change once made, so telling their versions apart is
+/// enough.
+impl PartialEq for Spans {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+    }
+}
+
+impl Spans {
+    fn new(lines: Vec<Arc<[(Range<usize>, usize)]>>) -> Self {
+        Self {
+            version: NEXT_VERSION.fetch_add(1, atomic::Ordering::Relaxed),
+            lines,
+        }
+    }
+
+    /// The spans of line `number`.
+    pub fn line(&self, number: usize) -> &[(Range<usize>, usize)] {
+        self.lines.get(number).map_or(&[], |line| &line[..])
+    }
+
+    /// The first line whose spans are not the ones of `other`.
+    pub fn first_difference(&self, other: &Self) -> usize {
+        self.lines
+            .iter()
+            .zip(&other.lines)
+            .position(|(line, other)| !Arc::ptr_eq(line, other))
+            .unwrap_or(self.lines.len().min(other.lines.len()))
+    }
+}
+
+/// A document as tree-sitter parsed it, kept to parse it again from.
+pub struct Syntax {
+    extension: String,
+    parser: Parser,
+    tree: Option<Tree>,
+    text: String,
+    spans: Arc<Spans>,
+}
+
+impl Syntax {
+    /// Parses `text` as the language of `extension`, leaving it all
+    /// uncolored if there is no grammar for it or parsing fails.
+    pub fn new(text: String, extension: &str) -> Self {
+        let mut parser = Parser::new();
+        let grammar = grammar(extension);
+
+        let tree = grammar
+            .filter(|grammar| parser.set_language(grammar.language).is_ok())
+            .and_then(|_| parser.parse(&text, None));
+
+        let starts = starts(&text);
+        let lines = match (grammar, &tree) {
+            (Some(grammar), Some(tree)) => {
+                highlight(grammar, tree, &text, &starts, 0..starts.len())
+            }
+            _ => vec![Arc::from([]); starts.len()],
+        };
+
+        Self {
+            extension: extension.to_owned(),
+            parser,
+            tree,
+            text,
+            spans: Arc::new(Spans::new(lines)),
+        }
+    }
+
+    /// The extension of the language the document is parsed as.
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    /// The spans of the document as last parsed.
+    pub fn spans(&self) -> Arc<Spans> {
+        self.spans.clone()
+    }
+
+    /// Parses the document again as `text`, now that it was edited.
+    pub fn edit(&mut self, text: String) {
+        if text == self.text {
+            return;
+        }
+
+        let (Some(grammar), Some(mut tree)) = (grammar(&self.extension), self.tree.take()) else {
+            *self = Self::new(text, &self.extension.clone());
+            return;
+        };
+
+        let edit = input_edit(&self.text, &text);
+        tree.edit(&edit);
+
+        let Some(parsed) = self.parser.parse(&text, Some(&tree)) else {
+            *self = Self::new(text, &self.extension.clone());
+            return;
+        };
+
+        // The lines of the edit, and those of the nodes which it made
+        // another kind of node, like the ones after an opened comment
+        let (first, last) = tree.changed_ranges(&parsed).fold(
+            (edit.start_position.row, edit.new_end_position.row),
+            |(first, last), range| {
+                (
+                    first.min(range.start_point.row),
+                    last.max(range.end_point.row),
+                )
+            },
+        );
+
+        let starts = starts(&text);
+        let last = last.min(starts.len() - 1);
+        let first = first.min(last);
+        let delta = edit.new_end_position.row as isize - edit.old_end_position.row as isize;
+        let after = (last + 1).saturating_add_signed(-delta);
+
+        let mut lines = self.spans.lines[..first.min(self.spans.lines.len())].to_vec();
+        lines.extend(highlight(grammar, &parsed, &text, &starts, first..last + 1));
+        lines.extend(self.spans.lines.iter().skip(after).cloned());
+        lines.resize(starts.len(), Arc::from([]));
+
+        self.tree = Some(parsed);
+        self.text = text;
+        self.spans = Arc::new(Spans::new(lines));
+    }
+}
+
+/// The offset of each line of `text`.
+fn starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+        .collect()
+}
+
+/// The edit that turns `old` into `new`, as the bytes between what they
+/// start and end with alike.
+fn input_edit(old: &str, new: &str) -> InputEdit {
+    let mut start = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    while !old.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = old.len().min(new.len()) - start;
+    end = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(end)
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    while !old.is_char_boundary(old.len() - end) || !new.is_char_boundary(new.len() - end) {
+        end -= 1;
+    }
+
+    let point = |text: &str, offset: usize| {
+        let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+
+        Point {
+            row: text[..offset].matches('\n').count(),
+            column: offset - line_start,
+        }
+    };
+
+    InputEdit {
+        start_byte: start,
+        old_end_byte: old.len() - end,
+        new_end_byte: new.len() - end,
+        start_position: point(old, start),
+        old_end_position: point(old, old.len() - end),
+        new_end_position: point(new, new.len() - end),
+    }
+}
+
+/// The spans of the `lines` of `text`, which `starts` are the offsets of.
+fn highlight(
+    grammar: &Grammar,
+    tree: &Tree,
+    text: &str,
+    starts: &[usize],
+    lines: Range<usize>,
+) -> Vec<Arc<[(Range<usize>, usize)]>> {
+    let line_end = |number: usize| starts.get(number + 1).map_or(text.len(), |next| next - 1);
+
+    let mut spans = vec![Vec::new(); lines.len()];
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(starts[lines.start]..line_end(lines.end - 1));
+
+    let mut last = None;
+
+    for (found, index) in cursor.captures(&grammar.query, tree.root_node(), text.as_bytes()) {
+        let node = found.captures[index].node;
+
+        // Of the patterns that match a node, the one that comes first wins
+        if last == Some(node) {
+            continue;
+        }
+
+        last = Some(node);
+
+        let Some(capture) = grammar.captures[found.captures[index].index as usize] else {
+            continue;
+        };
+
+        let Range { start, end } = node.byte_range();
+
+        // A span may run over several lines, like a block comment
+        let first = starts.partition_point(|&line| line <= start) - 1;
+
+        for number in first.max(lines.start)..lines.end {
+            let line_start = starts[number];
+
+            if line_start >= end {
+                break;
+            }
+
+            let span = start.max(line_start)..end.min(line_end(number));
+
+            if !span.is_empty() {
+                spans[number - lines.start]
+                    .push((span.start - line_start..span.end - line_start, capture));
+            }
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|mut line| {
+            // Nodes inside others come after them, to be colored over them
+            line.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+
+            Arc::from(line)
+        })
+        .collect()
+}
+
+/// The color of the capture at `index` of the captures, in the roles of
+/// `palette`.
+pub fn color(palette: &Palette, index: usize) -> Color {
+    match CAPTURES.get(index).copied().unwrap_or_default() {
+        "comment" => palette.comment,
+        "keyword" | "operator" => palette.keyword,
+        "string" => palette.string,
+        "constant" | "number" => palette.number,
+        "type" | "constructor" => palette.kind,
+        "function" => palette.function,
+        "escape" => palette.escape,
+        "attribute" | "label" | "variable.builtin" => palette.variable,
+        _ => palette.text,
+    }
+}
+
+fn grammar(extension: &str) -> Option<&'static Grammar> {
+    let index = EXTENSIONS.iter().position(|known| *known == extension)?;
+
+    GRAMMARS[index]
+        .get_or_init(|| {
+            let (language, highlights) = match extension {
+                "rs" => (
+                    tree_sitter_rust::language(),
+                    tree_sitter_rust::HIGHLIGHT_QUERY.to_owned(),
+                ),
+                "js" => (
+                    tree_sitter_javascript::language(),
+                    tree_sitter_javascript::HIGHLIGHT_QUERY.to_owned(),
+                ),
+                // TypeScript only adds to the queries of JavaScript, and
+                // the patterns that come first win
+                "ts" => (
+                    tree_sitter_typescript::language_typescript(),
+                    [
+                        tree_sitter_typescript::HIGHLIGHT_QUERY,
+                        tree_sitter_javascript::HIGHLIGHT_QUERY,
+                    ]
+                    .join("\n"),
+                ),
+                _ => (
+                    tree_sitter_python::language(),
+                    tree_sitter_python::HIGHLIGHT_QUERY.to_owned(),
+                ),
+            };
+
+            let query = Query::new(language, &highlights).ok()?;
+
+            // Each is colored as the longest of the captures it is named
+            // after
+            let captures = query
+                .capture_names()
+                .iter()
+                .map(|name| {
+                    CAPTURES
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, capture)| {
+                            name.strip_prefix(*capture)
+                                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+                        })
+                        .max_by_key(|(_, capture)| capture.len())
+                        .map(|(index, _)| index)
+                })
+                .collect();
+
+            Some(Grammar {
+                language,
+                query,
+                captures,
+            })
+        })
+        .as_ref()
+}
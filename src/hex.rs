@@ -0,0 +1,51 @@
+//! Showing binary files as a hex dump.
+
+use std::fmt::Write;
+
+/// How many bytes each line of the dump shows.
+const WIDTH: usize = 16;
+
+/// How far into a file to look for NUL bytes, which text never has.
+const SNIFF_LENGTH: usize = 8000;
+
+/// Whether `bytes` look like a binary file rather than text.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_LENGTH)].contains(&0)
+}
+
+/// The offset, the bytes in hex and the printable ones of every line of
+/// `bytes`, like `hexdump -C`.
+pub fn dump(bytes: &[u8]) -> String {
+    let mut dump = String::with_capacity(bytes.len() / WIDTH * 80);
+
+    for (index, line) in bytes.chunks(WIDTH).enumerate() {
+        let _ = write!(dump, "{:08x}  ", index * WIDTH);
+
+        for position in 0..WIDTH {
+            match line.get(position) {
+                Some(byte) => {
+                    let _ = write!(dump, "{byte:02x} ");
+                }
+                None => dump.push_str("   "),
+            }
+
+            if position == WIDTH / 2 - 1 {
+                dump.push(' ');
+            }
+        }
+
+        dump.push_str(" |");
+        dump.extend(line.iter().map(|byte| {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                char::from(*byte)
+            } else {
+                '.'
+            }
+        }));
+        dump.push_str("|\n");
+    }
+
+    let _ = write!(dump, "{:08x}", bytes.len());
+
+    dump
+}
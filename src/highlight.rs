@@ -0,0 +1,395 @@
+//! Syntax highlighting with extra colored spans laid on top.
+//!
+//! The syntax is highlighted either by the highlighter of iced, one line
+//! at a time, or by tree-sitter for the languages picked for it. Either
+//! panicking on some input leaves the document plain instead of bringing
+//! the editor down.
+
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use iced::advanced::text::highlighter::{self, Format, Highlighter as _};
+use iced::highlighter as syntax;
+use iced::{Color, Font, Theme};
+
+use crate::{fold, grammar, themes};
+
+/// Colored spans of a line, as byte ranges.
+pub type Overlays = BTreeMap<usize, Vec<(Range<usize>, Color)>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub syntax: syntax::Settings,
+    /// What tree-sitter made of the document, to highlight it with instead.
+    pub spans: Option<Arc<grammar::Spans>>,
+    /// The colors that replace the ones of the syntax theme.
+    pub palette: Option<&'static themes::Palette>,
+    /// The colors of the tree-sitter highlighting, by role.
+    pub colors: &'static themes::Palette,
+    pub overlays: Overlays,
+}
+
+pub enum Highlight {
+    Syntax(syntax::Highlight),
+    Remapped(Format<Font>),
+    Overlay(Color),
+}
+
+pub fn to_format(highlight: &Highlight, _theme: &Theme) -> Format<Font> {
+    match highlight {
+        Highlight::Syntax(highlight) => highlight.to_format(),
+        Highlight::Remapped(format) => *format,
+        Highlight::Overlay(color) => Format {
+            color: Some(*color),
+            font: None,
+        },
+    }
+}
+
+/// What highlights the syntax of the lines of a document, which it is
+/// handed in order.
+pub trait Backend {
+    fn update(&mut self, settings: &Settings);
+
+    /// Starts over from line `line`, as when it changed.
+    fn change_line(&mut self, line: usize);
+
+    fn highlight_line(&mut self, line: &str) -> Vec<(Range<usize>, Highlight)>;
+
+    fn current_line(&self) -> usize;
+}
+
+impl Backend for syntax::Highlighter {
+    fn update(&mut self, settings: &Settings) {
+        highlighter::Highlighter::update(self, &settings.syntax);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        highlighter::Highlighter::change_line(self, line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Vec<(Range<usize>, Highlight)> {
+        highlighter::Highlighter::highlight_line(self, line)
+            .map(|(range, highlight)| (range, Highlight::Syntax(highlight)))
+            .collect()
+    }
+
+    fn current_line(&self) -> usize {
+        highlighter::Highlighter::current_line(self)
+    }
+}
+
+/// Highlights the lines of a document with what tree-sitter made of all
+/// of it, starting over from the first line it made something else of.
+pub struct TreeSitterHighlighter {
+    spans: Arc<grammar::Spans>,
+    colors: &'static themes::Palette,
+    current_line: usize,
+}
+
+impl TreeSitterHighlighter {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            spans: settings.spans.clone().unwrap_or_default(),
+            colors: settings.colors,
+            current_line: 0,
+        }
+    }
+}
+
+impl Backend for TreeSitterHighlighter {
+    fn update(&mut self, settings: &Settings) {
+        let spans = settings.spans.clone().unwrap_or_default();
+
+        let line = if std::ptr::eq(settings.colors, self.colors) {
+            self.spans.first_difference(&spans)
+        } else {
+            0
+        };
+
+        self.spans = spans;
+        self.colors = settings.colors;
+        self.change_line(line);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Vec<(Range<usize>, Highlight)> {
+        let spans = self.spans.line(self.current_line);
+        self.current_line += 1;
+
+        // The spans of a document edited since it was parsed may no longer
+        // fit its lines
+        spans
+            .iter()
+            .filter_map(|(range, capture)| {
+                let range = range.start.min(line.len())..range.end.min(line.len());
+
+                (!range.is_empty()).then(|| {
+                    let format = Format {
+                        color: Some(grammar::color(self.colors, *capture)),
+                        font: None,
+                    };
+
+                    (range, Highlight::Remapped(format))
+                })
+            })
+            .collect()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+/// Leaves every line uncolored, once highlighting them failed.
+struct Plain {
+    current_line: usize,
+}
+
+impl Backend for Plain {
+    fn update(&mut self, _settings: &Settings) {
+        self.change_line(0);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, _line: &str) -> Vec<(Range<usize>, Highlight)> {
+        self.current_line += 1;
+
+        Vec::new()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+pub struct Highlighter {
+    backend: Box<dyn Backend>,
+    is_tree_sitter: bool,
+    extension: String,
+    /// Whether the backend panicked, so that the lines stay plain until
+    /// another language is highlighted.
+    has_failed: bool,
+    palette: Option<&'static themes::Palette>,
+    overlays: Overlays,
+}
+
+impl Highlighter {
+    /// Runs `f` on the backend, leaving the lines plain from `line` on if
+    /// it panics.
+    fn guard<T: Default>(&mut self, line: usize, f: impl FnOnce(&mut dyn Backend) -> T) -> T {
+        let backend = &mut self.backend;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| f(backend.as_mut()))) {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown error");
+
+                eprintln!(
+                    "warning: highlighting .{} failed, showing it plain: {message}",
+                    self.extension
+                );
+
+                self.backend = Box::new(Plain { current_line: line });
+                self.has_failed = true;
+
+                T::default()
+            }
+        }
+    }
+}
+
+/// The backend that highlights with `settings`.
+fn backend(settings: &Settings) -> Box<dyn Backend> {
+    if settings.spans.is_some() {
+        Box::new(TreeSitterHighlighter::new(settings))
+    } else {
+        Box::new(syntax::Highlighter::new(&settings.syntax))
+    }
+}
+
+impl highlighter::Highlighter for Highlighter {
+    type Settings = Settings;
+    type Highlight = Highlight;
+
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Highlight)>
+    where
+        Self: 'a;
+
+    fn new(settings: &Self::Settings) -> Self {
+        let mut highlighter = Self {
+            backend: Box::new(Plain { current_line: 0 }),
+            is_tree_sitter: settings.spans.is_some(),
+            extension: settings.syntax.extension.clone(),
+            has_failed: false,
+            palette: settings.palette,
+            overlays: settings.overlays.clone(),
+        };
+
+        // Parsing with tree-sitter happens right away
+        if let Some(backend) = highlighter.guard(0, |_| Some(backend(settings))) {
+            highlighter.backend = backend;
+        }
+
+        highlighter
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        let is_tree_sitter = new_settings.spans.is_some();
+
+        // Switching between the highlighter of iced and tree-sitter, or
+        // trying again with another language after failing
+        if is_tree_sitter != self.is_tree_sitter
+            || (self.has_failed && new_settings.syntax.extension != self.extension)
+        {
+            *self = Self::new(new_settings);
+            return;
+        }
+
+        self.extension = new_settings.syntax.extension.clone();
+        self.guard(0, |backend| backend.update(new_settings));
+
+        // The backend starts over where its own highlighting changed, and
+        // the lines the remapped colors or the overlays changed on are
+        // highlighted again as well
+        let line = if new_settings.palette == self.palette {
+            first_difference(&self.overlays, &new_settings.overlays)
+        } else {
+            Some(0)
+        };
+
+        if let Some(line) = line {
+            self.change_line(line);
+        }
+
+        self.palette = new_settings.palette;
+        self.overlays = new_settings.overlays.clone();
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.guard(line, |backend| backend.change_line(line));
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let number = self.backend.current_line();
+
+        let mut highlights: Vec<_> = self
+            .guard(number + 1, |backend| backend.highlight_line(line))
+            .into_iter()
+            .map(|(range, highlight)| match (highlight, self.palette) {
+                (Highlight::Syntax(highlight), Some(palette)) => {
+                    let format = highlight.to_format();
+
+                    let format = Format {
+                        color: format.color.map(|color| palette.remap(color)),
+                        ..format
+                    };
+
+                    (range, Highlight::Remapped(format))
+                }
+                (highlight, _) => (range, highlight),
+            })
+            .collect();
+
+        if let Some(overlays) = self.overlays.get(&number) {
+            highlights.extend(overlays.iter().filter_map(|(range, color)| {
+                let range = range.start.min(line.len())..range.end.min(line.len());
+
+                (!range.is_empty()).then_some((range, Highlight::Overlay(*color)))
+            }));
+        }
+
+        highlights.into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.backend.current_line()
+    }
+}
+
+/// The first line whose overlays are not the same in `old` and `new`.
+fn first_difference(old: &Overlays, new: &Overlays) -> Option<usize> {
+    old.keys()
+        .chain(new.keys())
+        .filter(|line| old.get(line) != new.get(line))
+        .min()
+        .copied()
+}
+
+/// The color of the semantic tokens of type `kind`, in the roles of
+/// `palette`, if it is one worth telling apart from the syntax.
+pub fn semantic(palette: &themes::Palette, kind: &str) -> Option<Color> {
+    match kind {
+        "namespace" | "type" | "class" | "enum" | "interface" | "struct" | "typeParameter" => {
+            Some(palette.kind)
+        }
+        "function" | "method" | "macro" => Some(palette.function),
+        "parameter" | "variable" => Some(palette.variable),
+        "enumMember" | "number" => Some(palette.number),
+        "keyword" | "operator" => Some(palette.keyword),
+        "string" => Some(palette.string),
+        "comment" => Some(palette.comment),
+        _ => None,
+    }
+}
+
+/// Converts a range of character columns of `line` into a byte range.
+pub fn byte_range(line: &str, start: usize, end: usize) -> Range<usize> {
+    let offset = |column: usize| {
+        line.char_indices()
+            .nth(column)
+            .map_or(line.len(), |(offset, _)| offset)
+    };
+
+    offset(start)..offset(end)
+}
+
+/// Colors every bracket of `text` by how deeply it is nested, so that the
+/// two brackets of a pair share a color.
+pub fn rainbow(text: &str) -> Overlays {
+    let palette = [
+        Color::from_rgb(0.95, 0.8, 0.2),
+        Color::from_rgb(0.85, 0.45, 0.85),
+        Color::from_rgb(0.3, 0.65, 0.95),
+    ];
+
+    let mut overlays = Overlays::new();
+    let mut depth = 0;
+
+    for (number, line) in text.split('\n').enumerate() {
+        for (offset, bracket) in fold::brackets(line) {
+            let level = match bracket {
+                '{' | '[' | '(' => {
+                    depth += 1;
+                    depth - 1
+                }
+                _ if depth == 0 => continue,
+                _ => {
+                    depth -= 1;
+                    depth
+                }
+            };
+
+            overlays
+                .entry(number)
+                .or_default()
+                .push((offset..offset + 1, palette[level % palette.len()]));
+        }
+    }
+
+    overlays
+}
@@ -0,0 +1,54 @@
+//! Where the cursor jumped from, to go back and forth like a browser.
+
+use std::path::PathBuf;
+
+/// The most locations remembered in each direction.
+const CAPACITY: usize = 100;
+
+/// A cursor position in the full text of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The file, unless the document was never saved.
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    back: Vec<Location>,
+    forward: Vec<Location>,
+}
+
+impl History {
+    /// Remembers the location a jump started from.
+    ///
+    /// Jumping somewhere new forgets where going back came from.
+    pub fn push(&mut self, location: Location) {
+        self.forward.clear();
+
+        if self.back.last() != Some(&location) {
+            self.back.push(location);
+        }
+
+        if self.back.len() > CAPACITY {
+            let _ = self.back.remove(0);
+        }
+    }
+
+    /// The location to go back to from `current`.
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        let location = self.back.pop()?;
+        self.forward.push(current);
+
+        Some(location)
+    }
+
+    /// The location to go forward to from `current`, after going back.
+    pub fn forward(&mut self, current: Location) -> Option<Location> {
+        let location = self.forward.pop()?;
+        self.back.push(current);
+
+        Some(location)
+    }
+}
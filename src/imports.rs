@@ -0,0 +1,268 @@
+//! Sorting the `use` declarations at the top of Rust files.
+//!
+//! Only the contiguous block of declarations before the first item is
+//! touched, and anything that a line-based reading could misunderstand,
+//! like comments or attributes inside it, leaves the file as it is.
+
+/// How long a merged declaration may get before it is left unmerged.
+const MAX_WIDTH: usize = 100;
+
+/// Groups, sorts and merges the leading `use` declarations of `text`.
+pub fn organize(text: &str) -> Result<String, String> {
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    let start = lines
+        .iter()
+        .position(|line| !is_header(line))
+        .filter(|index| visibility(lines[*index]).is_some())
+        .ok_or_else(|| "There are no use declarations at the top".to_owned())?;
+
+    let mut declarations = Vec::new();
+    let mut end = start;
+    let mut index = start;
+
+    while index < lines.len() {
+        let line = lines[index];
+
+        if line.trim().is_empty() {
+            index += 1;
+            continue;
+        }
+
+        if line.trim_start().starts_with("#[") {
+            return Err("Attributes on use declarations are left alone".to_owned());
+        }
+
+        let Some(visibility) = visibility(line) else {
+            break;
+        };
+
+        if line.starts_with(char::is_whitespace) {
+            return Err("The use declarations are indented".to_owned());
+        }
+
+        let first = index;
+
+        loop {
+            if lines[index].contains("//") || lines[index].contains("/*") {
+                return Err("Comments in use declarations are left alone".to_owned());
+            }
+
+            if lines[index].trim_end().ends_with(';') {
+                break;
+            }
+
+            index += 1;
+
+            if index == lines.len() {
+                return Err("A use declaration does not end".to_owned());
+            }
+        }
+
+        let source = &lines[first..=index];
+
+        let joined = source.join(" ");
+        let body = joined
+            .trim()
+            .strip_prefix(visibility)
+            .and_then(|rest| rest.trim_start().strip_prefix("use "))
+            .and_then(|rest| rest.trim_end().strip_suffix(';'))
+            .filter(|body| !body.contains(';'))
+            .ok_or_else(|| "A use declaration could not be read".to_owned())?;
+
+        declarations.push(Declaration {
+            visibility: visibility.to_owned(),
+            body: normalize(body),
+            source: source.iter().map(|line| (*line).to_owned()).collect(),
+        });
+
+        index += 1;
+        end = index;
+    }
+
+    let mut organized = merge(declarations);
+    organized.sort_by(|a, b| a.key().cmp(&b.key()));
+
+    let mut block = Vec::new();
+    let mut previous = None;
+
+    for declaration in &organized {
+        let group = (declaration.visibility.clone(), declaration.group());
+
+        if previous.is_some_and(|previous| previous != group) {
+            block.push(String::new());
+        }
+
+        block.extend(declaration.lines());
+        previous = Some(group);
+    }
+
+    let mut result: Vec<String> = lines[..start].iter().map(|line| (*line).to_owned()).collect();
+    result.extend(block);
+    result.extend(lines[end..].iter().map(|line| (*line).to_owned()));
+
+    Ok(result.join("\n"))
+}
+
+#[derive(Debug, Clone)]
+struct Declaration {
+    visibility: String,
+    /// The path, with its whitespace normalized.
+    body: String,
+    /// The original lines, kept for declarations that span several.
+    source: Vec<String>,
+}
+
+impl Declaration {
+    /// Standard library, then other crates, then this one.
+    fn group(&self) -> u8 {
+        let root = self
+            .body
+            .trim_start_matches("::")
+            .split("::")
+            .next()
+            .unwrap_or_default();
+
+        match root {
+            "std" | "core" | "alloc" => 0,
+            "crate" | "self" | "super" => 2,
+            _ => 1,
+        }
+    }
+
+    fn key(&self) -> (bool, u8, String) {
+        (
+            !self.visibility.is_empty(),
+            self.group(),
+            self.body.to_lowercase(),
+        )
+    }
+
+    fn lines(&self) -> Vec<String> {
+        if self.source.len() > 1 {
+            self.source.clone()
+        } else {
+            vec![format!("{}use {};", self.visibility, self.body)]
+        }
+    }
+
+    /// The prefix and the names it imports, unless it nests braces.
+    fn split(&self) -> Option<(String, Vec<String>)> {
+        if self.source.len() > 1 {
+            return None;
+        }
+
+        let (prefix, names) = match self.body.split_once("::{") {
+            Some((prefix, rest)) => {
+                let names = rest.strip_suffix('}')?;
+
+                if names.contains(['{', '}']) {
+                    return None;
+                }
+
+                (prefix, names.split(',').map(str::trim).collect::<Vec<_>>())
+            }
+            None => {
+                if self.body.contains(['{', '}']) {
+                    return None;
+                }
+
+                let (prefix, name) = self.body.rsplit_once("::")?;
+
+                (prefix, vec![name])
+            }
+        };
+
+        Some((
+            prefix.to_owned(),
+            names
+                .into_iter()
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        ))
+    }
+}
+
+/// Merges the declarations that import from the same path.
+fn merge(declarations: Vec<Declaration>) -> Vec<Declaration> {
+    let mut merged: Vec<(Declaration, Option<(String, Vec<String>)>)> = Vec::new();
+
+    for declaration in declarations {
+        let split = declaration.split();
+
+        let same = split.as_ref().and_then(|(prefix, _)| {
+            merged.iter_mut().find(|(other, other_split)| {
+                other.visibility == declaration.visibility
+                    && other_split
+                        .as_ref()
+                        .is_some_and(|(other_prefix, _)| other_prefix == prefix)
+            })
+        });
+
+        match (same, split) {
+            (Some((other, Some((prefix, names)))), Some((_, more))) => {
+                let mut all = names.clone();
+                all.extend(more);
+                sort_names(&mut all);
+
+                let body = join(prefix, &all);
+
+                if declaration.visibility.len() + body.len() + "use ;".len() <= MAX_WIDTH {
+                    other.body = body;
+                    *names = all;
+                } else {
+                    merged.push((declaration, None));
+                }
+            }
+            (_, split) => merged.push((declaration, split)),
+        }
+    }
+
+    merged.into_iter().map(|(declaration, _)| declaration).collect()
+}
+
+fn join(prefix: &str, names: &[String]) -> String {
+    match names {
+        [name] => format!("{prefix}::{name}"),
+        names => format!("{prefix}::{{{}}}", names.join(", ")),
+    }
+}
+
+/// Puts `self` first and the rest in alphabetical order, without
+/// duplicates.
+fn sort_names(names: &mut Vec<String>) {
+    names.sort_by_key(|name| (name != "self", name.to_lowercase()));
+    names.dedup();
+}
+
+/// Collapses the whitespace of a declaration spread over several lines.
+fn normalize(body: &str) -> String {
+    body.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("{ ", "{")
+        .replace(" }", "}")
+        .replace(":: ", "::")
+        .replace(",}", "}")
+}
+
+/// The visibility of a `use` declaration, or `None` if `line` does not
+/// start one.
+fn visibility(line: &str) -> Option<&'static str> {
+    let line = line.trim_start();
+
+    ["", "pub ", "pub(crate) ", "pub(super) "]
+        .into_iter()
+        .find(|visibility| {
+            line.strip_prefix(visibility)
+                .is_some_and(|rest| rest.starts_with("use "))
+        })
+}
+
+/// Whether `line` may come before the `use` declarations.
+fn is_header(line: &str) -> bool {
+    let line = line.trim();
+
+    line.is_empty() || line.starts_with("//") || line.starts_with("#![")
+}
@@ -0,0 +1,290 @@
+//! Indentation of lines.
+
+use std::fmt;
+
+/// The leading whitespace of `line`.
+pub fn of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Fits a multi-line `text` to being pasted at a cursor indented by
+/// `indentation`.
+///
+/// The whitespace that the lines have in common is taken off, and every
+/// line but the first, which continues the line of the cursor, gets
+/// `indentation` instead. The first line only counts towards what is in
+/// common when it was copied with its indentation.
+pub fn reindent(text: &str, indentation: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    let first = lines.first().copied().unwrap_or_default();
+    let counted = if of(first).is_empty() {
+        &lines[1..]
+    } else {
+        &lines[..]
+    };
+
+    let common = counted
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| of(line))
+        .reduce(common_prefix)
+        .unwrap_or_default();
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(number, line)| {
+            if number == 0 {
+                line.trim_start().to_owned()
+            } else if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{indentation}{}", line.strip_prefix(common).unwrap_or(line))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether how far lines are indented decides how they nest, in the
+/// language of files with `extension`.
+pub fn is_significant(extension: &str) -> bool {
+    matches!(
+        extension,
+        "py" | "pyw" | "pyi" | "nim" | "coffee" | "yaml" | "yml" | "sass" | "pug" | "haml"
+    )
+}
+
+/// Fits a multi-line `text` to being pasted at a cursor indented by
+/// `indentation`, in a language where indentation is significant.
+///
+/// Rather than lining the lines up with the cursor, this keeps them where
+/// they were relative to the first, which continues the line of the cursor.
+/// When the first line was copied without its indentation, it is guessed
+/// from the next line: one level less if the first line opens a block with
+/// `:`, the same otherwise. The indentation is redone in `style`, with tabs
+/// `tab_width` columns wide.
+pub fn reindent_nested(text: &str, indentation: &str, style: Style, tab_width: usize) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let columns = |line: &str| width(of(line), tab_width);
+
+    let first = lines.first().copied().unwrap_or_default();
+    let next = lines.iter().skip(1).find(|line| !line.trim().is_empty());
+
+    let base = match next {
+        _ if !of(first).is_empty() => columns(first),
+        Some(next) if opens_block(first) => {
+            let level = match detect(text).unwrap_or(style) {
+                Style::Tabs => tab_width,
+                Style::Spaces(width) => width,
+            };
+
+            columns(next).saturating_sub(level)
+        }
+        Some(next) => columns(next),
+        None => 0,
+    };
+
+    let target = width(indentation, tab_width);
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(number, line)| {
+            if number == 0 {
+                line.trim_start().to_owned()
+            } else if line.trim().is_empty() {
+                String::new()
+            } else {
+                let width = (target + columns(line)).saturating_sub(base);
+
+                whitespace(width, style, tab_width) + line.trim_start()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether `line` ends with a `:` opening a block, before any comment.
+fn opens_block(line: &str) -> bool {
+    let code = line.split('#').next().unwrap_or(line);
+
+    code.trim_end().ends_with(':')
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let length = a
+        .char_indices()
+        .zip(b.chars())
+        .take_while(|((_, x), y)| x == y)
+        .last()
+        .map_or(0, |((index, x), _)| index + x.len_utf8());
+
+    &a[..length]
+}
+
+/// How a file indents its lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Tabs,
+    /// Spaces, this many per level.
+    Spaces(usize),
+}
+
+impl Style {
+    /// The whitespace that moves the cursor at `column` to the next level.
+    pub fn step(self, column: usize) -> String {
+        match self {
+            Self::Tabs => "\t".to_owned(),
+            Self::Spaces(width) => " ".repeat(width - column % width),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tabs => f.write_str("Tabs"),
+            Self::Spaces(width) => write!(f, "Spaces: {width}"),
+        }
+    }
+}
+
+/// How most indented lines of `text` are indented, if any are.
+///
+/// The width of spaces is the step between the indentation of a line and
+/// the one above it that comes up the most.
+pub fn detect(text: &str) -> Option<Style> {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    let mut steps = [0; 9];
+    let mut previous = 0;
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let indentation = of(line);
+
+        if indentation.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+
+        let width = indentation.len();
+
+        if width > 0 {
+            spaces += 1;
+        }
+
+        if let Some(count) = steps.get_mut(width.abs_diff(previous)) {
+            *count += 1;
+        }
+
+        previous = width;
+    }
+
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+
+    if tabs > spaces {
+        return Some(Style::Tabs);
+    }
+
+    let width = (2..steps.len())
+        .max_by_key(|width| (steps[*width], std::cmp::Reverse(*width)))
+        .filter(|width| steps[*width] > 0)
+        .unwrap_or(4);
+
+    Some(Style::Spaces(width))
+}
+
+/// Whether some lines of `text` are indented with tabs and others with
+/// spaces, or lines are indented with both.
+pub fn is_mixed(text: &str) -> bool {
+    let mut tabs = false;
+    let mut spaces = false;
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let indentation = of(line);
+
+        tabs = tabs || indentation.contains('\t');
+        spaces = spaces || indentation.contains(' ');
+
+        if tabs && spaces {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// `text` with the indentation of every line redone in `style`, keeping
+/// its width. Tabs are `tab_width` columns wide.
+pub fn normalize(text: &str, style: Style, tab_width: usize) -> String {
+    text.split('\n')
+        .map(|line| {
+            let indentation = of(line);
+
+            if !indentation.contains([' ', '\t']) {
+                return line.to_owned();
+            }
+
+            whitespace(width(indentation, tab_width), style, tab_width) + line.trim_start()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How many columns `indentation` takes, with tabs `tab_width` wide.
+fn width(indentation: &str, tab_width: usize) -> usize {
+    indentation.chars().fold(0, |width, c| match c {
+        '\t' => width + tab_width - width % tab_width,
+        _ => width + 1,
+    })
+}
+
+/// Indentation `width` columns wide in `style`.
+fn whitespace(width: usize, style: Style, tab_width: usize) -> String {
+    match style {
+        Style::Tabs => "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width),
+        Style::Spaces(_) => " ".repeat(width),
+    }
+}
+
+/// `line` indented by one more level of `style`, unless it is blank.
+pub fn indent(line: &str, style: Style) -> String {
+    if line.trim().is_empty() {
+        line.to_owned()
+    } else {
+        style.step(0) + line
+    }
+}
+
+/// `line` with one level of `style` taken off its indentation, or what
+/// there is of it. Tabs are `tab_width` columns wide.
+///
+/// Indentation mixing tabs and spaces loses a level's width whatever it
+/// is made of, with spaces making up for a tab that was wider.
+pub fn outdent(line: &str, style: Style, tab_width: usize) -> String {
+    let level = match style {
+        Style::Tabs => tab_width,
+        Style::Spaces(width) => width,
+    };
+
+    let mut removed = 0;
+    let mut length = 0;
+
+    for c in of(line).chars() {
+        if removed >= level {
+            break;
+        }
+
+        removed += match c {
+            '\t' => tab_width - removed % tab_width,
+            _ => 1,
+        };
+        length += c.len_utf8();
+    }
+
+    " ".repeat(removed.saturating_sub(level)) + &line[length..]
+}
@@ -0,0 +1,38 @@
+//! Reformatting JSON documents.
+
+use serde::Serialize;
+
+/// Where a document stopped being valid JSON, as a line and byte column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Reindents `text` with two spaces, keeping the order of the keys.
+pub fn pretty(text: &str) -> Result<String, Error> {
+    let value = parse(text)?;
+    let mut output = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut output,
+        serde_json::ser::PrettyFormatter::with_indent(b"  "),
+    );
+
+    value
+        .serialize(&mut serializer)
+        .expect("Serializing a JSON value cannot fail");
+
+    Ok(String::from_utf8(output).expect("JSON is UTF-8"))
+}
+
+/// Removes all the whitespace between the tokens of `text`.
+pub fn minify(text: &str) -> Result<String, Error> {
+    Ok(parse(text)?.to_string())
+}
+
+fn parse(text: &str) -> Result<serde_json::Value, Error> {
+    serde_json::from_str(text).map_err(|error| Error {
+        line: error.line().saturating_sub(1),
+        column: error.column().saturating_sub(1),
+    })
+}
@@ -0,0 +1,44 @@
+//! The texts copied or cut recently, most recent first.
+
+use std::collections::VecDeque;
+
+/// How many texts are remembered.
+const CAPACITY: usize = 20;
+
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+}
+
+impl KillRing {
+    /// Remembers `text`, moving it to the front if it is already known.
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+
+        self.entries.retain(|entry| *entry != text);
+        self.entries.push_front(text);
+        self.entries.truncate(CAPACITY);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    pub fn position(&self, text: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry == text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+}
@@ -0,0 +1,55 @@
+//! The languages a document can be highlighted as, by the extension the
+//! highlighter knows them by.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    pub name: &'static str,
+    pub extension: &'static str,
+}
+
+impl Language {
+    pub const ALL: &'static [Self] = &[
+        Self::new("Plain text", "txt"),
+        Self::new("Bash", "sh"),
+        Self::new("C", "c"),
+        Self::new("C++", "cpp"),
+        Self::new("C#", "cs"),
+        Self::new("CSS", "css"),
+        Self::new("Go", "go"),
+        Self::new("HTML", "html"),
+        Self::new("Java", "java"),
+        Self::new("JavaScript", "js"),
+        Self::new("JSON", "json"),
+        Self::new("Makefile", "mk"),
+        Self::new("Markdown", "md"),
+        Self::new("Python", "py"),
+        Self::new("Ruby", "rb"),
+        Self::new("Rust", "rs"),
+        Self::new("SQL", "sql"),
+        Self::new("TOML", "toml"),
+        Self::new("TypeScript", "ts"),
+        Self::new("XML", "xml"),
+        Self::new("YAML", "yaml"),
+    ];
+
+    const fn new(name: &'static str, extension: &'static str) -> Self {
+        Self { name, extension }
+    }
+
+    /// The language of files with `extension`, if it is one of the known
+    /// ones.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|language| language.extension == extension)
+            .copied()
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
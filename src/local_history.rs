@@ -0,0 +1,169 @@
+//! Snapshots of documents kept regardless of version control, in
+//! `history` in the platform data directory.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::session;
+
+/// The most snapshots kept of a file.
+const CAPACITY: usize = 50;
+
+/// How long snapshots are kept.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A version of a file, as it was at some point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Where the snapshot is stored.
+    pub path: PathBuf,
+    pub time: SystemTime,
+}
+
+impl Snapshot {
+    pub fn read(&self) -> io::Result<String> {
+        std::fs::read_to_string(&self.path)
+    }
+
+    /// How long ago the snapshot was taken.
+    pub fn age(&self) -> String {
+        ago(self.time)
+    }
+}
+
+/// How long ago `time` was, like `5 minutes ago`.
+pub fn ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+
+    let (count, unit) = match seconds {
+        0..=59 => return "Just now".to_owned(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86_399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
+
+/// Keeps `text` as the latest snapshot of the file at `file`, unless it
+/// already is, and forgets the oldest ones.
+pub async fn save(file: PathBuf, text: String) -> Result<(), io::ErrorKind> {
+    let directory = directory(&file).ok_or(io::ErrorKind::NotFound)?;
+    let snapshots = list(&file);
+
+    if let Some(latest) = snapshots.first() {
+        if tokio::fs::read_to_string(&latest.path)
+            .await
+            .is_ok_and(|latest| latest == text)
+        {
+            return Ok(());
+        }
+    }
+
+    tokio::fs::create_dir_all(&directory)
+        .await
+        .map_err(|error| error.kind())?;
+
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    tokio::fs::write(directory.join(millis.to_string()), text)
+        .await
+        .map_err(|error| error.kind())?;
+
+    let old = snapshots.iter().enumerate().filter(|(index, snapshot)| {
+        *index >= CAPACITY - 1 || snapshot.time.elapsed().unwrap_or_default() > MAX_AGE
+    });
+
+    for (_, snapshot) in old {
+        let _ = tokio::fs::remove_file(&snapshot.path).await;
+    }
+
+    Ok(())
+}
+
+/// The snapshots of the file at `file`, the latest first.
+pub fn list(file: &Path) -> Vec<Snapshot> {
+    let Some(entries) = directory(file).and_then(|directory| std::fs::read_dir(directory).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let millis: u64 = entry.file_name().to_str()?.parse().ok()?;
+
+            Some(Snapshot {
+                path: entry.path(),
+                time: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            })
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| b.time.cmp(&a.time));
+    snapshots
+}
+
+/// The directory of the snapshots of `file`, named after its whole path.
+///
+/// Every byte that could not be in a name, `_` included, is written as `_`
+/// and its two hex digits, so that no two paths share a directory.
+fn directory(file: &Path) -> Option<PathBuf> {
+    let name: String = session::key(file)
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || byte == b'.' {
+                char::from(byte).to_string()
+            } else {
+                format!("_{byte:02x}")
+            }
+        })
+        .collect();
+
+    dirs::data_dir().map(|directory| directory.join("text-editor").join("history").join(name))
+}
+
+/// The local history panel.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub snapshots: Vec<Snapshot>,
+    /// The snapshot being compared with the document, and its text.
+    pub selected: Option<(usize, String)>,
+    /// The lines around the changes from the selected snapshot to the
+    /// document.
+    pub diff: Vec<(similar::ChangeTag, String)>,
+}
+
+impl State {
+    /// Compares the selected snapshot with `current`, the text of the
+    /// document.
+    pub fn compare(&mut self, current: &str) {
+        let Some((_, snapshot)) = &self.selected else {
+            self.diff.clear();
+            return;
+        };
+
+        let diff = similar::TextDiff::from_lines(snapshot.as_str(), current);
+
+        self.diff = diff
+            .grouped_ops(2)
+            .iter()
+            .flatten()
+            .flat_map(|operation| diff.iter_changes(operation))
+            .map(|change| (change.tag(), change.value().trim_end().to_owned()))
+            .collect();
+    }
+}
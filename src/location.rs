@@ -0,0 +1,137 @@
+//! Locations written as `file:line:column`, like compilers and grep print
+//! them.
+
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use regex::Regex;
+
+/// A place to go to, with a line and column counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The file, unless the location is in the open one.
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub column: Option<usize>,
+    /// The last line of a range of lines that starts at `line`.
+    pub end: Option<usize>,
+}
+
+/// Parses `line`, `line:column`, `file:line` or `file:line:column`, each
+/// of which may end in `-line` to refer to a range of lines, like
+/// `file:10-20`.
+pub fn parse(text: &str) -> Option<Reference> {
+    let text = text.trim();
+    let number = |part: &str| part.parse::<usize>().ok().filter(|number| *number > 0);
+
+    let (text, end) = match text.rsplit_once('-') {
+        Some((start, end))
+            if number(end).is_some() && start.ends_with(|c: char| c.is_ascii_digit()) =>
+        {
+            (start, number(end))
+        }
+        _ => (text, None),
+    };
+
+    // Splitting from the right keeps the colons of Windows drives in paths
+    let mut parts = text.rsplitn(3, ':');
+    let last = parts.next()?;
+    let middle = parts.next();
+    let first = parts.next();
+
+    let (path, line, column) = match (first, middle.and_then(number), number(last)) {
+        (Some(path), Some(line), Some(column)) => (Some(path), line, Some(column)),
+        (None, Some(line), Some(column)) => (None, line, Some(column)),
+        (_, _, Some(line)) => {
+            let path = text.rsplit_once(':').map(|(path, _)| path);
+
+            (path, line, None)
+        }
+        _ => return None,
+    };
+
+    Some(Reference {
+        path: path.filter(|path| !path.is_empty()).map(PathBuf::from),
+        line,
+        column,
+        end: end.filter(|end| *end > line),
+    })
+}
+
+/// A place in the open file given by how far into it it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Offset {
+    Byte(usize),
+    Percent(f64),
+}
+
+impl Offset {
+    /// The byte offset in a file of `length` bytes.
+    pub fn resolve(self, length: usize) -> usize {
+        match self {
+            Self::Byte(byte) => byte.min(length),
+            Self::Percent(percent) => (length as f64 * percent / 100.0) as usize,
+        }
+    }
+}
+
+/// Parses `@byte` or `percent%`.
+pub fn parse_offset(text: &str) -> Option<Offset> {
+    let text = text.trim();
+
+    if let Some(byte) = text.strip_prefix('@') {
+        return byte.trim().parse().ok().map(Offset::Byte);
+    }
+
+    text.strip_suffix('%')?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|percent| (0.0..=100.0).contains(percent))
+        .map(Offset::Percent)
+}
+
+/// The references to other files in `line`, with their byte ranges.
+///
+/// Only files with an extension or a directory count, since `word:1` is
+/// about as likely to be something else.
+pub fn find(line: &str) -> Vec<(Range<usize>, Reference)> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?:[A-Za-z]:\\)?[\w.\-/\\]+:\d+(?::\d+)?(?:-\d+)?")
+            .expect("The pattern of references is valid")
+    });
+
+    pattern
+        .find_iter(line)
+        .filter_map(|found| {
+            let reference = parse(found.as_str())?;
+
+            let is_file = reference.path.as_deref().is_some_and(|path| {
+                path.extension().is_some() || path.components().count() > 1
+            });
+
+            is_file.then_some((found.range(), reference))
+        })
+        .collect()
+}
+
+/// The path of a referenced file, relative ones being looked up in `root`
+/// first and then in the working directory.
+pub fn resolve(path: &Path, root: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let in_root = root.join(path);
+
+    if in_root.exists() {
+        in_root
+    } else {
+        path.to_path_buf()
+    }
+}
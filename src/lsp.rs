@@ -0,0 +1,707 @@
+//! A minimal Language Server Protocol client.
+//!
+//! The server runs inside a subscription, which reports what it hears as
+//! [`Event`]s and accepts [`Request`]s through the [`Connection`] it hands
+//! out once the server is initialized.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use iced::futures::{channel::mpsc, stream, SinkExt, StreamExt};
+use iced::{subscription, Subscription};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    process::{ChildStdin, ChildStdout},
+};
+
+use crate::config::Server;
+use crate::diagnostics::{Diagnostic, Level};
+
+/// Files whose presence marks the root of a workspace.
+const WORKSPACE_MARKERS: &[&str] = &[
+    ".git",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+];
+
+/// The kinds of semantic tokens there are colors for.
+const TOKEN_TYPES: &[&str] = &[
+    "namespace",
+    "type",
+    "class",
+    "enum",
+    "interface",
+    "struct",
+    "typeParameter",
+    "parameter",
+    "variable",
+    "property",
+    "enumMember",
+    "function",
+    "method",
+    "macro",
+    "keyword",
+    "comment",
+    "string",
+    "number",
+    "operator",
+];
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Connected(Connection),
+    Diagnostics(PathBuf, Vec<Diagnostic>),
+    Completions(Vec<String>),
+    Hover(Option<String>),
+    Definition(Option<Location>),
+    SemanticTokens(PathBuf, Vec<Token>),
+    Disconnected,
+}
+
+/// A position in a file, as the server reports it.
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub character: usize,
+}
+
+/// A span of a line that the server knows the role of, like naming a type
+/// or a function, in characters.
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub line: usize,
+    pub character: usize,
+    pub length: usize,
+    /// The type of the token, like `function`.
+    pub kind: String,
+}
+
+/// A handle to a running language server.
+#[derive(Debug, Clone)]
+pub struct Connection(mpsc::Sender<Request>);
+
+impl Connection {
+    pub fn send(&mut self, request: Request) {
+        let _ = self.0.try_send(request);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    Open {
+        path: PathBuf,
+        language: String,
+        text: String,
+    },
+    Change {
+        path: PathBuf,
+        version: i32,
+        text: String,
+    },
+    Save {
+        path: PathBuf,
+    },
+    Close {
+        path: PathBuf,
+    },
+    Complete {
+        path: PathBuf,
+        line: usize,
+        character: usize,
+    },
+    Hover {
+        path: PathBuf,
+        line: usize,
+        character: usize,
+    },
+    Definition {
+        path: PathBuf,
+        line: usize,
+        character: usize,
+    },
+    SemanticTokens {
+        path: PathBuf,
+    },
+}
+
+/// The language server of the open document.
+#[derive(Debug)]
+pub struct State {
+    pub server: Server,
+    pub root: PathBuf,
+    pub connection: Option<Connection>,
+    /// The canonical path of the document the server knows about.
+    pub document: Option<PathBuf>,
+    pub version: i32,
+}
+
+impl State {
+    pub fn new(server: Server, root: PathBuf) -> Self {
+        Self {
+            server,
+            root,
+            connection: None,
+            document: None,
+            version: 0,
+        }
+    }
+
+    /// Sends a request if the server is ready to take it.
+    pub fn send(&mut self, request: Request) {
+        if let Some(connection) = self.connection.as_mut() {
+            connection.send(request);
+        }
+    }
+}
+
+/// The closest directory above `path` that looks like a workspace root.
+pub fn workspace_root(path: &Path) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .find(|directory| {
+            WORKSPACE_MARKERS
+                .iter()
+                .any(|marker| directory.join(marker).exists())
+        })
+        .or_else(|| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Launches `server` in `root` for as long as the subscription is alive.
+pub fn connect(server: Server, root: PathBuf) -> Subscription<Event> {
+    subscription::channel(
+        (server.clone(), root.clone()),
+        100,
+        move |mut output| async move {
+            let _ = run(&server, &root, &mut output).await;
+            let _ = output.send(Event::Disconnected).await;
+
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+enum Input {
+    Server(Value),
+    Client(Request),
+}
+
+#[derive(Debug, Clone)]
+enum Pending {
+    Completion,
+    Hover,
+    Definition,
+    SemanticTokens(PathBuf),
+}
+
+async fn run(server: &Server, root: &Path, output: &mut mpsc::Sender<Event>) -> io::Result<()> {
+    let mut process = tokio::process::Command::new(&server.command)
+        .args(&server.args)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = process.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    let mut stdout = BufReader::new(process.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?);
+
+    let mut client = Client {
+        stdin,
+        next_id: 1,
+        pending: HashMap::new(),
+        token_types: Vec::new(),
+    };
+
+    client
+        .write(&json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "processId": std::process::id(),
+                "rootUri": uri(root),
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "hover": { "contentFormat": ["plaintext", "markdown"] },
+                        "definition": { "linkSupport": true },
+                        "publishDiagnostics": {},
+                        "semanticTokens": {
+                            "requests": { "full": true },
+                            "tokenTypes": TOKEN_TYPES,
+                            "tokenModifiers": [],
+                            "formats": ["relative"],
+                        },
+                    },
+                },
+            },
+        }))
+        .await?;
+
+    let initialized = loop {
+        let message = read(&mut stdout).await?;
+
+        if message.get("id") == Some(&json!(0)) {
+            break message;
+        }
+    };
+
+    // Tokens name their type by its index in the legend of the server
+    let legend = &initialized["result"]["capabilities"]["semanticTokensProvider"]["legend"];
+
+    client.token_types = legend["tokenTypes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|kind| kind.as_str().unwrap_or_default().to_owned())
+        .collect();
+
+    client.notify("initialized", json!({})).await?;
+
+    let (sender, receiver) = mpsc::channel(100);
+    let _ = output.send(Event::Connected(Connection(sender))).await;
+
+    let messages = stream::unfold(stdout, |mut stdout| async move {
+        let message = read(&mut stdout).await.ok()?;
+
+        Some((message, stdout))
+    });
+
+    let mut inputs = stream::select(
+        Box::pin(messages).map(Input::Server),
+        receiver.map(Input::Client),
+    );
+
+    while let Some(input) = inputs.next().await {
+        match input {
+            Input::Client(request) => client.send(request).await?,
+            Input::Server(message) => {
+                if let Some(event) = client.receive(message).await? {
+                    let _ = output.send(event).await;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct Client {
+    stdin: ChildStdin,
+    next_id: i64,
+    pending: HashMap<i64, Pending>,
+    /// The legend of the semantic tokens of the server, empty if it has
+    /// none.
+    token_types: Vec<String>,
+}
+
+impl Client {
+    async fn send(&mut self, request: Request) -> io::Result<()> {
+        match request {
+            Request::Open {
+                path,
+                language,
+                text,
+            } => {
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri(&path),
+                            "languageId": language,
+                            "version": 0,
+                            "text": text,
+                        },
+                    }),
+                )
+                .await
+            }
+            Request::Change {
+                path,
+                version,
+                text,
+            } => {
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri(&path), "version": version },
+                        "contentChanges": [{ "text": text }],
+                    }),
+                )
+                .await
+            }
+            Request::Save { path } => {
+                self.notify(
+                    "textDocument/didSave",
+                    json!({ "textDocument": { "uri": uri(&path) } }),
+                )
+                .await
+            }
+            Request::Close { path } => {
+                self.notify(
+                    "textDocument/didClose",
+                    json!({ "textDocument": { "uri": uri(&path) } }),
+                )
+                .await
+            }
+            Request::Complete {
+                path,
+                line,
+                character,
+            } => {
+                self.request(
+                    Pending::Completion,
+                    "textDocument/completion",
+                    json!({
+                        "textDocument": { "uri": uri(&path) },
+                        "position": { "line": line, "character": character },
+                    }),
+                )
+                .await
+            }
+            Request::Hover {
+                path,
+                line,
+                character,
+            } => {
+                self.request(
+                    Pending::Hover,
+                    "textDocument/hover",
+                    json!({
+                        "textDocument": { "uri": uri(&path) },
+                        "position": { "line": line, "character": character },
+                    }),
+                )
+                .await
+            }
+            Request::Definition {
+                path,
+                line,
+                character,
+            } => {
+                self.request(
+                    Pending::Definition,
+                    "textDocument/definition",
+                    json!({
+                        "textDocument": { "uri": uri(&path) },
+                        "position": { "line": line, "character": character },
+                    }),
+                )
+                .await
+            }
+            Request::SemanticTokens { path } => {
+                if self.token_types.is_empty() {
+                    return Ok(());
+                }
+
+                let params = json!({ "textDocument": { "uri": uri(&path) } });
+
+                self.request(
+                    Pending::SemanticTokens(path),
+                    "textDocument/semanticTokens/full",
+                    params,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn receive(&mut self, message: Value) -> io::Result<Option<Event>> {
+        let method = message.get("method").and_then(Value::as_str);
+
+        match (method, message.get("id")) {
+            // We advertise no capabilities that need answers from us
+            (Some(_), Some(id)) => {
+                self.write(&json!({ "jsonrpc": "2.0", "id": id, "result": null }))
+                    .await?;
+
+                Ok(None)
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                Ok(publish_diagnostics(&message["params"]))
+            }
+            (None, Some(id)) => {
+                let pending = id.as_i64().and_then(|id| self.pending.remove(&id));
+                let result = &message["result"];
+
+                Ok(match pending {
+                    Some(Pending::Completion) => Some(Event::Completions(completions(result))),
+                    Some(Pending::Hover) => Some(Event::Hover(hover(result))),
+                    Some(Pending::Definition) => Some(Event::Definition(definition(result))),
+                    Some(Pending::SemanticTokens(path)) => Some(Event::SemanticTokens(
+                        path,
+                        semantic_tokens(result, &self.token_types),
+                    )),
+                    None => None,
+                })
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn request(&mut self, pending: Pending, method: &str, params: Value) -> io::Result<()> {
+        let id = self.next_id;
+
+        self.next_id += 1;
+        self.pending.insert(id, pending);
+
+        self.write(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.write(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    async fn write(&mut self, message: &Value) -> io::Result<()> {
+        let body = message.to_string();
+
+        self.stdin
+            .write_all(format!("Content-Length: {}\r\n\r\n{body}", body.len()).as_bytes())
+            .await?;
+
+        self.stdin.flush().await
+    }
+}
+
+async fn read(stdout: &mut BufReader<ChildStdout>) -> io::Result<Value> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+
+        if stdout.read_line(&mut header).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let header = header.trim();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    let mut body = vec![0; length.ok_or(io::ErrorKind::InvalidData)?];
+    stdout.read_exact(&mut body).await?;
+
+    serde_json::from_slice(&body).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn publish_diagnostics(params: &Value) -> Option<Event> {
+    let path = path(params["uri"].as_str()?)?;
+
+    let diagnostics = params["diagnostics"]
+        .as_array()?
+        .iter()
+        .filter_map(|diagnostic| {
+            let level = match diagnostic["severity"].as_u64() {
+                Some(1) => Level::Error,
+                Some(2) => Level::Warning,
+                _ => return None,
+            };
+
+            let start = &diagnostic["range"]["start"];
+            let end = &diagnostic["range"]["end"];
+            let line = start["line"].as_u64()? as usize;
+
+            let end_column = if end["line"].as_u64()? as usize > line {
+                usize::MAX
+            } else {
+                end["character"].as_u64()? as usize
+            };
+
+            Some(Diagnostic {
+                path: path.clone(),
+                line,
+                columns: (start["character"].as_u64()? as usize, end_column),
+                level,
+                message: diagnostic["message"].as_str()?.to_owned(),
+            })
+        })
+        .collect();
+
+    Some(Event::Diagnostics(path, diagnostics))
+}
+
+fn completions(result: &Value) -> Vec<String> {
+    let items = result
+        .as_array()
+        .or_else(|| result["items"].as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    items
+        .iter()
+        .filter_map(|item| {
+            item["insertText"]
+                .as_str()
+                .or_else(|| item["filterText"].as_str())
+                .or_else(|| item["label"].as_str())
+                .map(str::to_owned)
+        })
+        .collect()
+}
+
+/// The plain text of a hover result, without Markdown code fences.
+fn hover(result: &Value) -> Option<String> {
+    fn marked(value: &Value) -> Option<&str> {
+        value.as_str().or_else(|| value["value"].as_str())
+    }
+
+    let contents = &result["contents"];
+
+    let text = match contents.as_array() {
+        Some(parts) => parts
+            .iter()
+            .filter_map(marked)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => marked(contents)?.to_owned(),
+    };
+
+    let text = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let text = text.trim();
+
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// The first target of a definition result.
+///
+/// Servers may answer with a single location, a list of them, or a list of
+/// location links.
+/// The tokens of `result`, whose numbers come in fives: how many lines
+/// down from the previous token, how far from its start if on the same
+/// line or else from the start of the line, how long, which of `types`,
+/// and its modifiers.
+fn semantic_tokens(result: &Value, types: &[String]) -> Vec<Token> {
+    let Some(data) = result["data"].as_array() else {
+        return Vec::new();
+    };
+
+    let numbers: Vec<usize> = data
+        .iter()
+        .map(|number| number.as_u64().unwrap_or_default() as usize)
+        .collect();
+
+    let (mut line, mut character) = (0, 0);
+
+    numbers
+        .chunks_exact(5)
+        .filter_map(|token| {
+            if token[0] > 0 {
+                line += token[0];
+                character = 0;
+            }
+
+            character += token[1];
+
+            Some(Token {
+                line,
+                character,
+                length: token[2],
+                kind: types.get(token[3])?.clone(),
+            })
+        })
+        .collect()
+}
+
+fn definition(result: &Value) -> Option<Location> {
+    let location = match result.as_array() {
+        Some(locations) => locations.first()?,
+        None => result,
+    };
+
+    let uri = location["uri"]
+        .as_str()
+        .or_else(|| location["targetUri"].as_str())?;
+
+    let start = if location.get("targetSelectionRange").is_some() {
+        &location["targetSelectionRange"]["start"]
+    } else {
+        &location["range"]["start"]
+    };
+
+    Some(Location {
+        path: path(uri)?,
+        line: start["line"].as_u64()? as usize,
+        character: start["character"].as_u64()? as usize,
+    })
+}
+
+/// The `file://` URI of a path.
+pub fn uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char);
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    uri
+}
+
+/// The path of a `file://` URI.
+pub fn path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+
+    while index < encoded.len() {
+        let decoded = (encoded[index] == b'%')
+            .then(|| std::str::from_utf8(encoded.get(index + 1..index + 3)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            None => {
+                bytes.push(encoded[index]);
+                index += 1;
+            }
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+
+    // `file:///C:/...` on Windows
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => path.as_str(),
+    };
+
+    Some(PathBuf::from(path))
+}
@@ -1,22 +1,176 @@
+mod batch;
+mod calculate;
+mod compare;
+mod complete;
+mod config;
+mod conflict;
+mod csv;
+mod cursor;
+mod diagnostics;
+mod doc;
+mod emacs;
+mod emmet;
+mod encoding;
+mod find;
+mod fold;
+mod fonts;
+mod grammar;
+mod highlight;
+mod hex;
+mod history;
+mod imports;
+mod indent;
+mod json;
+mod kill_ring;
+mod language;
+mod local_history;
+mod location;
+mod lsp;
+mod number;
+mod outline;
+mod output;
+mod recovery;
+mod replace;
+mod run;
+mod save;
+mod scope;
+mod session;
+mod sibling;
+mod sort;
+mod style;
+mod surround;
+mod tag;
+mod template;
+mod themes;
+mod todo;
+mod transform;
+mod transpose;
+mod unicode;
+mod vim;
+mod watch;
+
 use std::{
+    future::Future,
     io,
+    ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
 use iced::{
-    executor, keyboard, widget::{
-        button, column, container, horizontal_space, pick_list, row, text, text_editor, tooltip,
-    }, Application, Command, Element, Font, Length, Settings, Theme
+    event, executor, keyboard, mouse, subscription, widget::{
+        button, checkbox, column, container, horizontal_space, pick_list, row, scrollable, slider,
+        text, text_editor, text_input, tooltip, Column, Row,
+    }, window, Application, Command, Element, Event, Font, Length, Settings, Subscription, Theme
 };
 
-use iced::highlighter::{self, Highlighter};
-use iced::theme;
+use iced::highlighter;
+use iced::{theme, Color};
+
+/// The default text size.
+const TEXT_SIZE: f32 = 16.0;
+
+/// The advance of a monospace glyph, relative to the text size.
+const GLYPH_WIDTH: f32 = 0.6;
+
+/// The column to wrap at when fixed wrapping is first turned on.
+const DEFAULT_WRAP_COLUMN: usize = 100;
+
+/// The line length limit when limiting it is first turned on.
+const DEFAULT_MAX_LINE_LENGTH: usize = 100;
+
+/// The most enclosing scope headers pinned above the text.
+const MAX_STICKY_LINES: usize = 3;
+
+/// The most completions offered at once.
+const MAX_COMPLETIONS: usize = 8;
+
+/// How much of a word has to be typed before it gets completed.
+const MIN_COMPLETION_PREFIX: usize = 2;
+
+/// How long the cursor has to rest before the breadcrumbs follow it.
+const BREADCRUMBS_DELAY: Duration = Duration::from_millis(150);
+
+/// How long typing pauses before the indentation is checked for a mix of
+/// tabs and spaces.
+const MIXED_INDENT_DELAY: Duration = Duration::from_millis(500);
+
+/// How far the cursor has to move at once to count as a jump.
+const JUMP_LINES: usize = 20;
+
+/// How soon a click has to follow the last to count towards a multiple
+/// click, like in the text editor itself.
+const MULTI_CLICK: Duration = Duration::from_millis(300);
+
+/// How long typing in the scratch buffer pauses before its text is kept.
+const SCRATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// How often unsaved changes are kept in the local history.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long a line can be, in bytes, before the file is shown plainly.
+const LONG_LINE: usize = 10_000;
+
+/// How much of a file too big to open whole is shown at a time, in bytes.
+const PAGE_SIZE: u64 = 1024 * 1024;
+
+/// How many lines a document has for its highlighting to be throttled.
+const THROTTLED_LINES: usize = 5_000;
+
+/// How many lines cropping can discard before it asks first.
+const LARGE_CROP_LINES: usize = 100;
+
+/// How long a frame of smooth scrolling lasts.
+const SCROLL_FRAME: Duration = Duration::from_millis(16);
+
+/// How many lines the editor widget scrolls by per notch of the wheel.
+const NOTCH_LINES: i32 = 4;
 
 fn main() -> iced::Result {
+    let mut config = config::Config::load();
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+
+    // Transforming files from a script needs no window, nor a display
+    match batch::parse(&arguments) {
+        Some(Ok((hooks, paths))) => {
+            std::process::exit(if batch::run(&config, &hooks, &paths) { 0 } else { 1 })
+        }
+        Some(Err(usage)) => {
+            eprintln!("{usage}");
+            std::process::exit(2)
+        }
+        None => {}
+    }
+
+    let mut fonts = vec![include_bytes!("../iced-editor-icons.ttf").as_slice().into()];
+
+    match config.font.as_deref().and_then(fonts::data) {
+        Some(data) => fonts.push(data.into()),
+        None => config.font = None,
+    }
+
+    // Like `file.rs`, or `file.rs:10-20` to point at some of its lines
+    let argument = arguments.into_iter().next().map(|argument| {
+        location::parse(&argument)
+            .filter(|reference| reference.path.is_some())
+            .unwrap_or(location::Reference {
+                path: Some(PathBuf::from(argument)),
+                line: 1,
+                column: None,
+                end: None,
+            })
+    });
+
     Editor::run(Settings {
         default_font: Font::MONOSPACE,
-        fonts: vec![include_bytes!("../iced-editor-icons.ttf").as_slice().into()],
+        fonts,
+        flags: (config, argument),
+        // Closing waits for unsaved changes to be saved or discarded
+        window: window::Settings {
+            exit_on_close_request: false,
+            ..window::Settings::default()
+        },
         ..Settings::default()
     })
 }
@@ -25,42 +179,925 @@ fn main() -> iced::Result {
 enum EditorError {
     DialogClosed,
     IO(io::ErrorKind),
+    InvalidPattern(String),
+    MissingFont(String),
+    Transform(String),
+    /// A JSON document broke at the given line and column.
+    InvalidJson(usize, usize),
+    /// A hook refused to save the document.
+    SaveHook(String),
+    /// The text could not be read or written in an encoding.
+    Encoding(String),
+    /// The missing directories of the path to save to could not be created.
+    CreateDirectory(PathBuf, io::ErrorKind),
+    /// No command runs files with the extension.
+    NoRunCommand(String),
+    /// Another process holds the file to itself, so it cannot be read.
+    Locked(PathBuf),
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     New,
+    /// Opens another window, as a process of its own.
+    NewWindow,
     Edit(text_editor::Action),
     Open,
     Save,
-    FileOpened(Result<(PathBuf, Arc<String>), EditorError>),
+    SaveAs,
+    /// Closes the window, asking first if that discards unsaved changes,
+    /// as when the window is asked to close.
+    Quit,
+    ConfirmQuit,
+    CancelQuit,
+    /// Saves again as the superuser, after being denied.
+    SaveElevated,
+    /// Opens the next text file in the directory of the open one.
+    NextFileInDir,
+    PrevFileInDir,
+    /// Opens the file switched to, discarding the unsaved changes.
+    ConfirmSwitchFile,
+    CancelSwitchFile,
+    /// A file was read for the open request of the generation.
+    FileOpened(u64, Result<Opened, EditorError>),
     FileSaved(Result<PathBuf, EditorError>),
-    ThemeSelected(highlighter::Theme),
+    FileChangedOnDisk(watch::Change),
+    /// Dismisses the warning about the open file being gone.
+    KeepInMemory,
+    ReloadFile,
+    /// Keeps the text as it is after the file changed on disk.
+    KeepChanges,
+    ReloadPolicySelected(config::Reload),
+    DialogDirectorySelected(config::DialogDirectory),
+    FixedDirectoryChanged(String),
+    FollowToggled(bool),
+    /// Text was appended to the followed file.
+    FileGrew(String),
+    StickToBottomToggled(bool),
+    /// Turns editing the document off or back on.
+    ReadOnlyToggled(bool),
+    /// Turns highlighting the document on or off for good.
+    HighlightingToggled(bool),
+    /// Wraps the lines of the document at the fixed column, or not,
+    /// whatever other files do.
+    DocumentWrapToggled(bool),
+    ScrollToBottom,
+    ThemeSelected(themes::Choice),
+    LanguageThemeToggled(bool),
+    LanguageSelected(language::Language),
+    /// Highlight and analyze a file shown plainly for its long lines.
+    HighlightAnyway,
+    /// Checks the indentation after the edits of the generation.
+    CheckIndentation(u64),
+    /// Redoes the indentation of every line in the indentation style of
+    /// the document.
+    NormalizeIndentation,
+    ToggleEncoding,
+    ReopenWithEncoding(encoding::Encoding),
+    ConfirmReopen,
+    CancelReopen,
+    /// The encoding to save the document in from now on.
+    EncodingSelected(encoding::Encoding),
+    LineEndingSelected(encoding::LineEnding),
+    /// Saves the document with a byte order mark, or without.
+    BomToggled(bool),
+    ToggleReplaceInFiles,
+    ReplacePatternChanged(String),
+    ReplacementChanged(String),
+    ReplaceRegexToggled(bool),
+    PreviewReplace,
+    ReplacePreviewed(Result<Vec<replace::FileReplacement>, EditorError>),
+    ReplaceFileToggled(usize, bool),
+    ApplyReplace,
+    ConfirmReplace,
+    /// How many changed lines replacing in files asks at, typed in the
+    /// settings.
+    ConfirmReplacedLinesChanged(String),
+    /// How many deleted lines deleting the selection asks at, typed in the
+    /// settings.
+    ConfirmDeletedLinesChanged(String),
+    ConfirmDeletion,
+    CancelDeletion,
+    CancelReplace,
+    FileReplaced(Result<PathBuf, EditorError>),
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    WindowResized(u32),
+    /// The window gained or lost focus.
+    WindowFocused(bool),
+    PauseWhenUnfocusedToggled(bool),
+    RefreshBreadcrumbs(u64),
+    /// Hand the highlighter the overlays held back while typing.
+    ReleaseOverlays,
+    GoToLine(usize),
+    OpenGoTo,
+    GoToChanged(String),
+    GoToSubmitted,
+    /// Gives the keyboard to the next open region, or the previous one.
+    CycleFocus(bool),
+    CheckOnSaveToggled(bool),
+    CheckFinished(diagnostics::Report),
+    NextDiagnostic,
+    LanguageServerToggled(bool),
+    Lsp(lsp::Event),
+    CompletionSelected(String),
+    ShowHover,
+    GoToDefinition,
+    NavigateBack,
+    NavigateForward,
+    /// Moves the cursor back to where the document was last edited.
+    GoToLastEdit,
+    ModifiersChanged(keyboard::Modifiers),
+    AcceptCompletion,
+    Tab,
+    RainbowBracketsToggled(bool),
+    ToggleFind,
+    FindQueryChanged(String),
+    FindOptionsChanged(find::Options),
+    FindNext,
+    FindPrevious,
+    /// Shows the query before the one in the input typed in last.
+    OlderQuery,
+    NewerQuery,
+    ToggleSettings,
+    HighlightColorChanged(config::Highlight, String),
+    LineHeightChanged(f32),
+    SmoothScrollingToggled(bool),
+    ScrollSpeedChanged(f32),
+    WheelLinesChanged(u8),
+    ScrollOffChanged(u8),
+    ScrollFrame,
+    ToggleOverwrite,
+    FontsListed(Vec<String>),
+    FontSelected(String),
+    FontDataLoaded(String, Option<Vec<u8>>),
+    FontLoaded(String, Result<(), iced::font::Error>),
+    DefaultFontSelected,
+    WrapColumnToggled(bool),
+    WrapColumnChanged(String),
+    MaxLineLengthToggled(bool),
+    MaxLineLengthChanged(String),
+    /// Moves the cursor to the next line longer than the limit.
+    NextLongLine,
+    OpenFind,
+    VimToggled(bool),
+    VimKey(char),
+    VimCommandOpened,
+    VimCommandChanged(String),
+    VimCommandSubmitted,
+    EmacsToggled(bool),
+    NormalizeOnPasteToggled(bool),
+    SaveHookToggled(save::Hook, bool),
+    TreeSitterToggled(&'static str, bool),
+    MiddleClickPasteToggled(bool),
+    QuadrupleClickToggled(bool),
+    /// Pastes the text last selected at the cursor.
+    PastePrimary,
+    KeepBackupToggled(bool),
+    AskBeforeOverwriteToggled(bool),
+    CreateDirectoriesToggled(bool),
+    /// Switches to the scratch buffer, which is never saved to a file.
+    OpenScratch,
+    KeepScratchToggled(bool),
+    /// Time to keep the text of the scratch buffer, unless it was edited
+    /// again since.
+    KeepScratch(u64),
+    RecoverNewFilesToggled(bool),
+    /// Opens the latest new file left unsaved by an earlier run.
+    RestoreRecovered,
+    DiscardRecovered,
+    /// Where to save the document, as picked in the dialog.
+    SavePathPicked(Result<PathBuf, EditorError>),
+    ConfirmOverwrite,
+    CancelOverwrite,
+    CleanUpUnicode,
+    /// Leaves no more blank lines in a row than the configured maximum.
+    CollapseBlankLines,
+    MaxBlankLinesChanged(u8),
+    MaxBufferChanged(u16),
+    /// Shows the next page of a file too big to open whole, or the
+    /// previous one.
+    TurnPage(bool),
+    InsertUnicode,
+    CodePointChanged(String),
+    CodePointSubmitted,
+    /// Selects what is between the tags around the cursor, in markup.
+    SelectTagContents,
+    /// Asks for a new name for the tag under the cursor and its partner.
+    RenameTag,
+    TagNameChanged(String),
+    TagNameSubmitted,
+    /// Writes the note on the line of the cursor, a new one if it has none.
+    EditNote,
+    NoteChanged(String),
+    NoteSubmitted,
+    RemoveNote,
+    NextNote,
+    /// Waits for a character picking the delimiters to wrap the selection
+    /// in.
+    Surround,
+    /// Waits for a character picking the delimiters to put in place of the
+    /// pair around the cursor.
+    ChangeSurrounding,
+    /// Removes the pair of delimiters around the cursor.
+    DeleteSurrounding,
+    SurroundTagChanged(String),
+    SurroundTagSubmitted,
+    DescribeCharacter,
+    Transform(transform::Transform),
+    FormatJson,
+    MinifyJson,
+    OrganizeImports,
+    /// Writes a doc comment stub above the Rust function under the cursor.
+    GenerateDocComment,
+    IncrementNumber,
+    CsvAlignedToggled(bool),
+    ToggleSort,
+    SortOrderSelected(sort::Order),
+    SortDelimiterChanged(String),
+    SortFieldChanged(String),
+    SortLines,
+    DecrementNumber,
+    ExpandSelection,
+    SelectLine,
+    SelectParagraph,
+    TransposeCharacters,
+    TransposeWords,
+    CropToSelection,
+    ConfirmCrop,
+    CancelCrop,
+    ShrinkSelection,
+    /// A key pressed with Control, Alt or Command, even if a widget
+    /// captured it.
+    ModifiedKeyPressed(keyboard::KeyCode, keyboard::Modifiers),
+    ClipboardRead(Option<String>),
+    CyclePaste,
+    ToggleKillRing,
+    KillRingPicked(usize),
+    SaveConfig,
+    MenuToggled(Menu),
+    /// An entry of the menu bar, which closes once it is picked.
+    MenuPicked(Box<Message>),
+    ToggleAbout,
+    ToggleShortcuts,
+    /// Runs the file with the command of its language.
+    Run,
+    RunOutput(run::Output),
+    /// Kills the process of a task that still runs.
+    CancelTask(Task),
+    AppendOutput(output::Line),
+    ToggleOutput,
+    OutputHeightChanged(f32),
+    /// Keeps the layout in the session, once a panel is done resizing.
+    SaveLayout,
+    /// Closes the panels kept open and gives them their default sizes.
+    ResetLayout,
+    ClearOutput,
+    /// An action in the output panel, where double-clicking a line
+    /// referring to a file goes there.
+    OutputAction(text_editor::Action),
+    ResolveConflict(conflict::Choice),
+    NextConflict,
+    PreviousConflict,
+    CompareWith,
+    CompareOpened(Result<Opened, EditorError>),
+    CloseComparison,
+    ToggleLocalHistory,
+    SnapshotSelected(usize),
+    ToggleTodos,
+    TodosFound(Vec<(PathBuf, todo::Marker)>),
+    /// Goes to the line of a marker of the list, in the open file if there
+    /// is no path.
+    TodoPicked(Option<PathBuf>, usize),
+    ToggleTemplates,
+    /// Starts a new file from a template of the templates panel.
+    TemplatePicked(usize),
+    RestoreSnapshot,
+    /// Time to keep a snapshot of unsaved changes.
+    TakeSnapshot,
+    SnapshotKept(Result<(), io::ErrorKind>),
+    /// Copies the version information, to paste in bug reports.
+    CopyDiagnostics,
+    OpenContextMenu,
+    ContextMenuPicked(MenuItem),
+    /// The clipboard, read to paste it from the context menu.
+    ClipboardPasted(Option<String>),
+    Escape,
+}
+
+/// A menu of the menu bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Menu {
+    File,
+    Edit,
+    View,
+    Help,
+}
+
+impl Menu {
+    const ALL: [Self; 4] = [Self::File, Self::Edit, Self::View, Self::Help];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::File => "File",
+            Self::Edit => "Edit",
+            Self::View => "View",
+            Self::Help => "Help",
+        }
+    }
+
+    /// The label, shortcut and message of every entry.
+    fn items(self) -> Vec<(&'static str, &'static str, Message)> {
+        match self {
+            Self::File => vec![
+                ("New", "Ctrl+N", Message::New),
+                ("New from template...", "", Message::ToggleTemplates),
+                ("Scratch buffer", "Ctrl+Shift+K", Message::OpenScratch),
+                ("New window", "Ctrl+Shift+N", Message::NewWindow),
+                ("Open...", "Ctrl+O", Message::Open),
+                ("Next file in folder", "F8", Message::NextFileInDir),
+                ("Previous file in folder", "Shift+F8", Message::PrevFileInDir),
+                ("Save", "Ctrl+S", Message::Save),
+                ("Save as...", "", Message::SaveAs),
+                ("Reload", "", Message::ReloadFile),
+                ("Reopen with encoding...", "", Message::ToggleEncoding),
+                ("Compare with...", "", Message::CompareWith),
+                ("Local history", "", Message::ToggleLocalHistory),
+                ("Quit", "", Message::Quit),
+            ],
+            Self::Edit => vec![
+                ("Cut", "Ctrl+X", Message::ContextMenuPicked(MenuItem::Cut)),
+                ("Copy", "Ctrl+C", Message::ContextMenuPicked(MenuItem::Copy)),
+                ("Paste", "Ctrl+V", Message::ContextMenuPicked(MenuItem::Paste)),
+                ("Select all", "", Message::ContextMenuPicked(MenuItem::SelectAll)),
+                ("Select line", "Ctrl+L", Message::SelectLine),
+                ("Select paragraph", "", Message::SelectParagraph),
+                ("Crop to selection", "", Message::CropToSelection),
+                ("Surround selection", "Ctrl+Alt+S", Message::Surround),
+                ("Change surrounding pair", "Ctrl+Alt+R", Message::ChangeSurrounding),
+                ("Delete surrounding pair", "", Message::DeleteSurrounding),
+                ("Transpose characters", "Ctrl+T", Message::TransposeCharacters),
+                ("Transpose words", "Alt+T", Message::TransposeWords),
+                ("Find", "Ctrl+F", Message::ToggleFind),
+                ("Replace in files", "Ctrl+Shift+H", Message::ToggleReplaceInFiles),
+                ("Go to...", "Ctrl+G", Message::OpenGoTo),
+                ("Go to definition", "F12", Message::GoToDefinition),
+                ("Go to last edit", "Ctrl+Alt+Q", Message::GoToLastEdit),
+                ("Sort lines", "F9", Message::ToggleSort),
+                ("Collapse blank lines", "", Message::CollapseBlankLines),
+                ("Next merge conflict", "F7", Message::NextConflict),
+                ("Format JSON", "Ctrl+Alt+F", Message::FormatJson),
+                ("Minify JSON", "Ctrl+Alt+M", Message::MinifyJson),
+                ("Organize imports", "Ctrl+Alt+O", Message::OrganizeImports),
+                ("Generate doc comment", "Ctrl+Alt+D", Message::GenerateDocComment),
+                ("Note on line...", "Ctrl+Alt+N", Message::EditNote),
+                ("Next note", "", Message::NextNote),
+                ("Select tag contents", "", Message::SelectTagContents),
+                ("Rename tag...", "", Message::RenameTag),
+                ("Run", "F5", Message::Run),
+                ("Insert Unicode character", "Ctrl+Shift+U", Message::InsertUnicode),
+                ("Overtype", "Insert", Message::ToggleOverwrite),
+            ],
+            Self::View => vec![
+                ("Fold", "Ctrl+Shift+[", Message::ToggleFold),
+                ("Fold all", "Ctrl+Alt+[", Message::FoldAll),
+                ("Unfold all", "Ctrl+Alt+]", Message::UnfoldAll),
+                ("Describe character", "Ctrl+Shift+I", Message::DescribeCharacter),
+                ("Output", "Ctrl+J", Message::ToggleOutput),
+                ("TODO list", "", Message::ToggleTodos),
+                ("Next region", "F6", Message::CycleFocus(false)),
+                ("Reset layout", "", Message::ResetLayout),
+                ("Settings", "Ctrl+,", Message::ToggleSettings),
+            ],
+            Self::Help => vec![
+                ("Keyboard shortcuts", "F1", Message::ToggleShortcuts),
+                ("About", "", Message::ToggleAbout),
+            ],
+        }
+    }
+}
+
+/// A process started by the editor, which runs until it finishes or its
+/// subscription is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Task {
+    /// The file running, see `Editor::run`.
+    Run,
+    /// `cargo check`, see `Editor::check`.
+    Check,
+}
+
+/// An input taking a query that is remembered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryInput {
+    Find,
+    Pattern,
+    Replacement,
+}
+
+/// What is being surrounded, once the delimiters are picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Surrounding {
+    /// Wrapped in the delimiters.
+    Selection,
+    /// The innermost bracket pair or string literal around the cursor,
+    /// whose delimiters are replaced.
+    Pair,
+}
+
+impl Surrounding {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Selection => "the selection",
+            Self::Pair => "the pair around the cursor",
+        }
+    }
+}
+
+/// A part of the window that takes the keyboard, which F6 cycles through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Editor,
+    Find,
+    ReplaceInFiles,
+    GoTo,
+    /// The command line of Vim.
+    Command,
+}
+
+/// An entry of the context menu of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuItem {
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    GoToDefinition,
+    GenerateDocComment,
+    Transform(transform::Transform),
+}
+
+/// A file read from disk.
+#[derive(Debug, Clone)]
+struct Opened {
+    path: PathBuf,
+    content: Arc<String>,
+    /// Whether the file is binary, with its hex dump as the content.
+    is_binary: bool,
+    /// Whether some bytes are not valid in the encoding, and show as
+    /// replacement characters.
+    is_lossy: bool,
+    encoding: encoding::Encoding,
+    /// Whether the file starts with the byte order mark of its encoding.
+    has_bom: bool,
+    line_ending: encoding::LineEnding,
+    /// Whether a line is long enough to slow down highlighting it.
+    has_long_lines: bool,
+    /// The bytes read, to decode them again in another encoding.
+    bytes: Arc<Vec<u8>>,
+    /// The part of the file read, if it is too big to read whole.
+    page: Option<Page>,
+}
+
+/// The bytes of a file too big to open whole that are shown, which are
+/// whole lines unless one is longer than a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Page {
+    start: u64,
+    end: u64,
+    /// The size of the whole file.
+    total: u64,
+}
+
+/// Another file that saving as would replace.
+#[derive(Debug, Clone)]
+struct Overwrite {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Where the cursor and selection were in a file before reading it again,
+/// to find them in what it reads now.
+#[derive(Debug, Clone)]
+struct Place {
+    path: PathBuf,
+    /// The full text before reloading it.
+    text: String,
+    /// The start of the selection, or the cursor, as real line and byte
+    /// column.
+    start: (usize, usize),
+    /// The end of the selection, if any.
+    end: Option<(usize, usize)>,
+    /// How many lines the cursor was below the top of the view.
+    offset: usize,
+}
+
+/// A smooth scroll under way.
+#[derive(Debug, Clone, Copy)]
+struct Scrolling {
+    /// The lines left to scroll by, down if positive.
+    lines: i32,
+    /// Whether the cursor moves along, as when paging.
+    is_paging: bool,
+}
+
+/// What a key of an alternate keymap turns into.
+#[derive(Debug)]
+enum Output {
+    /// An action applied to the buffer as if the widget produced it.
+    Action(text_editor::Action),
+    Message(Message),
 }
 
 struct Editor {
     path: Option<PathBuf>,
     content: text_editor::Content,
     error: Option<EditorError>,
-    theme: highlighter::Theme,
+    theme: themes::Choice,
     is_dirty: bool,
+    /// When the document was last saved to disk, while open.
+    saved_at: Option<SystemTime>,
+    /// The size and modification time of the file, as of opening or last
+    /// saving it.
+    on_disk: Option<(u64, SystemTime)>,
+    /// When the document, as it is, was kept in the local history.
+    snapshot_at: Option<SystemTime>,
+    /// The extension of the language the document is treated as, instead
+    /// of the one of its file.
+    language: Option<String>,
+    encoding: encoding::Encoding,
+    /// Whether the file is saved with a byte order mark.
+    has_bom: bool,
+    line_ending: encoding::LineEnding,
+    is_encoding_open: bool,
+    /// The encoding to reopen the file in once discarding the unsaved
+    /// changes is confirmed.
+    reopen_as: Option<encoding::Encoding>,
+    /// How the document indents, as detected when it was opened.
+    indent: indent::Style,
+    /// Whether the file cannot be written to without elevated permissions.
+    is_read_only: bool,
+    /// Whether the open file was removed from disk after it was opened.
+    is_missing: bool,
+    /// Whether the open file was changed on disk by another program.
+    is_changed_on_disk: bool,
+    /// Bumped whenever the file is read or written, to watch it anew.
+    disk_generation: u64,
+    is_saving: bool,
+    /// Whether the document is the read-only hex dump of a binary file.
+    is_binary: bool,
+    /// Whether the file is not valid in its encoding, and is shown
+    /// read-only with replacement characters until reopened in another.
+    is_lossy: bool,
+    /// The part of the file shown read-only, if it is too big to open.
+    page: Option<Page>,
+    /// The bytes of the file as last read, while they are still what is on
+    /// disk as far as the editor knows.
+    bytes: Option<Arc<Vec<u8>>>,
+    /// Whether highlighting and the analyses of the text are off, to keep
+    /// files with very long lines, like minified ones, responsive.
+    is_plain: bool,
+    /// How long the file was when following it started, while it is
+    /// followed.
+    follow: Option<u64>,
+    /// What the followed file grew by while following was paused by
+    /// scrolling up.
+    follow_backlog: String,
+    replace: Option<replace::State>,
+    find: Option<find::State>,
+    /// The queries found and replaced before, shared by the find bar and
+    /// replacing in files.
+    searches: find::History,
+    replacements: find::History,
+    /// The query input typed in last, which Up and Down browse the history
+    /// of.
+    query_input: Option<QueryInput>,
+    /// The part of the window last given the keyboard.
+    region: Region,
+    /// The text last selected in the editor, which the middle button
+    /// pastes.
+    primary: Option<String>,
+    /// When a triple click last selected a line, for a fourth click to
+    /// select the paragraph.
+    line_clicked_at: Option<Instant>,
+    sort: Option<sort::State>,
+    /// The text of the table while its aligned view is shown instead.
+    csv_raw: Option<String>,
+    /// The byte ranges that expanding the selection went through, the
+    /// current one last.
+    scopes: Vec<Range<usize>>,
+    is_settings_open: bool,
+    is_about_open: bool,
+    is_shortcuts_open: bool,
+    /// The last run of the file.
+    run: Option<run::State>,
+    run_generation: u64,
+    /// What tools printed, oldest first.
+    output: Vec<output::Line>,
+    /// The text of `output`, shown read-only so that it can be selected.
+    output_content: text_editor::Content,
+    local_history: Option<local_history::State>,
+    /// The markers of the other files below the folder, while listing the
+    /// markers.
+    todos: Option<Vec<(PathBuf, todo::Marker)>>,
+    /// Whether the document is the scratch buffer.
+    is_scratch: bool,
+    scratch_generation: u64,
+    /// What the new file is known by until it is saved, to copy it aside.
+    untitled_id: Option<String>,
+    /// The new files earlier runs left unsaved, offered back until
+    /// restored or discarded.
+    recovered: Vec<recovery::Buffer>,
+    /// The templates panel, while open.
+    templates: Option<Vec<template::Template>>,
+    /// The file the document is compared with, and how their lines match.
+    comparison: Option<(PathBuf, Vec<compare::Row>)>,
+    folds: fold::Folds,
+    regions: Vec<fold::Region>,
+    rainbow_brackets: bool,
+    brackets: highlight::Overlays,
+    suspicious: Vec<unicode::Suspicious>,
+    /// The merge conflicts of the full text.
+    conflicts: Vec<conflict::Conflict>,
+    /// The code point being typed to be inserted.
+    code_point: Option<String>,
+    /// The new name of the tag being renamed.
+    tag_name: Option<String>,
+    /// What the next character typed picks the delimiters of.
+    surrounding: Option<Surrounding>,
+    /// The tag being typed to surround with.
+    surround_tag: Option<(Surrounding, String)>,
+    /// The location being typed to go to, like `src/main.rs:42:10`.
+    go_to: Option<String>,
+    top_line: usize,
+    /// The smooth scroll under way.
+    scrolling: Option<Scrolling>,
+    /// Whether the window has focus.
+    is_focused: bool,
+    /// The size and modification time of the file when the window went
+    /// idle, to tell what changed on disk when it is focused again.
+    idle_on_disk: Option<(u64, SystemTime)>,
+    /// Whether typing replaces the character under the cursor.
+    is_overwriting: bool,
+    /// The byte range of the full text to crop the document to, once
+    /// confirmed.
+    crop: Option<Range<usize>>,
+    /// How many lines the selection to delete spans, once confirmed.
+    deletion: Option<usize>,
+    /// The existing file to save over, once confirmed.
+    overwrite: Option<Overwrite>,
+    /// The file to switch to, once discarding the unsaved changes is
+    /// confirmed.
+    switch_to: Option<PathBuf>,
+    /// Whether closing the window waits for discarding the unsaved changes
+    /// to be confirmed.
+    is_quitting: bool,
+    /// Whether the file is run once the save it waits for is done.
+    run_after_save: bool,
+    /// Whether the view stays at the bottom as the document grows, while
+    /// it is there.
+    sticks_to_bottom: bool,
+    /// Whether editing the document was turned off.
+    is_kept_read_only: bool,
+    /// Whether highlighting the document was turned off, as for a file that
+    /// the highlighter chokes on.
+    is_kept_plain: bool,
+    /// Whether the document wraps at the fixed column, when it differs from
+    /// the configuration.
+    wraps_at_column: Option<bool>,
+    window_height: u32,
+    visible_lines: usize,
+    breadcrumbs: Vec<outline::Scope>,
+    breadcrumbs_generation: u64,
+    /// Whether the document indents with both tabs and spaces.
+    is_indent_mixed: bool,
+    mixed_indent_generation: u64,
+    /// The overlays, and the spans tree-sitter found, that the highlighter
+    /// keeps while typing in a long document.
+    held_highlighting: Option<(highlight::Overlays, Option<Arc<grammar::Spans>>)>,
+    /// The document as tree-sitter parsed it, when its language is picked
+    /// to highlight with it.
+    syntax: Option<grammar::Syntax>,
+    check_on_save: bool,
+    /// The `cargo check` running, by generation and project root.
+    check: Option<(u64, PathBuf)>,
+    check_generation: u64,
+    /// Bumped by every request to open a file, and by new files, so that
+    /// only the latest request gets opened.
+    open_generation: u64,
+    diagnostics: Vec<diagnostics::Diagnostic>,
+    /// What the language server says the spans of the document are, like
+    /// types or functions, by real line.
+    semantic_tokens: Vec<lsp::Token>,
+    config: config::Config,
+    session: session::Session,
+    font: Font,
+    fonts: Vec<String>,
+    /// The wrap column as typed, which may not be a number yet.
+    wrap_column: String,
+    /// The line length limit as typed in the settings.
+    max_line_length: String,
+    vim: Option<vim::State>,
+    emacs: Option<emacs::State>,
+    kill_ring: kill_ring::KillRing,
+    is_kill_ring_open: bool,
+    is_context_menu_open: bool,
+    /// The menu of the menu bar that is open.
+    menu: Option<Menu>,
+    /// The entry of the kill ring pasted last, and where it ended.
+    last_paste: Option<(usize, (usize, usize))>,
+    lsp_enabled: bool,
+    lsp: Option<lsp::State>,
+    completions: Vec<String>,
+    completion_index: usize,
+    hover: Option<String>,
+    hover_position: Option<(usize, usize)>,
+    modifiers: keyboard::Modifiers,
+    /// The places the cursor jumped away from, as file, line and character.
+    history: history::History,
+    /// Where the cursor was after the latest edit.
+    last_edit: Option<history::Location>,
+    /// Where to put the cursor once the file being opened arrives.
+    pending_jump: Option<(PathBuf, usize, usize)>,
+    /// The real lines to highlight once the file being opened arrives.
+    pending_lines: Option<Range<usize>>,
+    /// The real lines that were referred to when opening the file, shown
+    /// until the next edit.
+    highlighted_lines: Option<Range<usize>>,
+    /// Where the cursor and selection were in the file being reloaded.
+    reloading: Option<Place>,
+    /// The notes on the lines of the file, by real line.
+    notes: Vec<session::Note>,
+    /// The real line whose note is being written, and what it says so far.
+    note: Option<(usize, String)>,
 }
 impl Application for Editor {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = (config::Config, Option<location::Reference>);
+
+    fn new((config, argument): Self::Flags) -> (Self, Command<Message>) {
+        let window_height = window::Settings::default().size.1;
+        let session = session::Session::load();
+
+        let (file, pending_jump, pending_lines) = match argument {
+            Some(location::Reference {
+                path: Some(path),
+                line,
+                column,
+                end,
+            }) => (
+                path.clone(),
+                Some((path, line - 1, column.map_or(0, |column| column - 1))),
+                end.map(|end| line - 1..end),
+            ),
+            _ => (default_file(), None, None),
+        };
+        let max_size = config.max_buffer_size();
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
         (
             Self {
                 path: None,
                 content: text_editor::Content::new(),
                 error: None,
-                theme: highlighter::Theme::SolarizedDark,
+                theme: themes::Choice::Syntax(highlighter::Theme::SolarizedDark),
                 is_dirty: true,
+                saved_at: None,
+                on_disk: None,
+                snapshot_at: None,
+                language: None,
+                encoding: encoding::Encoding::default(),
+                has_bom: false,
+                line_ending: encoding::LineEnding::default(),
+                is_encoding_open: false,
+                reopen_as: None,
+                indent: config.indent(),
+                is_read_only: false,
+                is_missing: false,
+                is_changed_on_disk: false,
+                disk_generation: 0,
+                is_saving: false,
+                is_binary: false,
+                is_lossy: false,
+                page: None,
+                bytes: None,
+                is_plain: false,
+                follow: None,
+                follow_backlog: String::new(),
+                replace: None,
+                find: None,
+                sort: None,
+                csv_raw: None,
+                scopes: Vec::new(),
+                is_settings_open: false,
+                is_about_open: false,
+                is_shortcuts_open: false,
+                run: None,
+                run_generation: 0,
+                output: Vec::new(),
+                output_content: text_editor::Content::new(),
+                local_history: None,
+                todos: None,
+                templates: None,
+                is_scratch: false,
+                scratch_generation: 0,
+                untitled_id: None,
+                recovered: if config.recover_new_files {
+                    recovery::list()
+                } else {
+                    Vec::new()
+                },
+                comparison: None,
+                folds: fold::Folds::default(),
+                regions: Vec::new(),
+                rainbow_brackets: true,
+                brackets: highlight::Overlays::new(),
+                suspicious: Vec::new(),
+                conflicts: Vec::new(),
+                code_point: None,
+                tag_name: None,
+                surrounding: None,
+                surround_tag: None,
+                go_to: None,
+                top_line: 0,
+                scrolling: None,
+                is_focused: true,
+                idle_on_disk: None,
+                is_overwriting: false,
+                crop: None,
+                deletion: None,
+                overwrite: None,
+                switch_to: None,
+                is_quitting: false,
+                run_after_save: false,
+                is_kept_read_only: false,
+                is_kept_plain: false,
+                wraps_at_column: None,
+                sticks_to_bottom: false,
+                window_height,
+                visible_lines: visible_lines(window_height, config.line_height),
+                breadcrumbs: Vec::new(),
+                breadcrumbs_generation: 0,
+                is_indent_mixed: false,
+                mixed_indent_generation: 0,
+                held_highlighting: None,
+                syntax: None,
+                check_on_save: false,
+                check: None,
+                check_generation: 0,
+                open_generation: 0,
+                diagnostics: Vec::new(),
+                semantic_tokens: Vec::new(),
+                font: fonts::font(config.font.as_deref()),
+                fonts: Vec::new(),
+                max_line_length: config
+                    .max_line_length
+                    .unwrap_or(DEFAULT_MAX_LINE_LENGTH)
+                    .to_string(),
+                wrap_column: config
+                    .wrap_column
+                    .unwrap_or(DEFAULT_WRAP_COLUMN)
+                    .to_string(),
+                vim: config.vim.then(vim::State::default),
+                emacs: config.emacs.then(emacs::State::default),
+                kill_ring: kill_ring::KillRing::default(),
+                is_kill_ring_open: false,
+                is_context_menu_open: false,
+                menu: None,
+                last_paste: None,
+                config,
+                searches: find::History::new(session.searches.clone()),
+                replacements: find::History::new(session.replacements.clone()),
+                query_input: None,
+                region: Region::Editor,
+                primary: None,
+                line_clicked_at: None,
+                session,
+                lsp_enabled: false,
+                lsp: None,
+                completions: Vec::new(),
+                completion_index: 0,
+                hover: None,
+                hover_position: None,
+                modifiers: keyboard::Modifiers::default(),
+                history: history::History::default(),
+                last_edit: None,
+                pending_jump,
+                pending_lines,
+                highlighted_lines: None,
+                reloading: None,
+                notes: Vec::new(),
+                note: None,
             },
-            Command::perform(load_file(default_file()), Message::FileOpened),
+            Command::batch([
+                Command::perform(load_file(file, max_size), |result| {
+                    Message::FileOpened(0, result)
+                }),
+                Command::perform(fonts::monospace(), Message::FontsListed),
+            ]),
         )
     }
 
@@ -70,206 +1107,7704 @@ impl Application for Editor {
 
     fn update(&mut self, message: Self::Message) -> Command<Message> {
         match message {
-            Message::Edit(action) => {
-                self.is_dirty = self.is_dirty || action.is_edit();
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Left))
+                if self.modifiers.alt() && !self.modifiers.command() =>
+            {
+                self.update(Message::NavigateBack)
+            }
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Right))
+                if self.modifiers.alt() && !self.modifiers.command() =>
+            {
+                self.update(Message::NavigateForward)
+            }
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Up))
+                if self.modifiers.alt() && !self.modifiers.command() =>
+            {
+                self.update(Message::ExpandSelection)
+            }
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Down))
+                if self.modifiers.alt() && !self.modifiers.command() =>
+            {
+                self.update(Message::ShrinkSelection)
+            }
+            Message::Edit(action @ text_editor::Action::Click(_)) if self.modifiers.command() => {
                 self.content.edit(action);
-                self.error = None;
+
+                let (line, column) = self.content.cursor_position();
+                let reference = self.content.line(line).and_then(|text| {
+                    location::find(&text)
+                        .into_iter()
+                        .find(|(range, _)| range.start <= column && column <= range.end)
+                });
+
+                match reference {
+                    Some((_, reference)) => self.open_reference(reference),
+                    None => self.update(Message::GoToDefinition),
+                }
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)))
+                if self.surrounding.is_some() =>
+            {
+                let Some(surrounding) = self.surrounding.take() else {
+                    return Command::none();
+                };
+
+                if c == surround::TAG {
+                    self.surround_tag = Some((surrounding, String::new()));
+
+                    return text_input::focus(surround_tag_input());
+                }
+
+                let (open, close) = surround::pair(&self.config.surround, c);
+                self.surround(surrounding, &open, &close);
+
+                Command::none()
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)))
+                if self.modifiers.alt()
+                    && (self.emacs.is_some() || (c == 'y' && self.last_paste.is_some())) =>
+            {
+                Command::none()
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Paste(_)))
+                if self.modifiers.command() && self.modifiers.shift() =>
+            {
+                self.update(Message::ToggleKillRing)
+            }
+            // Ctrl+Alt+V pastes as is
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Paste(text)))
+                if !self.modifiers.alt() =>
+            {
+                self.paste(&text)
+            }
+            Message::Edit(text_editor::Action::Edit(edit))
+                if self.vim.as_ref().is_some_and(|vim| vim.mode != vim::Mode::Insert) =>
+            {
+                match edit {
+                    text_editor::Edit::Insert(c) => self.update(Message::VimKey(c)),
+                    _ => Command::none(),
+                }
+            }
+            Message::Edit(text_editor::Action::Move(
+                motion @ (text_editor::Motion::Up | text_editor::Motion::Down),
+            )) if !self.completions.is_empty() => {
+                let count = self.completions.len();
+
+                self.completion_index = match motion {
+                    text_editor::Motion::Up => (self.completion_index + count - 1) % count,
+                    _ => (self.completion_index + 1) % count,
+                };
+
+                Command::none()
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Enter))
+                if !self.completions.is_empty() =>
+            {
+                self.update(Message::AcceptCompletion)
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert('\t')))
+                if self.modifiers.shift() =>
+            {
+                self.shift_lines(true)
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert('\t')))
+                if !self.completions.is_empty() || self.abbreviation().is_some() =>
+            {
+                self.update(Message::Tab)
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert('\t')))
+                if self
+                    .content
+                    .selection()
+                    .is_some_and(|selection| selection.contains('\n')) =>
+            {
+                self.shift_lines(false)
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert('\t')))
+                if self.indent != indent::Style::Tabs && self.content.selection().is_none() =>
+            {
+                let (_, column) = self.content.cursor_position();
+
+                self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(
+                    self.indent.step(column),
+                ))))
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)))
+                if self.is_overwriting && self.content.selection().is_none() =>
+            {
+                let (line, column) = self.content.cursor_position();
+
+                // At the end of a line there is nothing to type over, and
+                // deleting would join the next one
+                let is_at_end = self
+                    .content
+                    .line(line)
+                    .map_or(true, |text| column >= text.len());
+
+                if is_at_end {
+                    return self.edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)));
+                }
+
+                Command::batch([
+                    self.edit(text_editor::Action::Edit(text_editor::Edit::Delete)),
+                    self.edit(text_editor::Action::Edit(text_editor::Edit::Insert(c))),
+                ])
+            }
+            // Ctrl+Home and Ctrl+End go to the ends of the document
+            Message::Edit(text_editor::Action::Move(
+                motion @ (text_editor::Motion::Home | text_editor::Motion::End),
+            )) if self.modifiers.command() => {
+                self.edit(text_editor::Action::Move(document_end(motion)))
+            }
+            Message::Edit(text_editor::Action::Select(
+                motion @ (text_editor::Motion::Home | text_editor::Motion::End),
+            )) if self.modifiers.command() => {
+                self.edit(text_editor::Action::Select(document_end(motion)))
+            }
+            Message::Edit(text_editor::Action::Scroll { lines }) => {
+                let lines = wheel_lines(lines, self.config.wheel_lines);
+
+                if self.config.smooth_scrolling {
+                    self.glide(lines, false);
+
+                    Command::none()
+                } else {
+                    self.edit(text_editor::Action::Scroll { lines })
+                }
+            }
+            Message::Edit(text_editor::Action::Move(
+                motion @ (text_editor::Motion::PageUp | text_editor::Motion::PageDown),
+            )) if self.config.smooth_scrolling => {
+                let lines = self.visible_lines as i32;
+
+                self.glide(
+                    if matches!(motion, text_editor::Motion::PageUp) {
+                        -lines
+                    } else {
+                        lines
+                    },
+                    true,
+                );
+
+                Command::none()
+            }
+            Message::Edit(text_editor::Action::Edit(
+                text_editor::Edit::Delete | text_editor::Edit::Backspace,
+            )) if self.content.selection().is_some_and(|selection| {
+                config::Confirm::asks(self.config.confirm.deleted_lines, line_span(&selection))
+            }) && !self.is_view_only() =>
+            {
+                self.deletion = self.content.selection().map(|selection| line_span(&selection));
+
+                Command::none()
+            }
+            // The text editor takes a fourth click for a second double click
+            Message::Edit(text_editor::Action::SelectWord)
+                if self.config.quadruple_click_paragraph
+                    && self
+                        .line_clicked_at
+                        .is_some_and(|time| time.elapsed() < MULTI_CLICK) =>
+            {
+                self.line_clicked_at = None;
+                self.update(Message::SelectParagraph)
+            }
+            Message::Edit(action) => self.edit(action),
+            Message::Open => self.open(pick_file(
+                self.dialog_directory(),
+                self.config.file_filters.clone(),
+                self.config.max_buffer_size(),
+            )),
+            message @ (Message::NextFileInDir | Message::PrevFileInDir) => {
+                let direction = match message {
+                    Message::PrevFileInDir => sibling::Direction::Previous,
+                    _ => sibling::Direction::Next,
+                };
+
+                match self.path.as_deref().and_then(|path| sibling::find(path, direction)) {
+                    Some(path) if self.has_unsaved_changes() => {
+                        self.switch_to = Some(path);
+                        Command::none()
+                    }
+                    Some(path) => self.open(load_file(path, self.config.max_buffer_size())),
+                    None => Command::none(),
+                }
+            }
+            Message::ConfirmSwitchFile => match self.switch_to.take() {
+                Some(path) => self.open(load_file(path, self.config.max_buffer_size())),
+                None => Command::none(),
+            },
+            Message::CancelSwitchFile => {
+                self.switch_to = None;
+                self.pending_jump = None;
+                self.pending_lines = None;
+                Command::none()
+            }
+            // A later request or a new file superseded it
+            Message::FileOpened(generation, _) if generation != self.open_generation => {
                 Command::none()
             }
-            Message::Open => Command::perform(pick_file(), Message::FileOpened),
-            Message::FileOpened(Ok((path, content))) => {
+            Message::FileOpened(_, Ok(Opened {
+                path,
+                content,
+                is_binary,
+                is_lossy,
+                encoding,
+                has_bom,
+                line_ending,
+                has_long_lines,
+                bytes,
+                page,
+            })) => {
+                self.remember_folds();
+                self.remember_notes();
+                self.leave_scratch();
+                self.untitled_id = None;
                 self.is_dirty = false;
+                self.saved_at = None;
+                self.on_disk = on_disk(&path);
+                self.is_read_only = save::is_read_only(&path);
+                self.is_missing = false;
+                self.is_changed_on_disk = false;
+                self.disk_generation += 1;
+                self.restore_document(&path);
+                self.remember_directory(&path);
+                self.is_binary = is_binary;
+                self.is_lossy = is_lossy;
+                self.is_encoding_open = is_lossy;
+                self.page = page;
+                self.bytes = Some(bytes);
+                self.is_plain = has_long_lines || self.is_kept_plain;
+                self.encoding = encoding;
+                self.has_bom = has_bom;
+                self.line_ending = line_ending;
+                self.config = self.config.for_file(&path);
+                self.indent = indent::detect(&content).unwrap_or(self.config.indent());
+                self.local_history = None;
+
+                // What was on disk before editing it is worth keeping too
+                let snapshot = if !is_binary && page.is_none() {
+                    Command::perform(
+                        local_history::save(path.clone(), content.to_string()),
+                        Message::SnapshotKept,
+                    )
+                } else {
+                    Command::none()
+                };
+                self.follow = None;
+                self.follow_backlog.clear();
                 self.path = Some(path);
                 self.content = text_editor::Content::with(&content);
-                Command::none()
+                self.csv_raw = None;
+                self.is_indent_mixed = !is_binary && indent::is_mixed(&content);
+                self.folds.clear();
+                self.diagnostics.clear();
+                self.semantic_tokens.clear();
+                self.highlighted_lines = None;
+                self.top_line = 0;
+                self.analyze();
+                self.restore_folds();
+                self.restore_notes();
+                self.open_in_language_server();
+
+                if self.sticks_to_bottom {
+                    self.content
+                        .edit(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+                    self.scroll(0);
+                }
+
+                if let Some((target, line, character)) = self.pending_jump.take() {
+                    let lines = self.pending_lines.take();
+
+                    if self.path.as_deref().and_then(|path| path.canonicalize().ok())
+                        == target.canonicalize().ok()
+                    {
+                        self.jump_to(line, character);
+                        self.highlighted_lines = lines;
+                    }
+                }
+
+                if let Some(place) = self.reloading.take() {
+                    if self.path.as_ref() == Some(&place.path) && !self.sticks_to_bottom {
+                        self.return_to(place);
+                    }
+                }
+
+                snapshot
             }
-            Message::FileOpened(Err(error)) => {
+            Message::FileOpened(_, Err(error)) => {
                 println!("{:?}", &error);
                 self.error = Some(error);
                 Command::none()
             }
+            Message::NewWindow => {
+                let spawned = std::env::current_exe().and_then(|editor| {
+                    std::process::Command::new(editor)
+                        .stdin(std::process::Stdio::null())
+                        .spawn()
+                });
+
+                if let Err(error) = spawned {
+                    self.error = Some(EditorError::IO(error.kind()));
+                }
+
+                Command::none()
+            }
             Message::New => {
+                self.open_generation += 1;
+                self.remember_folds();
+                self.remember_notes();
+                self.notes.clear();
+                self.note = None;
+                self.leave_scratch();
+                self.untitled_id = Some(recovery::id());
                 self.path = None;
+                self.on_disk = None;
                 self.is_dirty = true;
+                self.is_read_only = false;
+                self.language = None;
+                self.encoding = encoding::Encoding::default();
+                self.has_bom = false;
+                self.line_ending = encoding::LineEnding::default();
+                self.config = self.config.global();
+                self.indent = self.config.indent();
+                self.is_missing = false;
+                self.is_changed_on_disk = false;
+                self.is_binary = false;
+                self.is_lossy = false;
+                self.page = None;
+                self.bytes = None;
+                self.is_plain = false;
+                self.follow = None;
+                self.follow_backlog.clear();
+                self.sticks_to_bottom = false;
+                self.is_kept_read_only = false;
+                self.is_kept_plain = false;
+                self.wraps_at_column = None;
                 self.content = text_editor::Content::new();
+                self.csv_raw = None;
+                self.is_indent_mixed = false;
+                self.folds.clear();
+                self.diagnostics.clear();
+                self.semantic_tokens.clear();
+                self.highlighted_lines = None;
+                self.top_line = 0;
+                self.analyze();
+                self.open_in_language_server();
                 Command::none()
             }
-            Message::Save => {
-                let content = self.content.text();
-                Command::perform(save_file(self.path.clone(), content), Message::FileSaved)
+            // Saving the dump would overwrite the bytes it shows, saving a
+            // page would lose the rest of the file, and saving what could not
+            // be decoded would lose the bytes it could not
+            Message::Save | Message::SaveAs
+                if self.is_binary || self.is_lossy || self.page.is_some() =>
+            {
+                Command::none()
             }
-            Message::FileSaved(Ok(path)) => {
-                self.path = Some(path);
-                self.is_dirty = false;
+            message @ (Message::Save | Message::SaveAs) => {
+                let path = match message {
+                    Message::SaveAs => None,
+                    _ => self.path.clone(),
+                };
 
-                Command::none()
+                match path {
+                    Some(path) => self.save_to(path),
+                    None => Command::perform(
+                        pick_save_path(self.dialog_directory()),
+                        Message::SavePathPicked,
+                    ),
+                }
             }
-            Message::FileSaved(Err(error)) => {
+            Message::SavePathPicked(Ok(path)) => {
+                let existing = if self.config.ask_before_overwrite
+                    && self.path.as_ref() != Some(&path)
+                {
+                    std::fs::metadata(&path).ok()
+                } else {
+                    None
+                };
+
+                match existing {
+                    Some(metadata) => {
+                        self.overwrite = Some(Overwrite {
+                            path,
+                            size: metadata.len(),
+                            modified: metadata.modified().ok(),
+                        });
+
+                        Command::none()
+                    }
+                    None => self.save_to(path),
+                }
+            }
+            Message::SavePathPicked(Err(error)) => {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.quit_on_save = false;
+                }
+
+                self.run_after_save = false;
                 self.error = Some(error);
                 Command::none()
             }
-            Message::ThemeSelected(theme) => {
-                self.theme = theme;
+            Message::ConfirmOverwrite => match self.overwrite.take() {
+                Some(overwrite) => self.save_to(overwrite.path),
+                None => Command::none(),
+            },
+            Message::CancelOverwrite => {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.quit_on_save = false;
+                }
 
+                self.run_after_save = false;
+                self.overwrite = None;
                 Command::none()
             }
-        }
-    }
+            Message::SaveElevated => match self.path.clone() {
+                Some(path) => {
+                    let bytes = match self.encode(&self.text()) {
+                        Ok(bytes) => bytes,
+                        Err(error) => {
+                            self.error = Some(error);
+                            return Command::none();
+                        }
+                    };
 
-    fn subscription(&self) -> iced::Subscription<Self::Message> {
-        keyboard::on_key_press(|key_code, modifiers| {
-            match key_code {
-                keyboard::KeyCode::N if modifiers.command() => Some(Message::New),
-                keyboard::KeyCode::O if modifiers.command() => Some(Message::Open),
-                keyboard::KeyCode::S if modifiers.command() => Some(Message::Save),
-                _ => None,
-            }
-        })
-    }
+                    self.is_saving = true;
 
-    fn view(&self) -> Element<'_, Message> {
-        let controls = row![
-            action(get_icon(Icon::New), "New...", Some(Message::New)),
-            action(get_icon(Icon::Open), "Open...", Some(Message::Open)),
-            action(
-                get_icon(Icon::Save),
-                "Save...",
-                self.is_dirty.then_some(Message::Save)
-            ),
-            horizontal_space(Length::Fill),
-            pick_list(
-                highlighter::Theme::ALL,
-                Some(self.theme),
-                Message::ThemeSelected
-            )
-        ]
-        .spacing(10);
-        let input = text_editor(&self.content)
-            .on_edit(Message::Edit)
-            .highlight::<Highlighter>(
-                highlighter::Settings {
-                    theme: self.theme,
-                    extension: self
-                        .path
-                        .as_ref()
-                        .and_then(|path| path.extension()?.to_str())
-                        .unwrap_or("rs")
-                        .to_string(),
-                },
-                |highlight, _theme| highlight.to_format(),
-            );
+                    Command::perform(
+                        async move {
+                            save::write_elevated(&path, &bytes)
+                                .await
+                                .map(|()| path)
+                                .map_err(EditorError::IO)
+                        },
+                        Message::FileSaved,
+                    )
+                }
+                None => Command::none(),
+            },
+            Message::FileSaved(Ok(path)) => {
+                self.bytes = None;
 
-        let status_bar = {
-            let status = if let Some(EditorError::IO(error)) = self.error.as_ref() {
-                text(error.to_string())
-            } else {
-                match self.path.as_deref().and_then(Path::to_str) {
-                    Some(path) => text(path).size(14),
-                    None => text("(New File)"),
+                // Saving as another file may change its language
+                if self.path.as_ref() != Some(&path) {
+                    self.restore_document(&path);
+                    self.remember_directory(&path);
+                    self.config = self.config.for_file(&path);
+                    self.indent = indent::detect(&self.text()).unwrap_or(self.config.indent());
+                    self.local_history = None;
+                    self.path = Some(path.clone());
+                    self.analyze();
                 }
-            };
 
-            let position = {
-                let (line, column) = self.content.cursor_position();
-                text(format!("{}:{}", line + 1, column + 1))
-            };
-            row![status, horizontal_space(Length::Fill), position]
-        };
+                if self.check_on_save && self.extension() == "rs" {
+                    if let Some(root) = diagnostics::project_root(&path) {
+                        self.check_generation += 1;
+                        self.check = Some((self.check_generation, root));
+                    }
+                }
 
-        container(column![controls, input, status_bar])
-            .padding(10)
-            .into()
-    }
+                if let Some(state) = self.lsp.as_mut() {
+                    if let Some(document) = state.document.clone() {
+                        state.send(lsp::Request::Save { path: document });
+                    }
+                }
 
-    fn theme(&self) -> Theme {
-        if self.theme.is_dark() {
-            Theme::Dark
-        } else {
-            Theme::Light
-        }
-    }
-}
+                self.error = None;
+                self.is_saving = false;
+                self.disk_generation += 1;
 
-async fn save_file(path: Option<PathBuf>, text: String) -> Result<PathBuf, EditorError> {
-    let path = if let Some(path) = path {
-        path
-    } else {
-        rfd::AsyncFileDialog::new()
-            .set_title("Save As...")
-            .save_file()
-            .await
-            .ok_or(EditorError::DialogClosed)
-            .map(|handle| handle.path().to_owned())?
-    };
+                // Saved as a file, it is no longer the scratch buffer
+                self.leave_scratch();
 
-    tokio::fs::write(&path, &text)
-        .await
-        .map_err(|err| EditorError::IO(err.kind()))?;
-    Ok(path)
-}
+                if let Some(id) = self.untitled_id.take() {
+                    recovery::remove(&id);
+                }
 
-fn action<'a>(
-    content: Element<'a, Message>,
-    label: &str,
-    on_press: Option<Message>,
-) -> Element<'a, Message> {
-    let is_disabled = on_press.is_none();
-    tooltip(
-        button(container(content).width(30).center_x())
-            .on_press_maybe(on_press)
-            .padding([5, 10])
-            .style(if is_disabled {
-                theme::Button::Secondary
-            } else {
-                theme::Button::Primary
-            }),
-        label,
-        tooltip::Position::FollowCursor,
-    )
-    .style(theme::Container::Box)
-    .into()
-}
+                let snapshot = Command::perform(
+                    local_history::save(path.clone(), self.text()),
+                    Message::SnapshotKept,
+                );
+                self.is_read_only = save::is_read_only(&path);
+                self.is_missing = false;
+                self.is_changed_on_disk = false;
+                self.on_disk = on_disk(&path);
+                self.path = Some(path);
+                self.is_dirty = false;
+                self.saved_at = Some(SystemTime::now());
+                self.remember_folds();
+                self.remember_notes();
+                self.open_in_language_server();
 
-enum Icon {
-    New,
-    Open,
-    Save,
-}
+                let snapshot = if std::mem::take(&mut self.run_after_save) {
+                    Command::batch([snapshot, self.update(Message::Run)])
+                } else {
+                    snapshot
+                };
 
-fn icon<'a>(codepoint: char) -> Element<'a, Message> {
-    const ICON_FONT: Font = Font::with_name("iced-editor-icons");
+                match self.vim.as_mut() {
+                    Some(vim) if vim.quit_on_save => Command::batch([snapshot, window::close()]),
+                    _ => snapshot,
+                }
+            }
+            Message::FileSaved(Err(error)) => {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.quit_on_save = false;
+                }
 
-    text(codepoint).font(ICON_FONT).into()
-}
+                self.run_after_save = false;
+                self.is_saving = false;
+                self.disk_generation += 1;
 
-fn get_icon<'a>(i: Icon) -> Element<'a, Message> {
-    match i {
+                self.error = Some(error);
+                Command::none()
+            }
+            Message::FileChangedOnDisk(change) if change.generation != self.disk_generation => {
+                Command::none()
+            }
+            Message::FileChangedOnDisk(change) => {
+                // What was read is no longer what is on disk
+                self.bytes = None;
+
+                if change.kind == watch::Kind::Removed {
+                    // What is on disk no longer matches the buffer, which
+                    // stays as it is
+                    self.is_missing = true;
+                    self.is_dirty = true;
+
+                    return Command::none();
+                }
+
+                self.is_missing = false;
+
+                // Growing is what a followed file is expected to do
+                if self.follow.is_some() {
+                    return Command::none();
+                }
+
+                let reload = match self.config.reload {
+                    config::Reload::Always => true,
+                    config::Reload::WhenClean => !self.is_dirty,
+                    config::Reload::Prompt | config::Reload::Never => false,
+                };
+
+                if reload {
+                    self.update(Message::ReloadFile)
+                } else {
+                    self.is_changed_on_disk = true;
+
+                    Command::none()
+                }
+            }
+            Message::ReloadFile => {
+                let Some(path) = self.path.clone() else {
+                    return Command::none();
+                };
+
+                self.reloading = Some(self.place(path.clone()));
+
+                let encoding = (!self.is_binary).then_some(self.encoding);
+
+                match self.page {
+                    Some(page) => self.open(load_page(path, page.start, false, encoding)),
+                    None => self.open(load_file_as(path, encoding, self.config.max_buffer_size())),
+                }
+            }
+            Message::KeepChanges => {
+                self.is_changed_on_disk = false;
+                self.is_dirty = true;
+
+                Command::none()
+            }
+            Message::ReloadPolicySelected(reload) => {
+                self.config.reload = reload;
+                self.update(Message::SaveConfig)
+            }
+            Message::DialogDirectorySelected(dialog_directory) => {
+                self.config.dialog_directory = dialog_directory;
+                self.update(Message::SaveConfig)
+            }
+            Message::FixedDirectoryChanged(directory) => {
+                self.config.fixed_directory = (!directory.is_empty()).then(|| directory.into());
+                self.update(Message::SaveConfig)
+            }
+            Message::KeepInMemory => {
+                self.is_missing = false;
+
+                Command::none()
+            }
+            Message::FollowToggled(is_following) => {
+                self.follow_backlog.clear();
+                self.follow = if is_following {
+                    self.path
+                        .as_deref()
+                        .and_then(|path| std::fs::metadata(path).ok())
+                        .map(|metadata| metadata.len())
+                } else {
+                    None
+                };
+
+                if self.follow.is_some() {
+                    self.content
+                        .edit(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+                    self.scroll(0);
+                }
+
+                Command::none()
+            }
+            Message::FileGrew(text) => {
+                self.bytes = None;
+                self.follow_backlog.push_str(&encoding::normalize(&text));
+                self.catch_up();
+
+                Command::none()
+            }
+            Message::StickToBottomToggled(sticks_to_bottom) => {
+                self.sticks_to_bottom = sticks_to_bottom;
+                self.remember_document();
+
+                if sticks_to_bottom {
+                    self.scroll_to_bottom();
+                }
+
+                Command::none()
+            }
+            Message::ReadOnlyToggled(is_read_only) => {
+                self.is_kept_read_only = is_read_only;
+                self.remember_document();
+
+                Command::none()
+            }
+            Message::HighlightingToggled(is_highlighted) => {
+                self.is_kept_plain = !is_highlighted;
+                self.is_plain = !is_highlighted;
+                self.remember_document();
+                self.analyze();
+
+                Command::none()
+            }
+            Message::DocumentWrapToggled(wraps) => {
+                let is_default = wraps == self.config.wrap_column.is_some();
+
+                self.wraps_at_column = (!is_default).then_some(wraps);
+                self.remember_document();
+
+                Command::none()
+            }
+            Message::ScrollToBottom => {
+                self.scroll_to_bottom();
+
+                Command::none()
+            }
+            Message::HighlightAnyway => {
+                self.is_plain = false;
+                self.analyze();
+
+                Command::none()
+            }
+            Message::CheckIndentation(generation) => {
+                if generation == self.mixed_indent_generation {
+                    self.is_indent_mixed = indent::is_mixed(&self.text());
+                }
+
+                Command::none()
+            }
+            Message::NormalizeIndentation => {
+                if self.is_view_only() {
+                    return Command::none();
+                }
+
+                let text = self.text();
+                let normalized = indent::normalize(&text, self.indent, self.config.tab_width);
+
+                if normalized != text {
+                    let (line, column) = self.content.cursor_position();
+                    let line = self.folds.real_line(line);
+
+                    self.folds.clear();
+                    self.show(&normalized, line, column);
+                    self.is_dirty = true;
+                    self.notify_language_server();
+                }
+
+                self.is_indent_mixed = false;
+
+                Command::none()
+            }
+            Message::LanguageSelected(language) => {
+                self.language = None;
+
+                let is_own = self.extension() == language.extension;
+                self.language = (!is_own).then(|| language.extension.to_owned());
+                self.remember_document();
+
+                self.analyze();
+                self.open_in_language_server();
+
+                Command::none()
+            }
+            Message::ToggleEncoding => {
+                self.is_encoding_open = !self.is_encoding_open;
+
+                Command::none()
+            }
+            Message::ReopenWithEncoding(encoding) if self.has_unsaved_changes() => {
+                self.reopen_as = Some(encoding);
+
+                Command::none()
+            }
+            Message::ConfirmReopen => match self.reopen_as.take() {
+                Some(encoding) => self.reopen(encoding),
+                None => Command::none(),
+            },
+            Message::CancelReopen => {
+                self.reopen_as = None;
+
+                Command::none()
+            }
+            Message::ReopenWithEncoding(encoding) => self.reopen(encoding),
+            Message::EncodingSelected(encoding) => {
+                self.encoding = encoding;
+                // UTF-16 cannot be told apart from other encodings without it
+                self.has_bom = encoding.is_utf_16() || (self.has_bom && encoding.has_bom());
+                self.is_dirty = true;
+
+                Command::none()
+            }
+            Message::BomToggled(has_bom) => {
+                self.has_bom = has_bom;
+                self.is_dirty = true;
+
+                Command::none()
+            }
+            Message::LineEndingSelected(line_ending) => {
+                self.line_ending = line_ending;
+                self.is_dirty = true;
+
+                Command::none()
+            }
+            Message::ThemeSelected(theme) => {
+                // A language with a theme of its own keeps the one picked
+                // for it
+                let extension = self.extension().to_owned();
+
+                if let Some(name) = self.config.themes.get_mut(&extension) {
+                    *name = theme.to_string();
+
+                    return self.update(Message::SaveConfig);
+                }
+
+                self.theme = theme;
+
+                Command::none()
+            }
+            Message::LanguageThemeToggled(is_enabled) => {
+                let extension = self.extension().to_owned();
+
+                if is_enabled {
+                    self.config
+                        .themes
+                        .insert(extension, self.theme_choice().to_string());
+                } else {
+                    self.config.themes.remove(&extension);
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::ToggleReplaceInFiles => {
+                self.replace = match self.replace.take() {
+                    Some(_) => None,
+                    None => Some(replace::State::default()),
+                };
+                self.query_input = self.replace.as_ref().map(|_| QueryInput::Pattern);
+
+                if self.replace.is_some() {
+                    return self.focus(Region::ReplaceInFiles);
+                }
+
+                Command::none()
+            }
+            Message::ReplacePatternChanged(pattern) => {
+                if let Some(state) = self.replace.as_mut() {
+                    state.query.pattern = pattern;
+                    state.results.clear();
+                    state.is_confirming = false;
+                }
+
+                self.searches.reset();
+                self.query_input = Some(QueryInput::Pattern);
+
+                Command::none()
+            }
+            Message::ReplacementChanged(replacement) => {
+                if let Some(state) = self.replace.as_mut() {
+                    state.query.replacement = replacement;
+                    state.results.clear();
+                    state.is_confirming = false;
+                }
+
+                self.replacements.reset();
+                self.query_input = Some(QueryInput::Replacement);
+
+                Command::none()
+            }
+            Message::ReplaceRegexToggled(is_regex) => {
+                if let Some(state) = self.replace.as_mut() {
+                    state.query.is_regex = is_regex;
+                    state.results.clear();
+                    state.is_confirming = false;
+                }
+
+                Command::none()
+            }
+            Message::PreviewReplace => {
+                let Some(state) = self.replace.as_ref() else {
+                    return Command::none();
+                };
+
+                if state.query.pattern.is_empty() {
+                    return Command::none();
+                }
+
+                let query = state.query.clone();
+                let open = self
+                    .path
+                    .clone()
+                    .map(|path| (path, (!self.is_view_only()).then(|| self.text())));
+
+                if let Some(state) = self.replace.as_mut() {
+                    state.previewed = query.clone();
+                }
+
+                let is_new_search = self.searches.record(&query.pattern);
+                let is_new_replacement = self.replacements.record(&query.replacement);
+
+                if is_new_search || is_new_replacement {
+                    self.save_query_history();
+                }
+
+                Command::perform(
+                    replace::search(self.root(), query, open),
+                    Message::ReplacePreviewed,
+                )
+            }
+            Message::ReplacePreviewed(Ok(results)) => {
+                for result in &results {
+                    for change in &result.changes {
+                        self.append_output(output::Line::at(
+                            format!(
+                                "{}:{}: {}",
+                                result.path.display(),
+                                change.line + 1,
+                                change.before.trim()
+                            ),
+                            output::Location {
+                                path: result.path.clone(),
+                                line: change.line,
+                                character: 0,
+                            },
+                        ));
+                    }
+                }
+
+                self.append_output(output::Line::new(format!(
+                    "[{} matches in {} files]",
+                    results.iter().map(|result| result.changes.len()).sum::<usize>(),
+                    results.len()
+                )));
+
+                if let Some(state) = self.replace.as_mut() {
+                    state.results = results;
+                    state.is_confirming = false;
+                }
+
+                Command::none()
+            }
+            Message::ReplacePreviewed(Err(error)) => {
+                self.error = Some(error);
+                Command::none()
+            }
+            Message::ReplaceFileToggled(index, is_included) => {
+                if let Some(replacement) = self
+                    .replace
+                    .as_mut()
+                    .and_then(|state| state.results.get_mut(index))
+                {
+                    replacement.is_included = is_included;
+                }
+
+                Command::none()
+            }
+            Message::ApplyReplace => {
+                let Some(state) = self.replace.as_mut() else {
+                    return Command::none();
+                };
+
+                let lines = state
+                    .included()
+                    .map(|replacement| replacement.changes.len())
+                    .sum();
+
+                if state.included().next().is_none() {
+                    Command::none()
+                } else if config::Confirm::asks(self.config.confirm.replaced_lines, lines) {
+                    state.is_confirming = true;
+                    Command::none()
+                } else {
+                    self.update(Message::ConfirmReplace)
+                }
+            }
+            Message::CancelReplace => {
+                if let Some(state) = self.replace.as_mut() {
+                    state.is_confirming = false;
+                }
+
+                Command::none()
+            }
+            Message::ConfirmReplace => {
+                let Some(state) = self.replace.as_mut() else {
+                    return Command::none();
+                };
+
+                let query = state.previewed.clone();
+                let replacements: Vec<_> = state.included().cloned().collect();
+                state.results.retain(|replacement| !replacement.is_included);
+                state.is_confirming = false;
+
+                let commands: Vec<_> = replacements
+                    .into_iter()
+                    .filter_map(|replacement| {
+                        let bytes = if self.path.as_ref() == Some(&replacement.path) {
+                            self.replace_in_document(&query)?
+                        } else {
+                            replacement.text.as_bytes().to_vec()
+                        };
+
+                        Some(Command::perform(
+                            save_file(replacement.path, bytes, self.backup(), false),
+                            Message::FileReplaced,
+                        ))
+                    })
+                    .collect();
+
+                Command::batch(commands)
+            }
+            Message::FileReplaced(Ok(path)) => {
+                if self.path.as_ref() == Some(&path) {
+                    self.disk_generation += 1;
+                    self.is_dirty = false;
+                    self.saved_at = Some(SystemTime::now());
+                    self.on_disk = on_disk(&path);
+                    self.bytes = None;
+                }
+
+                Command::none()
+            }
+            Message::FileReplaced(Err(error)) => {
+                self.error = Some(error);
+                Command::none()
+            }
+            Message::ToggleFold => {
+                let (line, column) = self.content.cursor_position();
+                let text = self.content.text();
+
+                if self.folds.is_folded(line) {
+                    let (text, _) = self.folds.unfold(&text, line);
+                    self.show(&text, line, column);
+                } else if let Some(region) = fold::regions(&text, self.extension())
+                    .into_iter()
+                    .rfind(|region| region.contains(line))
+                {
+                    let text = self.folds.fold(&text, region);
+                    self.show(&text, region.start, usize::MAX);
+                }
+
+                self.remember_folds();
+
+                Command::none()
+            }
+            Message::FoldAll => {
+                let (line, column) = self.content.cursor_position();
+                let mut text = self.content.text();
+                let mut cursor = (line, column);
+
+                for region in fold::outermost(&fold::regions(&text, self.extension()))
+                    .into_iter()
+                    .rev()
+                {
+                    if region.contains(line) {
+                        cursor = (region.start, usize::MAX);
+                    } else if region.end < line {
+                        cursor.0 -= region.end - region.start;
+                    }
+
+                    text = self.folds.fold(&text, region);
+                }
+
+                self.show(&text, cursor.0, cursor.1);
+                self.remember_folds();
+
+                Command::none()
+            }
+            Message::UnfoldAll => {
+                let (line, column) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+                let text = self.text();
+
+                self.folds.clear();
+                self.show(&text, line, column);
+                self.remember_folds();
+
+                Command::none()
+            }
+            Message::WindowResized(height) => {
+                self.window_height = height;
+                self.visible_lines = visible_lines(height, self.config.line_height);
+                self.scroll(0);
+
+                Command::none()
+            }
+            Message::WindowFocused(is_focused) => {
+                let was_idle = self.is_idle();
+                self.is_focused = is_focused;
+
+                match (was_idle, self.is_idle()) {
+                    (false, true) => {
+                        self.idle_on_disk = self.path.as_deref().and_then(on_disk);
+
+                        Command::none()
+                    }
+                    (true, false) => self.catch_up_on_disk(),
+                    _ => Command::none(),
+                }
+            }
+            Message::PauseWhenUnfocusedToggled(pause_when_unfocused) => {
+                self.config.pause_when_unfocused = pause_when_unfocused;
+                self.update(Message::SaveConfig)
+            }
+            Message::RefreshBreadcrumbs(generation) => {
+                if generation == self.breadcrumbs_generation {
+                    self.refresh_breadcrumbs();
+                }
+
+                Command::none()
+            }
+            Message::ReleaseOverlays => {
+                self.held_highlighting = None;
+
+                Command::none()
+            }
+            Message::CheckOnSaveToggled(check_on_save) => {
+                self.check_on_save = check_on_save;
+
+                if !check_on_save {
+                    self.check = None;
+                    self.diagnostics.clear();
+                }
+
+                Command::none()
+            }
+            Message::CheckFinished(report) => {
+                if self.check.as_ref().map(|(generation, _)| *generation) != Some(report.generation)
+                {
+                    return Command::none();
+                }
+
+                self.check = None;
+
+                match report.result {
+                    Ok(diagnostics) => {
+                        for diagnostic in &diagnostics {
+                            self.append_output(output::Line::at(
+                                diagnostic.to_string(),
+                                output::Location {
+                                    path: diagnostic.path.clone(),
+                                    line: diagnostic.line,
+                                    character: diagnostic.columns.0,
+                                },
+                            ));
+                        }
+
+                        self.append_output(output::Line::new(format!(
+                            "[cargo check: {} diagnostics]",
+                            diagnostics.len()
+                        )));
+
+                        let path = self.path.as_deref().and_then(|path| path.canonicalize().ok());
+
+                        self.diagnostics = diagnostics
+                            .into_iter()
+                            .filter(|diagnostic| Some(&diagnostic.path) == path.as_ref())
+                            .collect();
+                    }
+                    Err(error) => {
+                        self.error = Some(error);
+                    }
+                }
+
+                Command::none()
+            }
+            Message::NextDiagnostic => {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                let next = self
+                    .diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.line > line)
+                    .or(self.diagnostics.first());
+
+                if let Some(diagnostic) = next {
+                    let (line, (character, _)) = (diagnostic.line, diagnostic.columns);
+
+                    self.jump_to(line, character);
+                }
+
+                Command::none()
+            }
+            Message::LanguageServerToggled(lsp_enabled) => {
+                self.lsp_enabled = lsp_enabled;
+
+                if lsp_enabled {
+                    self.open_in_language_server();
+                } else {
+                    self.lsp = None;
+                    self.completions.clear();
+                }
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Connected(connection)) => {
+                if let Some(state) = self.lsp.as_mut() {
+                    state.connection = Some(connection);
+                    state.document = None;
+                }
+
+                self.open_in_language_server();
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Diagnostics(path, diagnostics)) => {
+                if self.path.as_deref().and_then(|path| path.canonicalize().ok()) == Some(path) {
+                    self.diagnostics = diagnostics;
+                }
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::SemanticTokens(path, tokens)) => {
+                if self.path.as_deref().and_then(|path| path.canonicalize().ok()) == Some(path) {
+                    self.semantic_tokens = tokens;
+                }
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Completions(completions)) => {
+                let prefix = self.word_before_cursor();
+
+                self.completions = completions
+                    .into_iter()
+                    .filter(|completion| completion.starts_with(&prefix) && *completion != prefix)
+                    .take(MAX_COMPLETIONS)
+                    .collect();
+                self.completion_index = 0;
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Hover(hover)) => {
+                // The cursor may have moved on while the server was thinking
+                if self.hover_position == Some(self.content.cursor_position()) {
+                    self.hover = hover;
+                }
+
+                Command::none()
+            }
+            Message::ShowHover => {
+                let (line, character) = self.lsp_position();
+
+                if let Some(state) = self.lsp.as_mut() {
+                    if let Some(path) = state.document.clone() {
+                        state.send(lsp::Request::Hover {
+                            path,
+                            line,
+                            character,
+                        });
+
+                        self.hover_position = Some(self.content.cursor_position());
+                    }
+                }
+
+                Command::none()
+            }
+            Message::GoToDefinition => {
+                let (line, character) = self.lsp_position();
+
+                if let Some(state) = self.lsp.as_mut() {
+                    if let Some(path) = state.document.clone() {
+                        state.send(lsp::Request::Definition {
+                            path,
+                            line,
+                            character,
+                        });
+                    }
+                }
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Definition(Some(location))) => {
+                self.history.push(self.location());
+
+                self.go_to(location.path, location.line, location.character)
+            }
+            Message::Lsp(lsp::Event::Definition(None)) => Command::none(),
+            Message::NavigateBack => match self.history.back(self.location()) {
+                Some(location) => self.navigate(location),
+                None => Command::none(),
+            },
+            Message::NavigateForward => match self.history.forward(self.location()) {
+                Some(location) => self.navigate(location),
+                None => Command::none(),
+            },
+            Message::GoToLastEdit => match self.last_edit.clone() {
+                // An untitled document that was replaced is gone
+                Some(location) if location.path.is_some() || self.path.is_none() => {
+                    self.history.push(self.location());
+                    self.navigate(location)
+                }
+                _ => Command::none(),
+            },
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
+            Message::AcceptCompletion => match self.completions.get(self.completion_index) {
+                Some(completion) => {
+                    let completion = completion.clone();
+                    self.update(Message::CompletionSelected(completion))
+                }
+                None => Command::none(),
+            },
+            Message::Tab => {
+                if !self.completions.is_empty() {
+                    self.update(Message::AcceptCompletion)
+                } else if let Some((abbreviation, expansion)) = self.abbreviation() {
+                    self.expand_abbreviation(&abbreviation, expansion)
+                } else {
+                    Command::none()
+                }
+            }
+            Message::RainbowBracketsToggled(rainbow_brackets) => {
+                self.rainbow_brackets = rainbow_brackets;
+                self.analyze();
+
+                Command::none()
+            }
+            Message::ToggleFind => {
+                if self.find.is_some() {
+                    self.close_find();
+                    Command::none()
+                } else {
+                    self.update(Message::OpenFind)
+                }
+            }
+            Message::OpenFind => {
+                if self.find.is_none() {
+                    let mut state = find::State::new(self.config.find);
+
+                    if let Some(selection) = self.content.selection() {
+                        if !selection.contains('\n') {
+                            state.query = selection;
+                        }
+                    }
+
+                    state.search(&self.content.text());
+                    self.find = Some(state);
+                }
+
+                self.query_input = Some(QueryInput::Find);
+                self.region = Region::Find;
+                text_input::focus(find_input())
+            }
+            Message::FindQueryChanged(query) => {
+                if let Some(state) = self.find.as_mut() {
+                    state.query = query;
+                    state.search(&self.content.text());
+                }
+
+                self.searches.reset();
+                self.query_input = Some(QueryInput::Find);
+
+                Command::none()
+            }
+            Message::FindOptionsChanged(options) => {
+                if let Some(state) = self.find.as_mut() {
+                    state.options = options;
+                    state.search(&self.content.text());
+                }
+
+                self.config.find = options;
+                self.update(Message::SaveConfig)
+            }
+            Message::FindNext => {
+                self.remember_find_query();
+                self.select_match(find::State::next);
+                Command::none()
+            }
+            Message::FindPrevious => {
+                self.remember_find_query();
+                self.select_match(find::State::previous);
+                Command::none()
+            }
+            message @ (Message::OlderQuery | Message::NewerQuery) => {
+                let is_older = matches!(message, Message::OlderQuery);
+
+                let browse = |history: &mut find::History, current: &str| {
+                    if is_older {
+                        history.older(current)
+                    } else {
+                        history.newer()
+                    }
+                };
+
+                match self.query_input {
+                    Some(QueryInput::Find) => {
+                        if let Some(state) = self.find.as_mut() {
+                            if let Some(query) = browse(&mut self.searches, &state.query) {
+                                state.query = query;
+                                state.search(&self.content.text());
+                            }
+                        }
+                    }
+                    Some(QueryInput::Pattern) => {
+                        if let Some(state) = self.replace.as_mut() {
+                            if let Some(pattern) = browse(&mut self.searches, &state.query.pattern)
+                            {
+                                state.query.pattern = pattern;
+                                state.results.clear();
+                                state.is_confirming = false;
+                            }
+                        }
+                    }
+                    Some(QueryInput::Replacement) => {
+                        if let Some(state) = self.replace.as_mut() {
+                            if let Some(replacement) =
+                                browse(&mut self.replacements, &state.query.replacement)
+                            {
+                                state.query.replacement = replacement;
+                                state.results.clear();
+                                state.is_confirming = false;
+                            }
+                        }
+                    }
+                    None => {}
+                }
+
+                Command::none()
+            }
+            Message::ToggleSettings => {
+                self.is_settings_open = !self.is_settings_open;
+                Command::none()
+            }
+            Message::HighlightColorChanged(highlight, value) => {
+                self.config.colors.set(highlight, value);
+
+                if self.config.colors.is_valid(highlight) {
+                    return self.update(Message::SaveConfig);
+                }
+
+                Command::none()
+            }
+            Message::LineHeightChanged(line_height) => {
+                self.config.line_height = config::clamp_line_height(line_height);
+                self.visible_lines = visible_lines(self.window_height, self.config.line_height);
+
+                Command::none()
+            }
+            Message::SmoothScrollingToggled(smooth_scrolling) => {
+                self.config.smooth_scrolling = smooth_scrolling;
+                self.update(Message::SaveConfig)
+            }
+            Message::ScrollSpeedChanged(scroll_speed) => {
+                self.config.scroll_speed = config::clamp_scroll_speed(scroll_speed);
+
+                Command::none()
+            }
+            Message::WheelLinesChanged(wheel_lines) => {
+                self.config.wheel_lines = wheel_lines;
+
+                Command::none()
+            }
+            Message::ScrollOffChanged(scroll_off) => {
+                self.config.scroll_off = scroll_off;
+
+                Command::none()
+            }
+            Message::ToggleOverwrite => {
+                self.is_overwriting = !self.is_overwriting;
+
+                Command::none()
+            }
+            Message::ScrollFrame => {
+                let Some(scrolling) = self.scrolling else {
+                    return Command::none();
+                };
+
+                // Each frame covers a share of what is left, so the scroll
+                // slows down as it arrives
+                let step = ((scrolling.lines.unsigned_abs() as f32 * self.config.scroll_speed)
+                    .ceil() as i32)
+                    .max(1)
+                    * scrolling.lines.signum();
+
+                self.scrolling = Some(Scrolling {
+                    lines: scrolling.lines - step,
+                    ..scrolling
+                })
+                .filter(|scrolling| scrolling.lines != 0);
+
+                let mut commands = vec![self.edit(text_editor::Action::Scroll { lines: step })];
+
+                // Paging keeps the cursor where it is in the view
+                if scrolling.is_paging {
+                    let motion = if step > 0 {
+                        text_editor::Motion::Down
+                    } else {
+                        text_editor::Motion::Up
+                    };
+
+                    for _ in 0..step.abs() {
+                        commands.push(self.edit(text_editor::Action::Move(motion)));
+                    }
+                }
+
+                Command::batch(commands)
+            }
+            Message::FontsListed(fonts) => {
+                self.fonts = fonts;
+                Command::none()
+            }
+            Message::FontSelected(family) => Command::perform(fonts::load(family.clone()), move |data| {
+                Message::FontDataLoaded(family.clone(), data)
+            }),
+            Message::FontDataLoaded(family, Some(data)) => iced::font::load(data)
+                .map(move |result| Message::FontLoaded(family.clone(), result)),
+            Message::FontDataLoaded(family, None) | Message::FontLoaded(family, Err(_)) => {
+                self.error = Some(EditorError::MissingFont(family));
+                Command::none()
+            }
+            Message::FontLoaded(family, Ok(())) => {
+                self.font = fonts::font(Some(&family));
+                self.config.font = Some(family);
+
+                self.update(Message::SaveConfig)
+            }
+            Message::DefaultFontSelected => {
+                self.font = Font::MONOSPACE;
+                self.config.font = None;
+
+                self.update(Message::SaveConfig)
+            }
+            Message::WrapColumnToggled(is_fixed) => {
+                self.config.wrap_column = is_fixed.then(|| {
+                    self.wrap_column
+                        .parse()
+                        .ok()
+                        .filter(|column| *column > 0)
+                        .unwrap_or(DEFAULT_WRAP_COLUMN)
+                });
+
+                self.update(Message::SaveConfig)
+            }
+            Message::WrapColumnChanged(value) => {
+                if !value.chars().all(|c| c.is_ascii_digit()) {
+                    return Command::none();
+                }
+
+                self.wrap_column = value;
+
+                match self.wrap_column.parse().ok().filter(|column| *column > 0) {
+                    Some(column) if self.config.wrap_column.is_some() => {
+                        self.config.wrap_column = Some(column);
+                        self.update(Message::SaveConfig)
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::MaxLineLengthToggled(is_limited) => {
+                self.config.max_line_length = is_limited.then(|| {
+                    self.max_line_length
+                        .parse()
+                        .ok()
+                        .filter(|length| *length > 0)
+                        .unwrap_or(DEFAULT_MAX_LINE_LENGTH)
+                });
+
+                self.update(Message::SaveConfig)
+            }
+            Message::MaxLineLengthChanged(value) => {
+                if !value.chars().all(|c| c.is_ascii_digit()) {
+                    return Command::none();
+                }
+
+                self.max_line_length = value;
+
+                match self.max_line_length.parse().ok().filter(|length| *length > 0) {
+                    Some(length) if self.config.max_line_length.is_some() => {
+                        self.config.max_line_length = Some(length);
+                        self.update(Message::SaveConfig)
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::NextLongLine => {
+                let (line, _) = self.content.cursor_position();
+                let long_lines = self.long_lines();
+
+                let next = long_lines
+                    .iter()
+                    .find(|(number, _)| *number > line)
+                    .or(long_lines.first());
+
+                if let Some((line, start)) = next.copied() {
+                    cursor::move_to(&mut self.content, line, start);
+                    self.scroll(0);
+                }
+
+                Command::none()
+            }
+            Message::SaveConfig => {
+                if let Err(error) = self.config.save() {
+                    self.error = Some(EditorError::IO(error));
+                }
+
+                Command::none()
+            }
+            Message::VimToggled(is_enabled) => {
+                self.config.vim = is_enabled;
+                self.vim = is_enabled.then(vim::State::default);
+
+                if is_enabled {
+                    self.config.emacs = false;
+                    self.emacs = None;
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::VimKey(c) => {
+                let Some(vim) = self.vim.as_mut() else {
+                    return Command::none();
+                };
+
+                let outputs = vim.key(c, &self.content);
+
+                self.run_keymap(outputs)
+            }
+            Message::VimCommandOpened => self.focus(Region::Command),
+            Message::VimCommandChanged(command) => {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.command = Some(command);
+                }
+
+                Command::none()
+            }
+            Message::VimCommandSubmitted => {
+                let Some(vim) = self.vim.as_mut() else {
+                    return Command::none();
+                };
+
+                let Some(command) = vim.command.take() else {
+                    return Command::none();
+                };
+
+                match command.trim() {
+                    "w" => self.update(Message::Save),
+                    "q" if self.has_unsaved_changes() => {
+                        vim.message =
+                            Some("No write since last change (add ! to override)".to_owned());
+                        Command::none()
+                    }
+                    "q" | "q!" => window::close(),
+                    "wq" | "x" => {
+                        vim.quit_on_save = true;
+                        self.update(Message::Save)
+                    }
+                    command => {
+                        if let Ok(line) = command.parse::<usize>() {
+                            self.jump_to(line.saturating_sub(1), 0);
+                        } else {
+                            vim.message = Some(format!("Not an editor command: {command}"));
+                        }
+
+                        Command::none()
+                    }
+                }
+            }
+            Message::EmacsToggled(is_enabled) => {
+                self.config.emacs = is_enabled;
+                self.emacs = is_enabled.then(emacs::State::default);
+
+                if is_enabled {
+                    self.config.vim = false;
+                    self.vim = None;
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::SaveHookToggled(hook, is_enabled) => {
+                self.config.on_save.retain(|enabled| *enabled != hook);
+
+                if is_enabled {
+                    self.config.on_save.push(hook);
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::TreeSitterToggled(extension, is_enabled) => {
+                self.config.tree_sitter.retain(|picked| picked != extension);
+
+                if is_enabled {
+                    self.config.tree_sitter.push(extension.to_owned());
+                }
+
+                let text = self.content.text();
+                self.reparse(&text);
+
+                self.update(Message::SaveConfig)
+            }
+            Message::MiddleClickPasteToggled(middle_click_paste) => {
+                self.config.middle_click_paste = middle_click_paste;
+                self.update(Message::SaveConfig)
+            }
+            Message::QuadrupleClickToggled(quadruple_click_paragraph) => {
+                self.config.quadruple_click_paragraph = quadruple_click_paragraph;
+                self.update(Message::SaveConfig)
+            }
+            Message::PastePrimary => match self.primary.clone() {
+                Some(primary) if self.config.middle_click_paste => self.paste(&primary),
+                _ => Command::none(),
+            },
+            Message::AskBeforeOverwriteToggled(ask_before_overwrite) => {
+                self.config.ask_before_overwrite = ask_before_overwrite;
+                self.update(Message::SaveConfig)
+            }
+            Message::CreateDirectoriesToggled(create_directories) => {
+                self.config.create_directories = create_directories;
+                self.update(Message::SaveConfig)
+            }
+            Message::OpenScratch if self.is_scratch => Command::none(),
+            Message::OpenScratch => {
+                let command = self.update(Message::New);
+                let text = if self.config.keep_scratch {
+                    self.session.scratch.clone()
+                } else {
+                    String::new()
+                };
+
+                self.is_scratch = true;
+                self.untitled_id = None;
+                self.is_dirty = false;
+                self.indent = indent::detect(&text).unwrap_or(self.config.indent());
+                self.show(&text, 0, 0);
+
+                command
+            }
+            Message::KeepScratchToggled(keep_scratch) => {
+                self.config.keep_scratch = keep_scratch;
+                self.update(Message::SaveConfig)
+            }
+            Message::RecoverNewFilesToggled(recover_new_files) => {
+                self.config.recover_new_files = recover_new_files;
+                self.update(Message::SaveConfig)
+            }
+            Message::RestoreRecovered => {
+                if self.recovered.is_empty() {
+                    return Command::none();
+                }
+
+                let buffer = self.recovered.remove(0);
+
+                let text = match buffer.read() {
+                    Ok(text) => text,
+                    Err(error) => {
+                        self.error = Some(EditorError::IO(error.kind()));
+                        return Command::none();
+                    }
+                };
+
+                let command = self.update(Message::New);
+
+                // It keeps being copied to where it was recovered from
+                self.untitled_id = Some(buffer.id);
+                self.indent = indent::detect(&text).unwrap_or(self.config.indent());
+                self.show(&text, 0, 0);
+
+                command
+            }
+            Message::DiscardRecovered => {
+                for buffer in self.recovered.drain(..) {
+                    recovery::remove(&buffer.id);
+                }
+
+                Command::none()
+            }
+            Message::KeepScratch(generation) => {
+                if generation == self.scratch_generation && self.is_scratch {
+                    self.keep_scratch();
+                }
+
+                Command::none()
+            }
+            Message::KeepBackupToggled(keep_backup) => {
+                self.config.keep_backup = keep_backup;
+                self.update(Message::SaveConfig)
+            }
+            Message::NormalizeOnPasteToggled(normalize_on_paste) => {
+                self.config.normalize_on_paste = normalize_on_paste;
+                self.update(Message::SaveConfig)
+            }
+            Message::CleanUpUnicode => {
+                if self.is_view_only() {
+                    return Command::none();
+                }
+
+                let (line, column) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+                let text = unicode::clean(&self.text());
+
+                self.folds.clear();
+                self.show(&text, line, column);
+                self.is_dirty = true;
+                self.notify_language_server();
+
+                Command::none()
+            }
+            Message::CollapseBlankLines => {
+                if self.is_view_only() {
+                    return Command::none();
+                }
+
+                let text = self.text();
+                let collapsed =
+                    save::collapse_blank_lines(&text, usize::from(self.config.max_blank_lines));
+
+                if collapsed != text {
+                    // The cursor stays on its line, which moves up by the
+                    // blank lines dropped above it
+                    let (line, column) = self.content.cursor_position();
+                    let line = self.folds.real_line(line);
+                    let above = save::collapse_blank_lines(
+                        &text.split_inclusive('\n').take(line).collect::<String>(),
+                        usize::from(self.config.max_blank_lines),
+                    );
+
+                    self.folds.clear();
+                    self.show(&collapsed, above.matches('\n').count(), column);
+                    self.is_dirty = true;
+                    self.notify_language_server();
+                }
+
+                Command::none()
+            }
+            Message::MaxBlankLinesChanged(max_blank_lines) => {
+                self.config.max_blank_lines = max_blank_lines;
+
+                Command::none()
+            }
+            Message::MaxBufferChanged(max_buffer_mib) => {
+                self.config.max_buffer_mib = max_buffer_mib;
+
+                Command::none()
+            }
+            Message::TurnPage(is_forward) => {
+                let (Some(path), Some(page)) = (self.path.clone(), self.page) else {
+                    return Command::none();
+                };
+
+                // A page before starts at a line, while the next one goes on
+                // the line of a page that is all one line
+                let start = if is_forward {
+                    page.end
+                } else {
+                    page.start.saturating_sub(PAGE_SIZE)
+                };
+
+                if start >= page.total || start == page.start {
+                    return Command::none();
+                }
+
+                let encoding = (!self.is_binary).then_some(self.encoding);
+
+                self.open(load_page(path, start, !is_forward, encoding))
+            }
+            Message::InsertUnicode => {
+                self.code_point = Some(String::new());
+                text_input::focus(code_point_input())
+            }
+            Message::CodePointChanged(code_point) => {
+                self.code_point = Some(code_point);
+                Command::none()
+            }
+            Message::CodePointSubmitted => {
+                match self.code_point.as_deref().and_then(unicode::parse_code_point) {
+                    Some(c) => {
+                        self.code_point = None;
+                        self.edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)))
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::SelectTagContents | Message::RenameTag
+                if !emmet::is_markup(self.extension()) =>
+            {
+                Command::none()
+            }
+            Message::SelectTagContents => {
+                let text = self.content.text();
+                let offset = self.selected_range(&text).start;
+                let is_html = self.extension() != "xml";
+
+                if let Some(pair) = tag::at(&text, offset, is_html)
+                    .or_else(|| tag::enclosing(&text, offset, is_html))
+                {
+                    self.select_range(&text, pair.contents());
+                }
+
+                Command::none()
+            }
+            Message::RenameTag => {
+                let text = self.content.text();
+                let (line, column) = self.content.cursor_position();
+                let offset = scope::offset(&text, line, column);
+
+                if let Some(pair) = tag::at(&text, offset, self.extension() != "xml") {
+                    self.tag_name = Some(text[pair.open.name].to_owned());
+
+                    return text_input::focus(tag_name_input());
+                }
+
+                Command::none()
+            }
+            Message::TagNameChanged(name) => {
+                self.tag_name = Some(name);
+                Command::none()
+            }
+            Message::TagNameSubmitted => {
+                let Some(name) = self.tag_name.take().filter(|name| !name.is_empty()) else {
+                    return Command::none();
+                };
+
+                if self.is_view_only() {
+                    return Command::none();
+                }
+
+                let text = self.text();
+                let (line, column) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+                let offset = scope::offset(&text, line, column);
+
+                if let Some(pair) = tag::at(&text, offset, self.extension() != "xml") {
+                    let renamed = tag::rename(&text, &pair, &name);
+
+                    self.folds.clear();
+                    self.show(&renamed, line, column);
+                    self.is_dirty = true;
+                    self.notify_language_server();
+                }
+
+                Command::none()
+            }
+            Message::EditNote => {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+                let text = self
+                    .notes
+                    .iter()
+                    .find(|note| note.anchor.line == line)
+                    .map_or(String::new(), |note| note.text.clone());
+
+                self.note = Some((line, text));
+
+                text_input::focus(note_input())
+            }
+            Message::NoteChanged(text) => {
+                if let Some((_, note)) = self.note.as_mut() {
+                    *note = text;
+                }
+
+                Command::none()
+            }
+            Message::NoteSubmitted => {
+                let Some((line, text)) = self.note.take() else {
+                    return Command::none();
+                };
+
+                self.notes.retain(|note| note.anchor.line != line);
+
+                // Emptying a note removes it
+                if !text.trim().is_empty() {
+                    self.notes.push(session::Note {
+                        text,
+                        anchor: session::Anchor {
+                            line,
+                            text: String::new(),
+                        },
+                    });
+                    self.notes.sort_by_key(|note| note.anchor.line);
+                }
+
+                self.remember_notes();
+
+                Command::none()
+            }
+            Message::RemoveNote => {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                self.note = None;
+                self.notes.retain(|note| note.anchor.line != line);
+                self.remember_notes();
+
+                Command::none()
+            }
+            Message::NextNote => {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                let next = self
+                    .notes
+                    .iter()
+                    .map(|note| note.anchor.line)
+                    .find(|noted| *noted > line)
+                    .or_else(|| self.notes.first().map(|note| note.anchor.line));
+
+                if let Some(next) = next {
+                    self.history.push(self.location());
+                    self.jump_to(next, 0);
+                }
+
+                Command::none()
+            }
+            Message::Surround => {
+                self.surrounding = (self.content.selection().is_some() && !self.is_view_only())
+                    .then_some(Surrounding::Selection);
+
+                Command::none()
+            }
+            Message::ChangeSurrounding => {
+                self.surrounding = (self.surrounding_pair().is_some() && !self.is_view_only())
+                    .then_some(Surrounding::Pair);
+
+                Command::none()
+            }
+            Message::DeleteSurrounding => {
+                self.surround(Surrounding::Pair, "", "");
+                Command::none()
+            }
+            Message::SurroundTagChanged(tag) => {
+                if let Some((_, typed)) = &mut self.surround_tag {
+                    *typed = tag;
+                }
+
+                Command::none()
+            }
+            Message::SurroundTagSubmitted => {
+                if let Some((surrounding, tag)) =
+                    self.surround_tag.take().filter(|(_, tag)| !tag.trim().is_empty())
+                {
+                    let (open, close) = surround::tag(&tag);
+                    self.surround(surrounding, &open, &close);
+                }
+
+                Command::none()
+            }
+            Message::DescribeCharacter => {
+                let (line, column) = self.content.cursor_position();
+
+                let character = self
+                    .content
+                    .line(line)
+                    .and_then(|text| text.get(column..)?.chars().next());
+
+                self.hover = character.map(unicode::describe);
+                self.hover_position = None;
+
+                Command::none()
+            }
+            Message::Transform(transform) => {
+                let Some(selection) = self.content.selection() else {
+                    self.error = Some(EditorError::Transform(
+                        "Select the text to transform".to_owned(),
+                    ));
+
+                    return Command::none();
+                };
+
+                match transform.apply(&selection) {
+                    Ok(text) => self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+                        Arc::new(text),
+                    ))),
+                    Err(error) => {
+                        self.error = Some(EditorError::Transform(error));
+
+                        Command::none()
+                    }
+                }
+            }
+            Message::CsvAlignedToggled(is_aligned) => {
+                let Some(delimiter) = csv::delimiter(self.extension()) else {
+                    return Command::none();
+                };
+
+                let (line, column) = self.content.cursor_position();
+                let current = self
+                    .content
+                    .line(line)
+                    .map(|text| text.to_string())
+                    .unwrap_or_default();
+
+                if is_aligned && self.csv_raw.is_none() {
+                    let raw = self.text();
+                    let line = self.folds.real_line(line);
+                    let fields = csv::fields(&current, delimiter);
+                    let index = csv::field_at(&current, column, delimiter);
+                    let offset = column - fields[index].start;
+
+                    let aligned = csv::align(&raw, delimiter);
+                    let column = aligned
+                        .split('\n')
+                        .nth(line)
+                        .and_then(|text| csv::aligned_fields(text).get(index).cloned())
+                        .map_or(0, |field| field.start + offset);
+
+                    self.folds.clear();
+                    self.show(&aligned, line, column);
+                    self.csv_raw = Some(raw);
+                } else if !is_aligned {
+                    if let Some(raw) = self.csv_raw.take() {
+                        let column = raw.split('\n').nth(line).map_or(0, |text| {
+                            csv::raw_column(&current, text, column, delimiter)
+                        });
+
+                        self.show(&raw, line, column);
+                    }
+                }
+
+                Command::none()
+            }
+            Message::ToggleSort => {
+                self.sort = match self.sort.take() {
+                    Some(_) => None,
+                    None => Some(sort::State::default()),
+                };
+
+                Command::none()
+            }
+            Message::SortOrderSelected(order) => {
+                if let Some(state) = self.sort.as_mut() {
+                    state.order = order;
+                }
+
+                Command::none()
+            }
+            Message::SortDelimiterChanged(delimiter) => {
+                if let Some(state) = self.sort.as_mut() {
+                    state.delimiter = delimiter;
+                }
+
+                Command::none()
+            }
+            Message::SortFieldChanged(field) => {
+                if let Some(state) = self.sort.as_mut() {
+                    state.field = field;
+                }
+
+                Command::none()
+            }
+            Message::SortLines => {
+                let Some(state) = self.sort.clone() else {
+                    return Command::none();
+                };
+
+                self.sort_lines(&state)
+            }
+            Message::ExpandSelection => {
+                let text = self.content.text();
+                let current = self.selected_range(&text);
+
+                if self.scopes.last() != Some(&current) {
+                    self.scopes = vec![current.clone()];
+                }
+
+                let range = scope::expand(&text, current.clone()).unwrap_or(current);
+
+                if self.scopes.last() != Some(&range) {
+                    self.scopes.push(range.clone());
+                }
+
+                self.select_range(&text, range);
+
+                Command::none()
+            }
+            Message::SelectLine => {
+                let text = self.content.text();
+                let selected = self.selected_range(&text);
+
+                // A selection of whole lines grows by the next one
+                let range =
+                    scope::line(&text, selected.start).start..scope::line(&text, selected.end).end;
+
+                self.select_range(&text, range);
+
+                Command::none()
+            }
+            Message::SelectParagraph => {
+                let text = self.content.text();
+                let selected = self.selected_range(&text);
+
+                self.select_range(&text, scope::paragraph(&text, selected.start));
+
+                Command::none()
+            }
+            message @ (Message::TransposeCharacters | Message::TransposeWords) => {
+                if self.content.selection().is_some() {
+                    return Command::none();
+                }
+
+                let (line, column) = self.content.cursor_position();
+                let text = self
+                    .content
+                    .line(line)
+                    .map(|text| text.to_string())
+                    .unwrap_or_default();
+
+                let swap = if matches!(message, Message::TransposeCharacters) {
+                    transpose::characters(&text, column)
+                } else {
+                    transpose::words(&text, column)
+                };
+
+                let Some(swap) = swap else {
+                    return Command::none();
+                };
+
+                cursor::move_to(&mut self.content, line, swap.range.start);
+                cursor::select_to(&mut self.content, line, swap.range.end);
+
+                self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(
+                    swap.text,
+                ))))
+            }
+            Message::CropToSelection => {
+                if self.content.selection().is_none() {
+                    return Command::none();
+                }
+
+                let display = self.content.text();
+                let selected = self.selected_range(&display);
+                self.select_range(&display, selected.clone());
+
+                // Folded lines inside the selection are kept too
+                let text = self.text();
+                let offset = |offset| {
+                    let (line, column) = scope::position(&display, offset);
+                    scope::offset(&text, self.folds.real_line(line), column)
+                };
+                let range = offset(selected.start)..offset(selected.end);
+
+                let discarded = text[..range.start].matches('\n').count()
+                    + text[range.end..].matches('\n').count();
+
+                // Without a file, there is no local history to get the rest
+                // back from
+                if discarded > LARGE_CROP_LINES || self.path.is_none() {
+                    self.crop = Some(range);
+
+                    Command::none()
+                } else {
+                    self.crop_to(range)
+                }
+            }
+            Message::ConfirmDeletion => {
+                self.deletion = None;
+
+                if self.content.selection().is_none() {
+                    return Command::none();
+                }
+
+                self.edit(text_editor::Action::Edit(text_editor::Edit::Delete))
+            }
+            Message::CancelDeletion => {
+                self.deletion = None;
+
+                Command::none()
+            }
+            Message::ConfirmReplacedLinesChanged(lines) => {
+                match threshold(&lines) {
+                    Some(threshold) => self.config.confirm.replaced_lines = threshold,
+                    None => return Command::none(),
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::ConfirmDeletedLinesChanged(lines) => {
+                match threshold(&lines) {
+                    Some(threshold) => self.config.confirm.deleted_lines = threshold,
+                    None => return Command::none(),
+                }
+
+                self.update(Message::SaveConfig)
+            }
+            Message::ConfirmCrop => match self.crop.take() {
+                Some(range) => self.crop_to(range),
+                None => Command::none(),
+            },
+            Message::CancelCrop => {
+                self.crop = None;
+
+                Command::none()
+            }
+            Message::ShrinkSelection => {
+                let text = self.content.text();
+                let current = self.selected_range(&text);
+
+                let range = if self.scopes.len() > 1 && self.scopes.last() == Some(&current) {
+                    let _ = self.scopes.pop();
+                    self.scopes.last().cloned().unwrap_or(current)
+                } else {
+                    self.scopes.clear();
+                    current
+                };
+
+                self.select_range(&text, range);
+
+                Command::none()
+            }
+            Message::IncrementNumber => self.add_to_numbers(1),
+            Message::DecrementNumber => self.add_to_numbers(-1),
+            Message::OrganizeImports => {
+                if self.extension() != "rs" || self.is_view_only() {
+                    return Command::none();
+                }
+
+                let text = self.text();
+
+                match imports::organize(&text) {
+                    Ok(organized) if organized != text => {
+                        let (line, column) = self.content.cursor_position();
+                        let line = self.folds.real_line(line);
+
+                        self.folds.clear();
+                        self.show(&organized, line, column);
+                        self.is_dirty = true;
+                        self.notify_language_server();
+                    }
+                    Ok(_) => {}
+                    Err(error) => self.error = Some(EditorError::Transform(error)),
+                }
+
+                Command::none()
+            }
+            Message::GenerateDocComment => {
+                if self.extension() != "rs" || self.is_view_only() {
+                    return Command::none();
+                }
+
+                let text = self.text();
+                let line = self.folds.real_line(self.content.cursor_position().0);
+                let lines: Vec<&str> = text.split('\n').collect();
+
+                let Some((at, comment)) = doc::stub(&lines, line) else {
+                    return Command::none();
+                };
+
+                let column = comment[0].len();
+                let commented = lines[..at]
+                    .iter()
+                    .copied()
+                    .chain(comment.iter().map(String::as_str))
+                    .chain(lines[at..].iter().copied())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                // Ready to write the summary
+                self.folds.clear();
+                self.show(&commented, at, column);
+                self.is_dirty = true;
+                self.notify_language_server();
+
+                Command::none()
+            }
+            Message::FormatJson => self.reformat_json(json::pretty),
+            Message::MinifyJson => self.reformat_json(json::minify),
+            Message::ModifiedKeyPressed(key_code, modifiers) => {
+                // The editor copies and cuts by itself, so catch up with the
+                // clipboard to remember what it took
+                let copied = if modifiers.command()
+                    && matches!(key_code, keyboard::KeyCode::C | keyboard::KeyCode::X)
+                {
+                    iced::clipboard::read(Message::ClipboardRead)
+                } else {
+                    Command::none()
+                };
+
+                // Vim increments with Ctrl+A and decrements with Ctrl+X,
+                // which cuts nothing without a selection; elsewhere they
+                // take Shift, to leave selecting all and cutting alone
+                if modifiers.command()
+                    && !modifiers.shift()
+                    && self.content.selection().is_none()
+                    && self.vim.as_ref().is_some_and(|vim| vim.mode != vim::Mode::Insert)
+                {
+                    match key_code {
+                        keyboard::KeyCode::A => return self.update(Message::IncrementNumber),
+                        keyboard::KeyCode::X => return self.update(Message::DecrementNumber),
+                        _ => {}
+                    }
+                }
+
+                let Some(emacs) = self
+                    .emacs
+                    .as_mut()
+                    .filter(|_| modifiers.control() || modifiers.alt())
+                else {
+                    return copied;
+                };
+
+                let command = match emacs.key(key_code, modifiers, &self.content) {
+                    Some(outputs) => self.run_keymap(outputs),
+                    None => match key_binding(key_code, modifiers) {
+                        Some(message) => self.update(message),
+                        None => Command::none(),
+                    },
+                };
+
+                Command::batch([copied, command])
+            }
+            Message::ClipboardRead(text) => {
+                if let Some(text) = text {
+                    self.kill_ring.push(text);
+                }
+
+                Command::none()
+            }
+            Message::CyclePaste => {
+                let Some((index, position)) = self.last_paste else {
+                    return Command::none();
+                };
+
+                if self.content.cursor_position() != position || self.kill_ring.len() < 2 {
+                    return Command::none();
+                }
+
+                for _ in self.kill_ring.get(index).unwrap_or_default().chars() {
+                    self.content
+                        .edit(text_editor::Action::Select(text_editor::Motion::Left));
+                }
+
+                let next = (index + 1) % self.kill_ring.len();
+                let text = self.kill_ring.get(next).unwrap_or_default().to_owned();
+
+                self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(
+                    text,
+                ))))
+            }
+            Message::ToggleKillRing => {
+                self.is_kill_ring_open = !self.is_kill_ring_open && !self.kill_ring.is_empty();
+                Command::none()
+            }
+            Message::KillRingPicked(index) => {
+                self.is_kill_ring_open = false;
+
+                match self.kill_ring.get(index) {
+                    Some(text) => {
+                        let text = text.to_owned();
+
+                        self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+                            Arc::new(text),
+                        )))
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::Quit if self.has_unsaved_changes() => {
+                self.is_quitting = true;
+
+                Command::none()
+            }
+            Message::Quit | Message::ConfirmQuit => window::close(),
+            Message::CancelQuit => {
+                self.is_quitting = false;
+
+                Command::none()
+            }
+            Message::ResolveConflict(choice) => {
+                let Some(conflict) = self.current_conflict().filter(|_| !self.is_view_only())
+                else {
+                    return Command::none();
+                };
+
+                let text = conflict::resolve(&self.text(), conflict, choice);
+
+                self.folds.clear();
+                self.show(&text, conflict.start, 0);
+                self.is_dirty = true;
+                self.notify_language_server();
+
+                Command::none()
+            }
+            message @ (Message::NextConflict | Message::PreviousConflict) => {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                let target = if matches!(message, Message::NextConflict) {
+                    self.conflicts
+                        .iter()
+                        .find(|conflict| conflict.start > line)
+                        .or(self.conflicts.first())
+                } else {
+                    self.conflicts
+                        .iter()
+                        .rev()
+                        .find(|conflict| conflict.start < line)
+                        .or(self.conflicts.last())
+                };
+
+                if let Some(start) = target.map(|conflict| conflict.start) {
+                    self.history.push(self.location());
+                    self.jump_to(start, 0);
+                }
+
+                Command::none()
+            }
+            Message::CompareWith => {
+                Command::perform(
+                    pick_file(
+                        self.dialog_directory(),
+                        self.config.file_filters.clone(),
+                        self.config.max_buffer_size(),
+                    ),
+                    Message::CompareOpened,
+                )
+            }
+            Message::CompareOpened(Ok(opened)) => {
+                let rows = compare::side_by_side(&self.text(), &opened.content);
+                self.comparison = Some((opened.path, rows));
+
+                Command::none()
+            }
+            Message::CompareOpened(Err(error)) => {
+                self.error = Some(error);
+
+                Command::none()
+            }
+            Message::CloseComparison => {
+                self.comparison = None;
+
+                Command::none()
+            }
+            Message::ToggleLocalHistory => {
+                self.local_history = match (self.local_history.take(), self.path.as_deref()) {
+                    (None, Some(path)) => Some(local_history::State {
+                        snapshots: local_history::list(path),
+                        selected: None,
+                        diff: Vec::new(),
+                    }),
+                    _ => None,
+                };
+
+                Command::none()
+            }
+            Message::ToggleTodos => {
+                if self.todos.take().is_some() {
+                    return Command::none();
+                }
+
+                self.todos = Some(Vec::new());
+
+                // Without a file, there is no folder to look in
+                let Some(path) = self.path.clone() else {
+                    return Command::none();
+                };
+
+                let keywords = self.config.todo_markers.keys().cloned().collect();
+
+                Command::perform(
+                    todo::search(self.root(), keywords, Some(path)),
+                    Message::TodosFound,
+                )
+            }
+            Message::TodosFound(found) => {
+                if let Some(todos) = self.todos.as_mut() {
+                    *todos = found;
+                }
+
+                Command::none()
+            }
+            Message::TodoPicked(path, line) => {
+                self.history.push(self.location());
+
+                match path {
+                    Some(path) => self.go_to(path, line, 0),
+                    None => {
+                        self.jump_to(line, 0);
+                        Command::none()
+                    }
+                }
+            }
+            Message::ToggleTemplates => {
+                self.templates = match self.templates {
+                    Some(_) => None,
+                    None => Some(template::list()),
+                };
+
+                Command::none()
+            }
+            Message::TemplatePicked(index) => {
+                let Some(template) = self
+                    .templates
+                    .take()
+                    .and_then(|templates| templates.into_iter().nth(index))
+                else {
+                    return Command::none();
+                };
+
+                let text = match template.read() {
+                    Ok(text) => text,
+                    Err(error) => {
+                        self.error = Some(EditorError::IO(error.kind()));
+                        return Command::none();
+                    }
+                };
+
+                let command = self.update(Message::New);
+
+                self.language = Some(template.extension);
+                self.indent = indent::detect(&text).unwrap_or(self.config.indent());
+                self.show(&text, 0, 0);
+
+                command
+            }
+            Message::SnapshotSelected(index) => {
+                let current = self.text();
+
+                if let Some(state) = self.local_history.as_mut() {
+                    match state.snapshots.get(index).map(local_history::Snapshot::read) {
+                        Some(Ok(text)) => {
+                            state.selected = Some((index, text));
+                            state.compare(&current);
+                        }
+                        Some(Err(error)) => self.error = Some(EditorError::IO(error.kind())),
+                        None => {}
+                    }
+                }
+
+                Command::none()
+            }
+            Message::RestoreSnapshot => {
+                let Some((_, snapshot)) = self
+                    .local_history
+                    .take()
+                    .and_then(|state| state.selected)
+                else {
+                    return Command::none();
+                };
+
+                let (line, column) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                self.folds.clear();
+                self.show(&snapshot, line, column);
+                self.is_dirty = true;
+                self.notify_language_server();
+
+                Command::none()
+            }
+            Message::TakeSnapshot => {
+                if self.is_scratch {
+                    self.keep_scratch();
+                }
+
+                if let Some(id) = self
+                    .untitled_id
+                    .as_deref()
+                    .filter(|_| self.is_dirty && self.config.recover_new_files)
+                {
+                    let text = self.text();
+
+                    if !text.is_empty() {
+                        let _ = recovery::save(id, &text);
+                    }
+                }
+
+                match self.path.clone().filter(|_| self.is_dirty) {
+                    Some(path) => Command::perform(
+                        local_history::save(path, self.text()),
+                        Message::SnapshotKept,
+                    ),
+                    None => Command::none(),
+                }
+            }
+            Message::SnapshotKept(result) => {
+                if result.is_ok() {
+                    self.snapshot_at = Some(SystemTime::now());
+                }
+
+                Command::none()
+            }
+            Message::ToggleAbout => {
+                self.is_about_open = !self.is_about_open;
+
+                Command::none()
+            }
+            Message::ToggleShortcuts => {
+                self.is_shortcuts_open = !self.is_shortcuts_open;
+
+                Command::none()
+            }
+            // What runs is the file on disk, so it has to have the edits first
+            Message::Run if self.has_unsaved_changes() && !self.is_view_only() => {
+                self.run_after_save = true;
+                self.update(Message::Save)
+            }
+            Message::Run => {
+                let Some(path) = self.path.clone() else {
+                    return Command::none();
+                };
+
+                let extension = self.extension().to_owned();
+
+                let Some(invocation) = self
+                    .config
+                    .run_commands
+                    .get(&extension)
+                    .and_then(|template| run::Invocation::new(template, &path))
+                else {
+                    self.error = Some(EditorError::NoRunCommand(extension));
+                    return Command::none();
+                };
+
+                self.session.layout.is_output_open = true;
+                self.save_layout();
+                self.append_output(output::Line::new(format!("$ {invocation}")));
+
+                // A newer run replaces the subscription, which kills the old
+                self.run_generation += 1;
+                self.run = Some(run::State::new(self.run_generation, invocation));
+
+                Command::none()
+            }
+            Message::RunOutput(output) => {
+                let Some(state) = self
+                    .run
+                    .as_mut()
+                    .filter(|state| state.generation == output.generation)
+                else {
+                    return Command::none();
+                };
+
+                let line = match output.event {
+                    run::Event::Line(line) => {
+                        output::Line::of_tool(line, &state.invocation.directory)
+                    }
+                    run::Event::Finished(outcome) => {
+                        state.outcome = Some(outcome);
+
+                        output::Line::new(format!("[{outcome}]"))
+                    }
+                };
+
+                self.append_output(line);
+
+                Command::none()
+            }
+            Message::CancelTask(task) => {
+                // Dropping the subscription of the task kills its process
+                match task {
+                    Task::Run => {
+                        if let Some(state) =
+                            self.run.as_mut().filter(|state| state.outcome.is_none())
+                        {
+                            state.outcome = Some(run::Outcome::Exited(None));
+                            self.append_output(output::Line::new("[Killed]"));
+                        }
+                    }
+                    Task::Check => {
+                        if self.check.take().is_some() {
+                            self.append_output(output::Line::new("[cargo check: cancelled]"));
+                        }
+                    }
+                }
+
+                Command::none()
+            }
+            Message::AppendOutput(line) => {
+                self.append_output(line);
+
+                Command::none()
+            }
+            Message::ToggleOutput => {
+                self.session.layout.is_output_open = !self.session.layout.is_output_open;
+                self.save_layout();
+
+                Command::none()
+            }
+            Message::OutputHeightChanged(height) => {
+                self.session.layout.output_height = height.clamp(
+                    *session::OUTPUT_HEIGHTS.start(),
+                    *session::OUTPUT_HEIGHTS.end(),
+                );
+
+                Command::none()
+            }
+            Message::SaveLayout => {
+                self.save_layout();
+                Command::none()
+            }
+            Message::ResetLayout => {
+                self.session.layout = session::Layout::default();
+                self.save_layout();
+
+                Command::none()
+            }
+            Message::ClearOutput => {
+                self.output.clear();
+                self.output_content = text_editor::Content::new();
+
+                Command::none()
+            }
+            Message::OutputAction(action) => {
+                if action.is_edit() {
+                    return Command::none();
+                }
+
+                let is_double_click = matches!(action, text_editor::Action::SelectWord);
+
+                self.output_content.edit(action);
+
+                let (line, _) = self.output_content.cursor_position();
+
+                match self.output.get(line).and_then(|line| line.location.clone()) {
+                    Some(location) if is_double_click => {
+                        self.go_to(location.path, location.line, location.character)
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::CopyDiagnostics => iced::clipboard::write(diagnostics_report()),
+            Message::MenuToggled(menu) => {
+                self.menu = if self.menu == Some(menu) {
+                    None
+                } else {
+                    Some(menu)
+                };
+
+                Command::none()
+            }
+            Message::MenuPicked(message) => {
+                self.menu = None;
+                self.update(*message)
+            }
+            Message::OpenContextMenu => {
+                self.is_context_menu_open = true;
+
+                Command::none()
+            }
+            Message::ContextMenuPicked(item) => {
+                self.is_context_menu_open = false;
+
+                let selection = self.content.selection();
+
+                match item {
+                    MenuItem::Cut | MenuItem::Copy => {
+                        let Some(selection) = selection else {
+                            return Command::none();
+                        };
+
+                        self.kill_ring.push(selection.clone());
+
+                        let copied = iced::clipboard::write(selection);
+
+                        if item == MenuItem::Cut {
+                            Command::batch([
+                                copied,
+                                self.edit(text_editor::Action::Edit(text_editor::Edit::Delete)),
+                            ])
+                        } else {
+                            copied
+                        }
+                    }
+                    MenuItem::Paste => iced::clipboard::read(Message::ClipboardPasted),
+                    MenuItem::SelectAll => Command::batch([
+                        self.edit(text_editor::Action::Move(text_editor::Motion::DocumentStart)),
+                        self.edit(text_editor::Action::Select(text_editor::Motion::DocumentEnd)),
+                    ]),
+                    MenuItem::GoToDefinition => self.update(Message::GoToDefinition),
+                    MenuItem::GenerateDocComment => self.update(Message::GenerateDocComment),
+                    MenuItem::Transform(transform) => self.update(Message::Transform(transform)),
+                }
+            }
+            Message::ClipboardPasted(text) => match text {
+                Some(text) => {
+                    self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))))
+                }
+                None => Command::none(),
+            },
+            Message::Escape => {
+                if !self.completions.is_empty()
+                    || self.hover.is_some()
+                    || self.is_kill_ring_open
+                    || self.is_context_menu_open
+                    || self.menu.is_some()
+                    || self.code_point.is_some()
+                    || self.tag_name.is_some()
+                    || self.surrounding.is_some()
+                    || self.surround_tag.is_some()
+                    || self.go_to.is_some()
+                    || self.note.is_some()
+                {
+                    self.completions.clear();
+                    self.hover = None;
+                    self.is_kill_ring_open = false;
+                    self.is_context_menu_open = false;
+                    self.menu = None;
+                    self.code_point = None;
+                    self.tag_name = None;
+                    self.surrounding = None;
+                    self.surround_tag = None;
+                    self.go_to = None;
+                    self.note = None;
+                } else if let Some(vim) = self
+                    .vim
+                    .as_mut()
+                    .filter(|vim| vim.command.is_some() || vim.mode != vim::Mode::Normal)
+                {
+                    vim.command = None;
+
+                    let outputs = vim.escape();
+
+                    return self.run_keymap(outputs);
+                } else if self.find.is_some() {
+                    self.close_find();
+                } else if self.sort.is_some() {
+                    self.sort = None;
+                } else if self.is_encoding_open {
+                    self.is_encoding_open = false;
+                } else if self.crop.is_some() {
+                    self.crop = None;
+                } else if self.deletion.is_some() {
+                    self.deletion = None;
+                } else if self.highlighted_lines.is_some() {
+                    self.highlighted_lines = None;
+                } else if self.overwrite.is_some() {
+                    return self.update(Message::CancelOverwrite);
+                } else if self.switch_to.is_some() {
+                    return self.update(Message::CancelSwitchFile);
+                } else if self.is_quitting {
+                    self.is_quitting = false;
+                } else if self.reopen_as.is_some() {
+                    self.reopen_as = None;
+                } else {
+                    self.is_settings_open = false;
+                    self.is_about_open = false;
+                    self.is_shortcuts_open = false;
+                }
+
+                Command::none()
+            }
+            Message::Lsp(lsp::Event::Disconnected) => {
+                if let Some(state) = self.lsp.as_mut() {
+                    state.connection = None;
+                    state.document = None;
+                }
+
+                Command::none()
+            }
+            Message::CompletionSelected(completion) => {
+                let prefix = self.word_before_cursor();
+                let mut commands = Vec::new();
+
+                for _ in prefix.chars() {
+                    commands.push(self.edit(text_editor::Action::Edit(
+                        text_editor::Edit::Backspace,
+                    )));
+                }
+
+                commands.push(self.edit(text_editor::Action::Edit(
+                    text_editor::Edit::Paste(Arc::new(completion)),
+                )));
+
+                self.completions.clear();
+
+                Command::batch(commands)
+            }
+            Message::OpenGoTo => {
+                self.go_to = Some(String::new());
+                self.focus(Region::GoTo)
+            }
+            Message::CycleFocus(is_backwards) => {
+                let regions = self.regions();
+                let count = regions.len();
+                let current = regions
+                    .iter()
+                    .position(|region| *region == self.region)
+                    .unwrap_or(0);
+
+                let next = if is_backwards {
+                    (current + count - 1) % count
+                } else {
+                    (current + 1) % count
+                };
+
+                self.focus(regions[next])
+            }
+            Message::GoToChanged(go_to) => {
+                self.go_to = Some(go_to);
+                Command::none()
+            }
+            Message::GoToSubmitted => {
+                if let Some(offset) = self.go_to.as_deref().and_then(location::parse_offset) {
+                    let text = self.text();
+                    let mut offset = offset.resolve(text.len());
+
+                    while !text.is_char_boundary(offset) {
+                        offset -= 1;
+                    }
+
+                    let (line, column) = scope::position(&text, offset);
+                    let character = text.split('\n').nth(line).map_or(0, |line| {
+                        line[..column].chars().count()
+                    });
+
+                    self.go_to = None;
+                    self.history.push(self.location());
+                    self.jump_to(line, character);
+
+                    return Command::none();
+                }
+
+                let Some(reference) = self.go_to.as_deref().and_then(location::parse) else {
+                    return Command::none();
+                };
+
+                self.go_to = None;
+                self.open_reference(reference)
+            }
+            Message::GoToLine(line) => {
+                self.history.push(self.location());
+
+                let indentation = self.content.line(line).map_or(0, |text| {
+                    text.len() - text.trim_start().len()
+                });
+
+                cursor::move_to(&mut self.content, line, indentation);
+                self.scroll(0);
+                self.refresh_breadcrumbs();
+
+                Command::none()
+            }
+        }
+    }
+
+    fn subscription(&self) -> iced::Subscription<Self::Message> {
+        let events = subscription::events_with(|event, status| match event {
+            // The text editor ignores the right button
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right))
+                if status == event::Status::Ignored =>
+            {
+                Some(Message::OpenContextMenu)
+            }
+            // And the middle one
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Middle))
+                if status == event::Status::Ignored =>
+            {
+                Some(Message::PastePrimary)
+            }
+            Event::Window(window::Event::Resized { height, .. }) => {
+                Some(Message::WindowResized(height))
+            }
+            Event::Window(window::Event::CloseRequested) => Some(Message::Quit),
+            Event::Window(window::Event::Focused) => Some(Message::WindowFocused(true)),
+            Event::Window(window::Event::Unfocused) => Some(Message::WindowFocused(false)),
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::ModifiersChanged(modifiers))
+            }
+            // The editor swallows Escape to unfocus itself
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Escape,
+                ..
+            }) => Some(Message::Escape),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            }) if modifiers.control() || modifiers.alt() || modifiers.command() => {
+                Some(Message::ModifiedKeyPressed(key_code, modifiers))
+            }
+            // Vim keys typed while the editor is unfocused
+            Event::Keyboard(keyboard::Event::CharacterReceived(c))
+                if status == event::Status::Ignored =>
+            {
+                Some(Message::VimKey(c))
+            }
+            _ => None,
+        });
+
+        let keys = if self.emacs.is_some() {
+            // Control and Alt belong to the Emacs keymap, which falls back
+            // to the usual bindings itself
+            keyboard::on_key_press(|key_code, modifiers| {
+                if modifiers.control() || modifiers.alt() {
+                    None
+                } else {
+                    key_binding(key_code, modifiers)
+                }
+            })
+        } else {
+            keyboard::on_key_press(key_binding)
+        };
+
+        let mut subscriptions = vec![events, keys];
+
+        if let Some(state) = &self.lsp {
+            subscriptions.push(
+                lsp::connect(state.server.clone(), state.root.clone()).map(Message::Lsp),
+            );
+        }
+
+        if let Some(path) = &self.path {
+            // Whatever changed while idle is caught up on when focused again
+            if !self.is_saving && !self.is_idle() {
+                subscriptions.push(
+                    watch::changes(self.disk_generation, path.clone())
+                        .map(Message::FileChangedOnDisk),
+                );
+            }
+
+            // Following was asked for, and starting it over would read the
+            // text appended since it started again
+            if let Some(offset) = self.follow {
+                subscriptions.push(watch::growth(path.clone(), offset).map(Message::FileGrew));
+            }
+        }
+
+        subscriptions.push(iced::time::every(SNAPSHOT_INTERVAL).map(|_| Message::TakeSnapshot));
+
+        if self.scrolling.is_some() && !self.is_idle() {
+            subscriptions.push(iced::time::every(SCROLL_FRAME).map(|_| Message::ScrollFrame));
+        }
+
+        if let Some(state) = self.run.as_ref().filter(|state| state.outcome.is_none()) {
+            subscriptions
+                .push(run::run(state.generation, state.invocation.clone()).map(Message::RunOutput));
+        }
+
+        if let Some((generation, root)) = &self.check {
+            subscriptions
+                .push(diagnostics::check(*generation, root.clone()).map(Message::CheckFinished));
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let controls = row![
+            action(get_icon(Icon::New), "New...", Some(Message::New)),
+            action(get_icon(Icon::Open), "Open...", Some(Message::Open)),
+            action(
+                get_icon(Icon::Save),
+                "Save...",
+                self.is_dirty.then_some(Message::Save)
+            ),
+            action(
+                get_icon(Icon::Replace),
+                "Replace in Files...",
+                Some(Message::ToggleReplaceInFiles)
+            ),
+            action(
+                get_icon(Icon::Settings),
+                "Settings",
+                Some(Message::ToggleSettings)
+            ),
+            horizontal_space(Length::Fill),
+            checkbox(
+                "Rainbow brackets",
+                self.rainbow_brackets,
+                Message::RainbowBracketsToggled
+            ),
+            checkbox(
+                "Language server",
+                self.lsp_enabled,
+                Message::LanguageServerToggled
+            ),
+            checkbox(
+                "Check on save",
+                self.check_on_save,
+                Message::CheckOnSaveToggled
+            ),
+            pick_list(transform::Transform::ALL, None, Message::Transform)
+                .placeholder("Transform..."),
+            pick_list(
+                themes::Choice::ALL,
+                Some(self.theme_choice()),
+                Message::ThemeSelected
+            )
+        ]
+        .spacing(10);
+        let (overlays, spans) = self
+            .held_highlighting
+            .clone()
+            .unwrap_or_else(|| (self.overlays(), self.tree_sitter_spans()));
+
+        let input = text_editor(&self.content)
+            .on_edit(Message::Edit)
+            .highlight::<highlight::Highlighter>(
+                highlight::Settings {
+                    syntax: highlighter::Settings {
+                        theme: self.theme_choice().syntax(),
+                        extension: if self.is_plain {
+                            "txt".to_owned()
+                        } else {
+                            self.extension().to_string()
+                        },
+                    },
+                    spans,
+                    palette: self.theme_choice().palette(),
+                    colors: self.theme_choice().colors(),
+                    overlays,
+                },
+                highlight::to_format,
+            )
+            .font(self.font)
+            .line_height(text::LineHeight::Relative(self.config.line_height))
+            .style(theme::TextEditor::Custom(Box::new(style::Editor {
+                selection: self.config.colors.color(config::Highlight::Selection),
+            })));
+
+        let status_bar = {
+            let status = match self.error.as_ref() {
+                Some(EditorError::IO(error)) => text(error.to_string()),
+                Some(
+                    EditorError::InvalidPattern(error)
+                    | EditorError::Transform(error)
+                    | EditorError::SaveHook(error)
+                    | EditorError::Encoding(error),
+                ) => text(error),
+                Some(EditorError::MissingFont(family)) => text(format!("{family} is not installed")),
+                Some(EditorError::NoRunCommand(extension)) => {
+                    text(format!("No command runs .{extension} files, see run_commands"))
+                }
+                Some(EditorError::Locked(path)) => text(format!(
+                    "{} is locked by another process, close it there to open it",
+                    path.display()
+                )),
+                Some(EditorError::CreateDirectory(directory, error)) => text(format!(
+                    "Could not create {}: {error}",
+                    directory.display()
+                )),
+                Some(EditorError::InvalidJson(line, column)) => text(format!(
+                    "Invalid JSON at line {}, column {}",
+                    line + 1,
+                    column + 1
+                )),
+                _ => match self.path.as_deref().and_then(Path::to_str) {
+                    Some(path) if self.is_missing => {
+                        text(format!("{path} no longer exists on disk")).size(14)
+                    }
+                    Some(path) if self.is_changed_on_disk => {
+                        text(format!("{path} changed on disk")).size(14)
+                    }
+                    Some(path) if self.is_lossy => text(format!(
+                        "{path} (not valid {}, shown read-only: reopen it in its encoding)",
+                        self.encoding
+                    ))
+                    .size(14),
+                    Some(path) if self.page.is_some() => {
+                        text(format!("{path} (too big to open whole, one page shown)")).size(14)
+                    }
+                    Some(path) if self.is_binary => {
+                        text(format!("{path} (binary, shown as hex, read-only)")).size(14)
+                    }
+                    Some(path) if self.is_read_only => {
+                        text(format!("{path} (read-only, saving needs elevated permissions)"))
+                            .size(14)
+                    }
+                    Some(path) => text(path).size(14),
+                    None if self.is_scratch => text("(Scratch, never asks to be saved)"),
+                    None => text("(New File)"),
+                },
+            };
+
+            let elevate = (self.path.is_some()
+                && matches!(
+                    self.error,
+                    Some(EditorError::IO(io::ErrorKind::PermissionDenied))
+                ))
+            .then(|| {
+                button(text("Retry as administrator").size(14))
+                    .on_press(Message::SaveElevated)
+                    .padding(0)
+                    .style(theme::Button::Text)
+            });
+
+            let folds = (!self.folds.is_empty())
+                .then(|| text(format!("{} folded", self.folds.len())).size(14));
+
+            let saved = match (self.has_unsaved_changes(), self.snapshot_at) {
+                (false, _) => self.path.is_some().then(|| {
+                    let detail = match self.saved_at {
+                        Some(time) => format!("Saved to disk: {}", local_history::ago(time)),
+                        None => "Unchanged since it was opened".to_owned(),
+                    };
+
+                    ("Saved", detail)
+                }),
+                (true, Some(time)) => Some((
+                    "In local history only",
+                    format!(
+                        "Kept in the local history: {}, not saved to disk",
+                        local_history::ago(time)
+                    ),
+                )),
+                (true, None) => Some((
+                    "Unsaved",
+                    "Neither on disk nor in the local history".to_owned(),
+                )),
+            }
+            .map(|(label, detail)| {
+                tooltip(text(label).size(14), detail, tooltip::Position::Top)
+                    .style(theme::Container::Box)
+            });
+
+            let diagnostic = {
+                let (line, _) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                self.diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.line == line)
+                    .map(|diagnostic| text(&diagnostic.message).size(14))
+            };
+
+            let checks: Option<Element<'_, Message>> = if self.check.is_some() {
+                Some(
+                    row![
+                        text("Checking...").size(14),
+                        button(text("Cancel").size(14))
+                            .on_press(Message::CancelTask(Task::Check))
+                            .padding(0)
+                            .style(theme::Button::Text),
+                    ]
+                    .spacing(5)
+                    .into(),
+                )
+            } else if !self.diagnostics.is_empty() {
+                let count = |level: diagnostics::Level| {
+                    self.diagnostics
+                        .iter()
+                        .filter(|diagnostic| diagnostic.level == level)
+                        .count()
+                };
+
+                let summary = format!(
+                    "{} errors, {} warnings",
+                    count(diagnostics::Level::Error),
+                    count(diagnostics::Level::Warning)
+                );
+
+                Some(
+                    button(text(summary).size(14))
+                        .on_press(Message::NextDiagnostic)
+                        .padding(0)
+                        .style(theme::Button::Text)
+                        .into(),
+                )
+            } else {
+                None
+            };
+
+            let position = {
+                let (line, column) = self.content.cursor_position();
+                let line = self.folds.real_line(line);
+
+                // Going to a byte offset is easier knowing the current one
+                if self.go_to.is_some() {
+                    let offset = scope::offset(&self.text(), line, column);
+
+                    text(format!("{}:{} (byte {offset})", line + 1, column + 1))
+                } else {
+                    text(format!("{}:{}", line + 1, column + 1))
+                }
+            };
+
+            let missing = (self.is_missing && self.error.is_none()).then(|| {
+                row![
+                    button(text("Save to recreate it").size(14))
+                        .on_press(Message::Save)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                    button(text("Keep editing in memory").size(14))
+                        .on_press(Message::KeepInMemory)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                ]
+                .spacing(10)
+            });
+
+            let changed = (self.is_changed_on_disk && self.error.is_none()).then(|| {
+                let link = |label: &'static str| {
+                    button(text(label).size(14))
+                        .padding(0)
+                        .style(theme::Button::Text)
+                };
+
+                if self.config.reload == config::Reload::Never {
+                    row![link("Dismiss").on_press(Message::KeepChanges)]
+                } else {
+                    row![
+                        link("Reload").on_press(Message::ReloadFile),
+                        link("Keep my version").on_press(Message::KeepChanges),
+                    ]
+                    .spacing(10)
+                }
+            });
+
+            let status: Element<'_, Message> = match self.on_disk {
+                Some((size, modified)) if self.error.is_none() && self.path.is_some() => {
+                    tooltip(
+                        status,
+                        format!(
+                            "Size: {}\nModified: {}",
+                            file_size(size),
+                            local_history::ago(modified)
+                        ),
+                        tooltip::Position::Top,
+                    )
+                    .style(theme::Container::Box)
+                    .into()
+                }
+                _ => status.into(),
+            };
+
+            let mut status_bar = row![status].spacing(10);
+
+            if let Some(missing) = missing {
+                status_bar = status_bar.push(missing);
+            }
+
+            if let Some(changed) = changed {
+                status_bar = status_bar.push(changed);
+            }
+
+            if let Some(elevate) = elevate {
+                status_bar = status_bar.push(elevate);
+            }
+
+            status_bar = status_bar.push(horizontal_space(Length::Fill));
+
+            if let Some(diagnostic) = diagnostic {
+                status_bar = status_bar.push(diagnostic);
+            }
+
+            if let Some(checks) = checks {
+                status_bar = status_bar.push(checks);
+            }
+
+            if let Some(folds) = folds {
+                status_bar = status_bar.push(folds);
+            }
+
+            if let Some(saved) = saved {
+                status_bar = status_bar.push(saved);
+            }
+
+            if self.path.is_some() {
+                status_bar = status_bar.push(
+                    checkbox("Follow", self.follow.is_some(), Message::FollowToggled)
+                        .size(14)
+                        .text_size(14),
+                );
+            }
+
+            if self.path.is_some() {
+                status_bar = status_bar.push(
+                    checkbox("Read-only", self.is_kept_read_only, Message::ReadOnlyToggled)
+                        .size(14)
+                        .text_size(14),
+                );
+                status_bar = status_bar.push(
+                    checkbox("Highlight", !self.is_kept_plain, Message::HighlightingToggled)
+                        .size(14)
+                        .text_size(14),
+                );
+            }
+
+            status_bar = status_bar.push(
+                checkbox(
+                    format!("Wrap at {}", self.fixed_wrap_column()),
+                    self.wrap_column().is_some(),
+                    Message::DocumentWrapToggled,
+                )
+                .size(14)
+                .text_size(14),
+            );
+
+            status_bar = status_bar.push(
+                checkbox(
+                    "Stick to bottom",
+                    self.sticks_to_bottom,
+                    Message::StickToBottomToggled,
+                )
+                .size(14)
+                .text_size(14),
+            );
+
+            if !self.notes.is_empty() {
+                let count = match self.notes.len() {
+                    1 => "1 note".to_owned(),
+                    count => format!("{count} notes"),
+                };
+
+                status_bar = status_bar.push(
+                    button(text(count).size(14))
+                        .on_press(Message::NextNote)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                );
+            }
+
+            if self.sticks_to_bottom && !self.is_at_bottom() {
+                status_bar = status_bar.push(
+                    button(text("Scroll to bottom").size(14))
+                        .on_press(Message::ScrollToBottom)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                );
+            }
+
+            if self.is_plain && !self.is_kept_plain {
+                status_bar = status_bar.push(
+                    button(text("Long lines, not highlighted: highlight anyway").size(14))
+                        .on_press(Message::HighlightAnyway)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                );
+            }
+
+            status_bar = status_bar.push(
+                pick_list(
+                    language::Language::ALL,
+                    language::Language::from_extension(self.extension()),
+                    Message::LanguageSelected,
+                )
+                .placeholder(self.extension())
+                .text_size(14)
+                .padding([0, 5]),
+            );
+
+            if let Some(max_line_length) = self.config.max_line_length {
+                let count = self.long_lines().len();
+
+                if count > 0 {
+                    let lines = if count == 1 { "line" } else { "lines" };
+
+                    status_bar = status_bar.push(
+                        button(
+                            text(format!("{count} {lines} over {max_line_length} characters"))
+                                .size(14),
+                        )
+                        .on_press(Message::NextLongLine)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                    );
+                }
+            }
+
+            if self.is_indent_mixed {
+                status_bar = status_bar.push(
+                    button(text(format!("Mixed indentation: convert to {}", self.indent)).size(14))
+                        .on_press(Message::NormalizeIndentation)
+                        .padding(0)
+                        .style(theme::Button::Text),
+                );
+            }
+
+            status_bar = status_bar.push(text(self.indent.to_string()).size(14));
+
+            status_bar = status_bar.push(
+                button(
+                    text(format!(
+                        "{}{} {}",
+                        self.encoding,
+                        if self.has_bom { " BOM" } else { "" },
+                        self.line_ending
+                    ))
+                    .size(14),
+                )
+                    .on_press(Message::ToggleEncoding)
+                    .padding(0)
+                    .style(theme::Button::Text),
+            );
+
+            if csv::delimiter(self.extension()).is_some() {
+                status_bar = status_bar.push(
+                    checkbox(
+                        "Aligned (read-only)",
+                        self.csv_raw.is_some(),
+                        Message::CsvAlignedToggled,
+                    )
+                    .size(14)
+                    .text_size(14),
+                );
+            }
+
+            if !self.suspicious.is_empty() {
+                status_bar = status_bar.push(
+                    button(
+                        text(format!(
+                            "Clean up {} suspicious characters",
+                            self.suspicious.len()
+                        ))
+                        .size(14),
+                    )
+                    .on_press(Message::CleanUpUnicode)
+                    .padding(0)
+                    .style(theme::Button::Text),
+                );
+            }
+
+            if let Some(vim) = &self.vim {
+                if let Some(message) = &vim.message {
+                    status_bar = status_bar.push(text(message).size(14));
+                }
+
+                status_bar = status_bar.push(text(vim.mode.label()).size(14));
+            }
+
+            if self.emacs.as_ref().is_some_and(emacs::State::is_waiting) {
+                status_bar = status_bar.push(text("C-x-").size(14));
+            }
+
+            status_bar = status_bar.push(
+                text(if self.is_overwriting { "OVR" } else { "INS" }).size(14),
+            );
+
+            status_bar.push(position)
+        };
+
+        let menu_bar = Menu::ALL.into_iter().fold(Row::new(), |bar, menu| {
+            bar.push(
+                button(text(menu.label()).size(14))
+                    .on_press(Message::MenuToggled(menu))
+                    .padding([2, 10])
+                    .style(if self.menu == Some(menu) {
+                        theme::Button::Primary
+                    } else {
+                        theme::Button::Text
+                    }),
+            )
+        });
+
+        let mut layout = Column::with_children(vec![menu_bar.into()]);
+
+        if let Some(menu) = self.menu {
+            layout = layout.push(menu_list(menu));
+        }
+
+        layout = layout.push(controls);
+
+        if !self.breadcrumbs.is_empty() {
+            let breadcrumbs = self.breadcrumbs.iter().enumerate().fold(
+                row![].spacing(5),
+                |breadcrumbs, (index, scope)| {
+                    let breadcrumbs = if index > 0 {
+                        breadcrumbs.push(text("›").size(14))
+                    } else {
+                        breadcrumbs
+                    };
+
+                    breadcrumbs.push(
+                        button(text(&scope.label).size(14))
+                            .on_press(Message::GoToLine(scope.line))
+                            .padding(0)
+                            .style(theme::Button::Text),
+                    )
+                },
+            );
+
+            layout = layout.push(breadcrumbs);
+        }
+
+        if self.is_settings_open {
+            layout = layout.push(self.settings_panel());
+        }
+
+        if self.is_about_open {
+            layout = layout.push(about_panel());
+        }
+
+        if self.session.layout.is_output_open {
+            layout = layout.push(self.output_panel());
+        }
+
+        if self.is_shortcuts_open {
+            layout = layout.push(shortcuts_panel(self.vim.is_some(), self.emacs.is_some()));
+        }
+
+        if let Some(state) = &self.local_history {
+            layout = layout.push(self.local_history_panel(state));
+        }
+
+        if let Some(todos) = &self.todos {
+            layout = layout.push(self.todo_panel(todos));
+        }
+
+        if let Some(templates) = &self.templates {
+            layout = layout.push(templates_panel(templates));
+        }
+
+        if let Some((path, rows)) = &self.comparison {
+            layout = layout.push(self.comparison_panel(path, rows));
+        }
+
+        if let Some(state) = self.replace.as_ref() {
+            layout = layout.push(self.replace_panel(state));
+        }
+
+        if let Some(state) = self.find.as_ref() {
+            layout = layout.push(self.find_bar(state));
+        }
+
+        if let Some(state) = self.sort.as_ref() {
+            layout = layout.push(sort_bar(state));
+        }
+
+        if self.is_encoding_open {
+            layout = layout.push(encoding_bar(self.encoding, self.has_bom, self.line_ending));
+        }
+
+        if let Some(overwrite) = &self.overwrite {
+            let name = overwrite.path.file_name().unwrap_or(overwrite.path.as_os_str());
+            let modified = overwrite.modified.map_or_else(String::new, |time| {
+                format!(", changed {}", local_history::ago(time).to_lowercase())
+            });
+
+            layout = layout.push(
+                row![
+                    text(format!(
+                        "{} already exists ({} bytes{modified}). Replace it?",
+                        name.to_string_lossy(),
+                        overwrite.size
+                    )),
+                    button("Replace").on_press(Message::ConfirmOverwrite),
+                    button("Cancel")
+                        .on_press(Message::CancelOverwrite)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(latest) = self.recovered.first() {
+            let count = match self.recovered.len() {
+                1 => "A new file".to_owned(),
+                count => format!("{count} new files"),
+            };
+
+            layout = layout.push(
+                row![
+                    text(format!(
+                        "{count} from an earlier run never got saved, the latest from {}",
+                        local_history::ago(latest.time).to_lowercase()
+                    )),
+                    button("Restore").on_press(Message::RestoreRecovered),
+                    button("Discard")
+                        .on_press(Message::DiscardRecovered)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if self.is_quitting {
+            layout = layout.push(
+                row![
+                    text("Discard the unsaved changes and quit?"),
+                    button("Discard").on_press(Message::ConfirmQuit),
+                    button("Cancel")
+                        .on_press(Message::CancelQuit)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(encoding) = self.reopen_as {
+            layout = layout.push(
+                row![
+                    text(format!("Discard the unsaved changes and reopen in {encoding}?")),
+                    button("Discard").on_press(Message::ConfirmReopen),
+                    button("Cancel")
+                        .on_press(Message::CancelReopen)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(path) = &self.switch_to {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+
+            layout = layout.push(
+                row![
+                    text(format!(
+                        "Discard the unsaved changes and open {}?",
+                        name.to_string_lossy()
+                    )),
+                    button("Discard").on_press(Message::ConfirmSwitchFile),
+                    button("Cancel")
+                        .on_press(Message::CancelSwitchFile)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if self.crop.is_some() {
+            layout = layout.push(
+                row![
+                    text(if self.path.is_some() {
+                        "Crop to the selection, discarding the rest of the document?"
+                    } else {
+                        "Crop the untitled document to the selection, losing the rest for good?"
+                    }),
+                    button("Crop").on_press(Message::ConfirmCrop),
+                    button("Cancel")
+                        .on_press(Message::CancelCrop)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(lines) = self.deletion {
+            layout = layout.push(
+                row![
+                    text(format!("Delete the {lines} selected lines?")),
+                    button("Delete").on_press(Message::ConfirmDeletion),
+                    button("Cancel")
+                        .on_press(Message::CancelDeletion)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if !self.conflicts.is_empty() {
+            layout = layout.push(conflict_bar(
+                &self.conflicts,
+                self.current_conflict(),
+                !self.is_view_only(),
+            ));
+        }
+
+        let sticky = self.sticky_lines();
+
+        if !sticky.is_empty() {
+            layout = layout.push(
+                container(sticky.into_iter().fold(Column::new(), |sticky, line| {
+                    sticky.push(text(line).style(theme::Text::Color(iced::Color {
+                        a: 0.7,
+                        ..self.theme().palette().text
+                    })))
+                }))
+                .width(Length::Fill)
+                .padding([0, 5])
+                .style(theme::Container::Box),
+            );
+        }
+
+        layout = match self.wrap_column() {
+            // Lines wrap at the edge of the editor, so put that edge at the
+            // column, past the padding of 5 on either side
+            Some(column) => layout.push(
+                container(input).max_width(column as f32 * TEXT_SIZE * GLYPH_WIDTH + 10.0),
+            ),
+            None => layout.push(input),
+        };
+
+        if let Some(hover) = &self.hover {
+            layout = layout.push(
+                container(text(hover).size(14))
+                    .max_width(600.0)
+                    .padding(5)
+                    .style(theme::Container::Box),
+            );
+        }
+
+        if !self.completions.is_empty() {
+            layout = layout.push(
+                container(self.completions.iter().enumerate().fold(
+                    Column::new(),
+                    |list, (index, completion)| {
+                        list.push(
+                            button(text(completion).size(14))
+                                .on_press(Message::CompletionSelected(completion.clone()))
+                                .width(Length::Fill)
+                                .padding([2, 5])
+                                .style(if index == self.completion_index {
+                                    theme::Button::Primary
+                                } else {
+                                    theme::Button::Text
+                                }),
+                        )
+                    },
+                ))
+                .width(Length::Fixed(300.0))
+                .style(theme::Container::Box),
+            );
+        }
+
+        if self.is_context_menu_open {
+            layout = layout.push(self.context_menu());
+        }
+
+        if self.is_kill_ring_open {
+            layout = layout.push(
+                container(self.kill_ring.iter().enumerate().fold(
+                    Column::new(),
+                    |list, (index, entry)| {
+                        let first = entry.lines().next().unwrap_or_default();
+                        let mut label: String = first.chars().take(60).collect();
+
+                        if label.len() < entry.len() {
+                            label.push('…');
+                        }
+
+                        list.push(
+                            button(text(label).size(14))
+                                .on_press(Message::KillRingPicked(index))
+                                .width(Length::Fill)
+                                .padding([2, 5])
+                                .style(theme::Button::Text),
+                        )
+                    },
+                ))
+                .width(Length::Fixed(400.0))
+                .style(theme::Container::Box),
+            );
+        }
+
+        if let Some(go_to) = &self.go_to {
+            let mut input = row![
+                text("Go to"),
+                text_input("file:line:column, @byte or percent%", go_to)
+                    .id(go_to_input())
+                    .on_input(Message::GoToChanged)
+                    .on_submit(Message::GoToSubmitted)
+                    .width(Length::Fixed(300.0)),
+            ]
+            .spacing(5);
+
+            if !go_to.trim().is_empty()
+                && location::parse(go_to).is_none()
+                && location::parse_offset(go_to).is_none()
+            {
+                input = input.push(
+                    text("Expected line, line:column, file:line:column, @byte or percent%")
+                        .size(14),
+                );
+            }
+
+            layout = layout.push(input);
+        }
+
+        if let Some(code_point) = &self.code_point {
+            let mut input = row![
+                text("U+"),
+                text_input("2603", code_point)
+                    .id(code_point_input())
+                    .on_input(Message::CodePointChanged)
+                    .on_submit(Message::CodePointSubmitted)
+                    .width(Length::Fixed(120.0)),
+            ]
+            .spacing(5);
+
+            input = match unicode::parse_code_point(code_point) {
+                Some(c) => input.push(text(unicode::describe(c)).size(14)),
+                None if !code_point.is_empty() => input.push(text("Not a code point").size(14)),
+                None => input,
+            };
+
+            layout = layout.push(input);
+        }
+
+        let (line, _) = self.content.cursor_position();
+        let line = self.folds.real_line(line);
+
+        if let Some((noted, note)) = &self.note {
+            layout = layout.push(
+                row![
+                    text(format!("Note on line {}", noted + 1)),
+                    text_input("Empty to remove it", note)
+                        .id(note_input())
+                        .on_input(Message::NoteChanged)
+                        .on_submit(Message::NoteSubmitted)
+                        .width(Length::Fill),
+                ]
+                .spacing(5),
+            );
+        } else if let Some(note) = self.notes.iter().find(|note| note.anchor.line == line) {
+            layout = layout.push(
+                row![
+                    text(format!("Note: {}", note.text)).width(Length::Fill),
+                    button("Edit").on_press(Message::EditNote),
+                    button("Remove")
+                        .on_press(Message::RemoveNote)
+                        .style(theme::Button::Secondary),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(page) = self.page {
+            layout = layout.push(
+                row![
+                    text(format!(
+                        "Showing bytes {}\u{2013}{} of {}, over the {} MiB limit, read-only",
+                        page.start,
+                        page.end,
+                        file_size(page.total),
+                        self.config.max_buffer_mib
+                    ))
+                    .width(Length::Fill),
+                    button("Previous page").on_press_maybe(
+                        (page.start > 0).then_some(Message::TurnPage(false))
+                    ),
+                    button("Next page").on_press_maybe(
+                        (page.end < page.total).then_some(Message::TurnPage(true))
+                    ),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(name) = &self.tag_name {
+            layout = layout.push(
+                row![
+                    text("Rename the tag and its partner to"),
+                    text_input("div", name)
+                        .id(tag_name_input())
+                        .on_input(Message::TagNameChanged)
+                        .on_submit(Message::TagNameSubmitted)
+                        .width(Length::Fixed(200.0)),
+                ]
+                .spacing(5),
+            );
+        }
+
+        if let Some(surrounding) = self.surrounding {
+            layout = layout.push(text(format!(
+                "Type what to surround {} with, or {} for a tag",
+                surrounding.label(),
+                surround::TAG
+            )));
+        }
+
+        if let Some((surrounding, tag)) = &self.surround_tag {
+            layout = layout.push(
+                row![
+                    text(format!("Surround {} with the tag", surrounding.label())),
+                    text_input("a href=\"#\"", tag)
+                        .id(surround_tag_input())
+                        .on_input(Message::SurroundTagChanged)
+                        .on_submit(Message::SurroundTagSubmitted)
+                        .width(Length::Fixed(200.0)),
+                ]
+                .spacing(5),
+            );
+        }
+
+        if let Some(command) = self.vim.as_ref().and_then(|vim| vim.command.as_ref()) {
+            layout = layout.push(row![
+                text(":"),
+                text_input("", command)
+                    .id(vim_command_input())
+                    .on_input(Message::VimCommandChanged)
+                    .on_submit(Message::VimCommandSubmitted),
+            ]);
+        }
+
+        container(layout.push(status_bar))
+            .padding(10)
+            .into()
+    }
+
+    fn theme(&self) -> Theme {
+        self.theme_choice().theme()
+    }
+}
+
+impl Editor {
+    /// Whether the window is in the background with the timers paused.
+    fn is_idle(&self) -> bool {
+        !self.is_focused && self.config.pause_when_unfocused
+    }
+
+    /// Handles what changed on disk while the window was idle, as watching
+    /// the file would have.
+    fn catch_up_on_disk(&mut self) -> Command<Message> {
+        let before = self.idle_on_disk.take();
+
+        let Some(path) = self.path.as_deref() else {
+            return Command::none();
+        };
+
+        if self.is_saving || self.is_changed_on_disk {
+            return Command::none();
+        }
+
+        let kind = match (before, on_disk(path)) {
+            (Some(_), None) if !self.is_missing => watch::Kind::Removed,
+            (_, Some(_)) if self.is_missing => watch::Kind::Restored,
+            (Some(before), Some(now)) if before != now => watch::Kind::Modified,
+            _ => return Command::none(),
+        };
+
+        self.update(Message::FileChangedOnDisk(watch::Change {
+            generation: self.disk_generation,
+            kind,
+        }))
+    }
+
+    /// The full text of the document, including any folded lines.
+    fn text(&self) -> String {
+        match &self.csv_raw {
+            Some(raw) => raw.clone(),
+            None => self.folds.expand(&self.content.text()),
+        }
+    }
+
+    /// The merge conflict the cursor is in.
+    fn current_conflict(&self) -> Option<conflict::Conflict> {
+        let (line, _) = self.content.cursor_position();
+        let line = self.folds.real_line(line);
+
+        self.conflicts
+            .iter()
+            .find(|conflict| conflict.contains(line))
+            .copied()
+    }
+
+    /// The bytes to write for `text`, in the encoding and with the line
+    /// endings of the file.
+    fn encode(&self, text: &str) -> Result<Vec<u8>, EditorError> {
+        self.encoding
+            .encode(&self.line_ending.apply(text), self.has_bom)
+            .map_err(EditorError::Encoding)
+    }
+
+    /// The extension of the language of the document, which decides how it
+    /// is highlighted, folded and checked.
+    ///
+    /// The language picked for the file comes first, then the one its name
+    /// is associated with in the configuration.
+    fn extension(&self) -> &str {
+        if let Some(language) = &self.language {
+            return language;
+        }
+
+        let associated = self
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name()?.to_str())
+            .and_then(|name| self.config.association(name));
+
+        if let Some(extension) = associated {
+            return extension;
+        }
+
+        self.path
+            .as_ref()
+            .and_then(|path| path.extension()?.to_str())
+            .unwrap_or("rs")
+    }
+
+    /// Whether the document is only for reading, like the aligned view of a
+    /// table, hex dumps and files kept read-only.
+    fn is_view_only(&self) -> bool {
+        self.csv_raw.is_some()
+            || self.is_binary
+            || self.is_lossy
+            || self.page.is_some()
+            || self.is_kept_read_only
+    }
+
+    /// Applies an action of the editor widget, keeping everything derived
+    /// from the text in step.
+    fn edit(&mut self, action: text_editor::Action) -> Command<Message> {
+        let is_edit = action.is_edit();
+
+        if matches!(action, text_editor::Action::Click(_)) {
+            self.is_context_menu_open = false;
+        }
+
+        // Only the focused editor acts, so the arrow keys are its own again
+        if !matches!(action, text_editor::Action::Scroll { .. }) {
+            self.query_input = None;
+            self.region = Region::Editor;
+            self.line_clicked_at =
+                matches!(action, text_editor::Action::SelectLine).then(Instant::now);
+        }
+
+        let is_selecting = matches!(
+            action,
+            text_editor::Action::Select(_)
+                | text_editor::Action::SelectWord
+                | text_editor::Action::SelectLine
+                | text_editor::Action::Drag(_)
+        );
+
+        if is_edit && self.is_view_only() {
+            return Command::none();
+        }
+
+        if is_edit && !self.folds.is_empty() {
+            self.unfold_edited_lines();
+        }
+
+        if is_edit {
+            self.crop = None;
+            self.deletion = None;
+            self.highlighted_lines = None;
+        }
+
+        let (line, _) = self.content.cursor_position();
+        let line_count = self.content.line_count();
+        let was_at_bottom = self.is_at_bottom();
+        let origin = (!is_edit).then(|| self.location());
+        let scrolled = match &action {
+            text_editor::Action::Scroll { lines } => *lines,
+            _ => 0,
+        };
+        let typed = match &action {
+            text_editor::Action::Edit(text_editor::Edit::Insert(c)) => Some(*c),
+            _ => None,
+        };
+        let pasted = match &action {
+            text_editor::Action::Edit(text_editor::Edit::Paste(text)) => {
+                self.kill_ring.position(text)
+            }
+            _ => None,
+        };
+
+        // The highlighter starts over from the top whenever the overlays
+        // change, so in long documents they change at most once in a while
+        let holds_highlighting = is_edit
+            && self.held_highlighting.is_none()
+            && self.config.highlight_interval > 0
+            && line_count > THROTTLED_LINES;
+
+        let release = if holds_highlighting {
+            Command::perform(
+                tokio::time::sleep(Duration::from_millis(self.config.highlight_interval)),
+                |()| Message::ReleaseOverlays,
+            )
+        } else {
+            Command::none()
+        };
+
+        self.is_dirty = (self.is_dirty || is_edit) && !self.is_scratch;
+        self.content.edit(action);
+        self.error = None;
+
+        if is_selecting {
+            if let Some(selection) = self.content.selection() {
+                self.primary = Some(selection);
+            }
+        }
+
+        if let Some(origin) = origin {
+            if self.content.cursor_position().0.abs_diff(line) >= JUMP_LINES {
+                self.history.push(origin);
+            }
+        }
+        self.last_paste = pasted.map(|index| (index, self.content.cursor_position()));
+
+        if is_edit {
+            let delta = self.content.line_count() as isize - line_count as isize;
+            let edited = self.folds.real_line(line);
+
+            // Notes below the edit move with their lines, and the ones on
+            // deleted lines end up on the line left
+            for note in &mut self.notes {
+                if note.anchor.line > edited {
+                    note.anchor.line = note.anchor.line.saturating_add_signed(delta).max(edited);
+                }
+            }
+
+            self.folds.shift(line, delta);
+            self.analyze_text();
+
+            // What is held is made of the edited text, not of the one before
+            if holds_highlighting {
+                self.held_highlighting = Some((self.overlays(), self.tree_sitter_spans()));
+            }
+
+            self.notify_language_server();
+            self.last_edit = Some(self.location());
+        }
+
+        match typed {
+            Some(c) if complete::is_identifier(c) || c == '.' || c == ':' => {
+                self.complete_word();
+                self.request_completions();
+            }
+            _ if scrolled == 0 => self.completions.clear(),
+            _ => {}
+        }
+
+        if scrolled == 0 {
+            self.hover = None;
+            self.hover_position = None;
+        }
+
+        self.scroll(scrolled);
+
+        let has_grown = self.content.line_count() > line_count;
+
+        if self.sticks_to_bottom && was_at_bottom && has_grown {
+            self.scroll_to_bottom();
+        } else if scrolled == 0 {
+            self.keep_scroll_off();
+        }
+
+        self.catch_up();
+
+        let check = if is_edit {
+            self.mixed_indent_generation += 1;
+
+            let generation = self.mixed_indent_generation;
+
+            Command::perform(tokio::time::sleep(MIXED_INDENT_DELAY), move |()| {
+                Message::CheckIndentation(generation)
+            })
+        } else {
+            Command::none()
+        };
+
+        let keep = if is_edit && self.is_scratch && self.config.keep_scratch {
+            self.scratch_generation += 1;
+
+            let generation = self.scratch_generation;
+
+            Command::perform(tokio::time::sleep(SCRATCH_DELAY), move |()| {
+                Message::KeepScratch(generation)
+            })
+        } else {
+            Command::none()
+        };
+
+        if is_edit || self.content.cursor_position().0 != line {
+            self.breadcrumbs_generation += 1;
+
+            let generation = self.breadcrumbs_generation;
+
+            return Command::batch([
+                release,
+                check,
+                keep,
+                Command::perform(tokio::time::sleep(BREADCRUMBS_DELAY), move |()| {
+                    Message::RefreshBreadcrumbs(generation)
+                }),
+            ]);
+        }
+
+        Command::batch([release, check, keep])
+    }
+
+    /// Whether closing the document would lose changes, which the scratch
+    /// buffer never does.
+    fn has_unsaved_changes(&self) -> bool {
+        self.is_dirty && !self.is_scratch
+    }
+
+    /// The displayed lines longer than the limit, with the byte where they
+    /// go over it.
+    fn long_lines(&self) -> Vec<(usize, usize)> {
+        let Some(max_line_length) = self.config.max_line_length else {
+            return Vec::new();
+        };
+
+        self.content
+            .text()
+            .split('\n')
+            .enumerate()
+            .filter_map(|(number, line)| {
+                line.char_indices()
+                    .nth(max_line_length)
+                    .map(|(start, _)| (number, start))
+            })
+            .collect()
+    }
+
+    /// Keeps which panels are open and their sizes for the next run.
+    fn save_layout(&mut self) {
+        // Losing the session only loses the layout, which is not worth an
+        // error
+        let _ = self.session.save();
+    }
+
+    /// Keeps the text of the scratch buffer in the session, if it is kept.
+    fn keep_scratch(&mut self) {
+        if !self.config.keep_scratch {
+            return;
+        }
+
+        self.session.scratch = self.text();
+
+        let _ = self.session.save();
+    }
+
+    /// Keeps the scratch buffer before another document replaces it.
+    fn leave_scratch(&mut self) {
+        if self.is_scratch {
+            self.keep_scratch();
+            self.is_scratch = false;
+        }
+    }
+
+    /// Pastes text from the clipboard, cleaned up as configured.
+    fn paste(&mut self, text: &str) -> Command<Message> {
+        let mut text = if self.config.normalize_on_paste {
+            unicode::normalize(text)
+        } else {
+            text.to_owned()
+        };
+
+        let reindents = self
+            .config
+            .reindent_on_paste
+            .iter()
+            .any(|extension| extension == self.extension());
+
+        if reindents && text.contains('\n') {
+            let (line, _) = self.content.cursor_position();
+            let indentation = self
+                .content
+                .line(line)
+                .map(|line| indent::of(&line).to_owned())
+                .unwrap_or_default();
+
+            // Without braces, how lines nest is all in their indentation
+            text = if indent::is_significant(self.extension()) {
+                indent::reindent_nested(&text, &indentation, self.indent, self.config.tab_width)
+            } else {
+                indent::reindent(&text, &indentation)
+            };
+        }
+
+        self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(text),
+        )))
+    }
+
+    /// Replaces the matches of `query` in the document as it is now, which
+    /// may have been edited since the preview, and the bytes to write.
+    ///
+    /// A document only for reading is left alone, since its text is not the
+    /// one of its file.
+    fn replace_in_document(&mut self, query: &replace::Query) -> Option<Vec<u8>> {
+        if self.is_view_only() {
+            return None;
+        }
+
+        let text = self.text();
+        let replaced = match replace::replace(query, &text) {
+            Ok(replaced) => replaced,
+            Err(error) => {
+                self.error = Some(error);
+                return None;
+            }
+        };
+
+        let bytes = match self.encode(&replaced) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                self.error = Some(error);
+                return None;
+            }
+        };
+
+        if replaced != text {
+            let (line, column) = self.content.cursor_position();
+            let line = self.folds.real_line(line);
+
+            self.folds.clear();
+            self.show(&replaced, line, column);
+            self.notify_language_server();
+        }
+
+        Some(bytes)
+    }
+
+    /// Runs the save hooks and writes the document to `path`.
+    fn save_to(&mut self, path: PathBuf) -> Command<Message> {
+        let text = self.text();
+
+        match save::run(&self.config, text.clone(), self.extension()) {
+            Ok(content) => {
+                if content != text {
+                    let (line, column) = self.content.cursor_position();
+                    let line = self.folds.real_line(line);
+
+                    self.folds.clear();
+                    self.show(&content, line, column);
+                    self.notify_language_server();
+                }
+
+                let bytes = match self.encode(&content) {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        if let Some(vim) = self.vim.as_mut() {
+                            vim.quit_on_save = false;
+                        }
+
+                        self.run_after_save = false;
+                        self.error = Some(error);
+                        return Command::none();
+                    }
+                };
+
+                self.is_saving = true;
+
+                Command::perform(
+                    save_file(path, bytes, self.backup(), self.config.create_directories),
+                    Message::FileSaved,
+                )
+            }
+            Err(error) => {
+                if let Some(vim) = self.vim.as_mut() {
+                    vim.quit_on_save = false;
+                }
+
+                self.run_after_save = false;
+                self.error = Some(EditorError::SaveHook(error.clone()));
+                self.update(Message::AppendOutput(output::Line::new(error)))
+            }
+        }
+    }
+
+    /// Replaces the document with the byte `range` of its full text.
+    ///
+    /// The whole document of a file is kept in the local history first,
+    /// since there is no undoing this; an untitled one is only cropped once
+    /// that is confirmed.
+    fn crop_to(&mut self, range: Range<usize>) -> Command<Message> {
+        if self.is_view_only() {
+            return Command::none();
+        }
+
+        let text = self.text();
+
+        let Some(kept) = text.get(range) else {
+            return Command::none();
+        };
+
+        let snapshot = match self.path.clone() {
+            Some(path) => Command::perform(
+                local_history::save(path, text.clone()),
+                Message::SnapshotKept,
+            ),
+            None => Command::none(),
+        };
+
+        self.folds.clear();
+        self.show(kept, 0, 0);
+        self.is_dirty = true;
+        self.notify_language_server();
+
+        snapshot
+    }
+
+    /// The byte range of `text` that is selected.
+    ///
+    /// The widget only tells the selected text, so this collapses the
+    /// selection to find where it starts.
+    fn selected_range(&mut self, text: &str) -> Range<usize> {
+        let selection = self.content.selection();
+
+        // Moving left collapses the selection into its start
+        if selection.is_some() {
+            self.content
+                .edit(text_editor::Action::Move(text_editor::Motion::Left));
+        }
+
+        let (line, column) = self.content.cursor_position();
+        let start = scope::offset(text, line, column);
+
+        start..start + selection.map_or(0, |selection| selection.len())
+    }
+
+    /// Puts `open` and `close` around what `surrounding` stands for.
+    fn surround(&mut self, surrounding: Surrounding, open: &str, close: &str) {
+        match surrounding {
+            Surrounding::Selection => self.surround_selection(open, close),
+            Surrounding::Pair => self.replace_surrounding_pair(open, close),
+        }
+    }
+
+    /// The innermost bracket pair or string literal around the cursor, as
+    /// a byte range of the full text, and the offset of the cursor.
+    fn surrounding_pair(&self) -> Option<(Range<usize>, usize)> {
+        let text = self.text();
+        let (line, column) = self.content.cursor_position();
+        let offset = scope::offset(&text, self.folds.real_line(line), column);
+
+        scope::pair(&text, offset).map(|pair| (pair, offset))
+    }
+
+    /// Puts `open` and `close` in place of the delimiters of the pair
+    /// around the cursor, which stays on the same character.
+    fn replace_surrounding_pair(&mut self, open: &str, close: &str) {
+        if self.is_view_only() {
+            return;
+        }
+
+        let Some((pair, offset)) = self.surrounding_pair() else {
+            return;
+        };
+
+        let text = self.text();
+        let replaced = surround::replace(&text, pair, open, close);
+        let (line, column) = scope::position(&replaced, offset - 1 + open.len());
+
+        self.folds.clear();
+        self.show(&replaced, line, column);
+        self.is_dirty = true;
+        self.notify_language_server();
+    }
+
+    /// The regions open, in the order F6 goes through them.
+    fn regions(&self) -> Vec<Region> {
+        let command = self.vim.as_ref().is_some_and(|vim| vim.command.is_some());
+
+        [
+            (Region::Editor, true),
+            (Region::Find, self.find.is_some()),
+            (Region::ReplaceInFiles, self.replace.is_some()),
+            (Region::GoTo, self.go_to.is_some()),
+            (Region::Command, command),
+        ]
+        .into_iter()
+        .filter_map(|(region, is_open)| is_open.then_some(region))
+        .collect()
+    }
+
+    /// Gives the keyboard to the input of `region`, or takes it away from
+    /// every input for the editor.
+    fn focus(&mut self, region: Region) -> Command<Message> {
+        self.region = region;
+        self.query_input = match region {
+            Region::Find => Some(QueryInput::Find),
+            Region::ReplaceInFiles => Some(QueryInput::Pattern),
+            _ => None,
+        };
+
+        text_input::focus(match region {
+            // No input has a new id, so they all let go of the keyboard
+            Region::Editor => text_input::Id::unique(),
+            Region::Find => find_input(),
+            Region::ReplaceInFiles => replace_pattern_input(),
+            Region::GoTo => go_to_input(),
+            Region::Command => vim_command_input(),
+        })
+    }
+
+    /// Wraps the selection in `open` and `close`, keeping it selected.
+    fn surround_selection(&mut self, open: &str, close: &str) {
+        let Some(selection) = self.content.selection() else {
+            return;
+        };
+
+        if self.is_view_only() {
+            return;
+        }
+
+        let visible = self.content.text();
+        let text = self.text();
+        let start = self.selected_range(&visible).start;
+
+        // The selection takes in whatever is folded inside it
+        let offset = |folds: &fold::Folds, (line, column): (usize, usize)| {
+            scope::offset(&text, folds.real_line(line), column)
+        };
+        let range = offset(&self.folds, scope::position(&visible, start))
+            ..offset(&self.folds, scope::position(&visible, start + selection.len()));
+
+        let surrounded = surround::wrap(&text, range.clone(), open, close);
+
+        self.folds.clear();
+        self.show(&surrounded, 0, 0);
+        self.select_range(&surrounded, range.start + open.len()..range.end + open.len());
+        self.is_dirty = true;
+        self.notify_language_server();
+    }
+
+    /// Selects the byte `range` of `text`, leaving the cursor at its end.
+    fn select_range(&mut self, text: &str, range: Range<usize>) {
+        let (line, column) = scope::position(text, range.start);
+        cursor::move_to(&mut self.content, line, column);
+
+        let (line, column) = scope::position(text, range.end);
+        cursor::select_to(&mut self.content, line, column);
+
+        self.scroll(0);
+    }
+
+    /// Sorts the lines touched by the selection, or all of them.
+    fn sort_lines(&mut self, state: &sort::State) -> Command<Message> {
+        let selection = self.content.selection();
+
+        // Moving left collapses the selection into its start
+        if selection.is_some() {
+            self.content
+                .edit(text_editor::Action::Move(text_editor::Motion::Left));
+        }
+
+        let (first, last) = match &selection {
+            Some(selection) => {
+                let (line, _) = self.content.cursor_position();
+                let selection = selection.strip_suffix('\n').unwrap_or(&selection);
+
+                (line, line + selection.matches('\n').count())
+            }
+            None => {
+                // Keep the final newline at the end
+                let last = self.content.line_count().saturating_sub(1);
+                let is_empty = self.content.line(last).is_some_and(|line| line.is_empty());
+
+                (0, if is_empty { last.saturating_sub(1) } else { last })
+            }
+        };
+
+        // Sorting would scramble the lines that folds hide
+        let (first, last) = self.unfold_lines(first, last);
+
+        let text = self.content.text();
+        let mut lines: Vec<&str> = text.split('\n').skip(first).take(last + 1 - first).collect();
+        let last_length = lines.last().map_or(0, |line| line.len());
+
+        state.sort(&mut lines);
+        let sorted = lines.join("\n");
+
+        cursor::move_to(&mut self.content, first, 0);
+        cursor::select_to(&mut self.content, last, last_length);
+
+        self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(sorted),
+        )))
+    }
+
+    /// Indents the lines touched by the selection, or the line of the cursor,
+    /// by a level, or outdents them.
+    ///
+    /// Whole lines get shifted even when the selection starts or ends in
+    /// the middle of one, and the selection then moves along with the text.
+    fn shift_lines(&mut self, is_outdent: bool) -> Command<Message> {
+        let selection = self.content.selection();
+
+        // Moving left collapses the selection into its start
+        if selection.is_some() {
+            self.content
+                .edit(text_editor::Action::Move(text_editor::Motion::Left));
+        }
+
+        let (first, column) = self.content.cursor_position();
+        let (last, end) = match &selection {
+            Some(selection) => {
+                let trimmed = selection.strip_suffix('\n').unwrap_or(selection);
+                let end = match selection.rfind('\n') {
+                    Some(newline) => selection.len() - newline - 1,
+                    None => column + selection.len(),
+                };
+
+                (first + trimmed.matches('\n').count(), end)
+            }
+            None => (first, column),
+        };
+
+        // A selection up to the start of a line leaves that line alone
+        let ends_before = selection
+            .as_ref()
+            .is_some_and(|selection| selection.ends_with('\n'));
+
+        // The lines that folds hide get shifted with the rest
+        let (first, last) = self.unfold_lines(first, last);
+
+        let text = self.content.text();
+        let lines: Vec<&str> = text.split('\n').skip(first).take(last + 1 - first).collect();
+        let shifted: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                if is_outdent {
+                    indent::outdent(line, self.indent, self.config.tab_width)
+                } else {
+                    indent::indent(line, self.indent)
+                }
+            })
+            .collect();
+
+        if shifted == lines {
+            return Command::none();
+        }
+
+        // Where a column of a line ends up, with the start of a line staying
+        // at the start so that whole lines stay selected
+        let moved = |index: usize, column: usize| {
+            let delta = shifted[index].len() as isize - lines[index].len() as isize;
+
+            if column == 0 {
+                0
+            } else {
+                column.saturating_add_signed(delta).min(shifted[index].len())
+            }
+        };
+
+        let start = moved(0, column);
+        let end = if ends_before {
+            (last + 1, 0)
+        } else {
+            (last, moved(lines.len() - 1, end))
+        };
+
+        cursor::move_to(&mut self.content, first, 0);
+        cursor::select_to(&mut self.content, last, lines[lines.len() - 1].len());
+
+        let command = self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(shifted.join("\n")),
+        )));
+
+        cursor::move_to(&mut self.content, first, start);
+
+        if selection.is_some() {
+            cursor::select_to(&mut self.content, end.0, end.1);
+        }
+
+        command
+    }
+
+    /// The display lines from `first` to `last` as lines of the document
+    /// shown unfolded, if folds hide any lines among them.
+    fn unfold_lines(&mut self, first: usize, last: usize) -> (usize, usize) {
+        if self.folds.headers(first..=last).is_empty() {
+            return (first, last);
+        }
+
+        let text = self.text();
+        let last = if last + 1 < self.content.line_count() {
+            self.folds.real_line(last + 1) - 1
+        } else {
+            text.matches('\n').count()
+        };
+        let first = self.folds.real_line(first);
+
+        self.folds.clear();
+        self.show(&text, first, 0);
+
+        (first, last)
+    }
+
+    /// Adds `delta` to the number at or after the cursor.
+    ///
+    /// With a selection, the first number of every selected line is counted
+    /// up by one more `delta` than the line before, making a sequence.
+    fn add_to_numbers(&mut self, delta: i128) -> Command<Message> {
+        let selection = self.content.selection();
+
+        // Moving left collapses the selection into its start
+        if selection.is_some() {
+            self.content
+                .edit(text_editor::Action::Move(text_editor::Motion::Left));
+        }
+
+        // Cursor columns are byte offsets into their line, like the ranges
+        // of numbers and the length of the selection
+        let (start_line, start_column) = self.content.cursor_position();
+        let start_column = self
+            .content
+            .line(start_line)
+            .map_or(0, |text| start_column.min(text.len()));
+        let (end_line, end_column) = match &selection {
+            Some(selection) => match selection.rfind('\n') {
+                Some(newline) => (
+                    start_line + selection.matches('\n').count(),
+                    selection.len() - newline - 1,
+                ),
+                None => (start_line, start_column + selection.len()),
+            },
+            None => (start_line, usize::MAX),
+        };
+
+        let mut commands = Vec::new();
+        let mut step = 1;
+
+        for line in start_line..=end_line {
+            let column = if line == start_line { start_column } else { 0 };
+
+            let Some(number) = self
+                .content
+                .line(line)
+                .and_then(|text| number::find(&text, column))
+            else {
+                continue;
+            };
+
+            if line == end_line && number.range.start >= end_column {
+                break;
+            }
+
+            let replacement = number.add(delta * step);
+            step += 1;
+
+            cursor::move_to(&mut self.content, line, number.range.start);
+            cursor::select_to(&mut self.content, line, number.range.end);
+            commands.push(self.edit(text_editor::Action::Edit(
+                text_editor::Edit::Paste(Arc::new(replacement)),
+            )));
+        }
+
+        Command::batch(commands)
+    }
+
+    /// Rewrites the selection, or the whole document, with `format`.
+    ///
+    /// When it is not valid JSON, the cursor is left where parsing failed.
+    fn reformat_json(
+        &mut self,
+        format: fn(&str) -> Result<String, json::Error>,
+    ) -> Command<Message> {
+        if let Some(selection) = self.content.selection() {
+            return match format(&selection) {
+                Ok(text) => self.edit(text_editor::Action::Edit(text_editor::Edit::Paste(
+                    Arc::new(text),
+                ))),
+                Err(error) => {
+                    // Moving left collapses the selection into its start
+                    self.content
+                        .edit(text_editor::Action::Move(text_editor::Motion::Left));
+
+                    let (line, column) = self.content.cursor_position();
+                    let column = if error.line == 0 {
+                        column + error.column
+                    } else {
+                        error.column
+                    };
+
+                    cursor::move_to(&mut self.content, line + error.line, column);
+                    self.invalid_json(self.folds.real_line(line) + error.line, column);
+                    self.scroll(0);
+
+                    Command::none()
+                }
+            };
+        }
+
+        let text = self.text();
+
+        match format(&text) {
+            Ok(mut formatted) => {
+                if text.ends_with('\n') {
+                    formatted.push('\n');
+                }
+
+                let (line, column) = self.content.cursor_position();
+
+                self.folds.clear();
+                self.show(&formatted, line, column);
+                self.is_dirty = true;
+                self.notify_language_server();
+            }
+            Err(error) => {
+                match self.folds.display_line(error.line) {
+                    Some(line) => cursor::move_to(&mut self.content, line, error.column),
+                    None => {
+                        self.folds.clear();
+                        self.show(&text, error.line, error.column);
+                    }
+                }
+
+                self.invalid_json(error.line, error.column);
+                self.scroll(0);
+            }
+        }
+
+        Command::none()
+    }
+
+    /// Reports that the document is not valid JSON from `line` and `column`
+    /// on, in the status bar and the output panel.
+    fn invalid_json(&mut self, line: usize, column: usize) {
+        self.error = Some(EditorError::InvalidJson(line, column));
+
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+
+        self.append_output(output::Line::at(
+            format!("{}:{}:{}: invalid JSON", path.display(), line + 1, column + 1),
+            output::Location {
+                path,
+                line,
+                character: column,
+            },
+        ));
+    }
+
+    /// Keeps the folds of the current file in the session, to fold them
+    /// again the next time it is opened.
+    fn remember_folds(&mut self) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+
+        let anchors: Vec<_> = self
+            .folds
+            .headers(0..=usize::MAX)
+            .into_iter()
+            .filter_map(|line| {
+                let text = self.content.line(line)?.to_string();
+
+                Some(session::Anchor {
+                    line: self.folds.real_line(line),
+                    text,
+                })
+            })
+            .collect();
+
+        let key = session::key(path);
+        let previous = self.session.folds.get(&key);
+
+        if previous.map_or(anchors.is_empty(), |previous| *previous == anchors) {
+            return;
+        }
+
+        if anchors.is_empty() {
+            let _ = self.session.folds.remove(&key);
+        } else {
+            let _ = self.session.folds.insert(key, anchors);
+        }
+
+        // Losing the session only loses folds, which is not worth an error
+        let _ = self.session.save();
+    }
+
+    /// Folds the regions that were folded when the file was last open.
+    /// Keeps the notes of the file in the session, each with the text of
+    /// its line to find it by next time.
+    fn remember_notes(&mut self) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+
+        let text = self.text();
+        let lines: Vec<&str> = text.split('\n').collect();
+
+        for note in &mut self.notes {
+            note.anchor.text = lines
+                .get(note.anchor.line)
+                .map_or(String::new(), |line| (*line).to_owned());
+        }
+
+        let key = session::key(path);
+
+        let previous = self.session.notes.get(&key);
+
+        if previous.map_or(self.notes.is_empty(), |previous| *previous == self.notes) {
+            return;
+        }
+
+        if self.notes.is_empty() {
+            let _ = self.session.notes.remove(&key);
+        } else {
+            let _ = self.session.notes.insert(key, self.notes.clone());
+        }
+
+        let _ = self.session.save();
+    }
+
+    /// Puts back the notes of the file, on the lines with the text they
+    /// were on or else where they were.
+    fn restore_notes(&mut self) {
+        self.note = None;
+        self.notes = self
+            .path
+            .as_deref()
+            .and_then(|path| self.session.notes.get(&session::key(path)))
+            .cloned()
+            .unwrap_or_default();
+
+        let text = self.text();
+        let lines: Vec<&str> = text.split('\n').collect();
+
+        for note in &mut self.notes {
+            note.anchor.line = note
+                .anchor
+                .find(&lines)
+                .unwrap_or(note.anchor.line.min(lines.len() - 1));
+        }
+
+        self.notes.sort_by_key(|note| note.anchor.line);
+    }
+
+    fn restore_folds(&mut self) {
+        let Some(anchors) = self
+            .path
+            .as_deref()
+            .and_then(|path| self.session.folds.get(&session::key(path)))
+        else {
+            return;
+        };
+
+        let mut text = self.content.text();
+
+        let mut starts: Vec<usize> = {
+            let lines: Vec<&str> = text.split('\n').collect();
+
+            anchors
+                .iter()
+                .filter_map(|anchor| anchor.find(&lines))
+                .collect()
+        };
+
+        starts.sort_unstable();
+        starts.dedup();
+
+        // Folding from the bottom keeps the lines above where they are
+        for start in starts.into_iter().rev() {
+            let Some(region) = self.regions.iter().find(|region| region.start == start) else {
+                continue;
+            };
+
+            // Only its end may have moved up, over the folds inside it
+            if let Some(end) = self.folds.display_line(region.end) {
+                text = self.folds.fold(&text, fold::Region { start, end });
+            }
+        }
+
+        if !self.folds.is_empty() {
+            self.show(&text, 0, 0);
+        }
+    }
+
+    /// Replaces the displayed text, placing the cursor at the given position.
+    fn show(&mut self, text: &str, line: usize, column: usize) {
+        self.content = text_editor::Content::with(text);
+        self.csv_raw = None;
+        cursor::move_to(&mut self.content, line, column);
+        self.is_indent_mixed = indent::is_mixed(text);
+        self.analyze();
+        self.scroll(0);
+    }
+
+    /// Recomputes everything derived from the displayed text, and the
+    /// breadcrumbs of the cursor line.
+    fn analyze(&mut self) {
+        self.analyze_text();
+        self.refresh_breadcrumbs();
+    }
+
+    /// Recomputes everything derived from the displayed text, but the
+    /// breadcrumbs, which edits refresh once typing pauses.
+    fn analyze_text(&mut self) {
+        let text = self.content.text();
+
+        // Any change may be one the local history lacks
+        self.snapshot_at = None;
+
+        if self.local_history.as_ref().is_some_and(|state| state.selected.is_some()) {
+            let current = self.text();
+
+            if let Some(state) = self.local_history.as_mut() {
+                state.compare(&current);
+            }
+        }
+
+        if let Some(state) = self.find.as_mut() {
+            state.search(&text);
+        }
+
+        self.reparse(&text);
+
+        if self.is_plain {
+            self.regions.clear();
+            self.brackets.clear();
+            self.suspicious.clear();
+            self.conflicts.clear();
+            self.breadcrumbs.clear();
+
+            return;
+        }
+
+        self.regions = fold::regions(&text, self.extension());
+        self.brackets = if self.rainbow_brackets {
+            highlight::rainbow(&text)
+        } else {
+            highlight::Overlays::new()
+        };
+
+        self.suspicious = unicode::scan(&text);
+
+        // Folding hides lines, so conflicts are found in the full text
+        self.conflicts = if text.contains("<<<<<<<") || !self.folds.is_empty() {
+            conflict::find(&self.text())
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Sets the document up as the file at `path` was last time.
+    fn restore_document(&mut self, path: &Path) {
+        let document = self.session.document(&session::key(path));
+
+        self.language = document.language;
+        self.wraps_at_column = document.wraps_at_column;
+        self.is_kept_read_only = document.is_read_only;
+        self.is_kept_plain = document.is_plain;
+        self.sticks_to_bottom = document.sticks_to_bottom;
+    }
+
+    /// Where file dialogs start, as configured.
+    fn dialog_directory(&self) -> Option<PathBuf> {
+        let file_directory = || {
+            self.path
+                .as_deref()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf)
+        };
+
+        match self.config.dialog_directory {
+            config::DialogDirectory::File => {
+                file_directory().or_else(|| self.session.last_directory.clone())
+            }
+            config::DialogDirectory::LastUsed => self.session.last_directory.clone(),
+            config::DialogDirectory::Fixed => self.config.fixed_directory.clone(),
+        }
+    }
+
+    /// Remembers the directory of `path` as the one used last.
+    fn remember_directory(&mut self, path: &Path) {
+        let directory = path.parent().map(Path::to_path_buf);
+
+        if directory.is_some() && directory != self.session.last_directory {
+            self.session.last_directory = directory;
+
+            let _ = self.session.save();
+        }
+    }
+
+    /// Keeps how the document is set up for the next time its file opens.
+    fn remember_document(&mut self) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+
+        let document = session::Document {
+            language: self.language.clone(),
+            wraps_at_column: self.wraps_at_column,
+            is_read_only: self.is_kept_read_only,
+            is_plain: self.is_kept_plain,
+            sticks_to_bottom: self.sticks_to_bottom,
+            used: 0,
+        };
+
+        self.session.remember(session::key(path), document);
+
+        let _ = self.session.save();
+    }
+
+    /// The column the document wraps at, if it does.
+    fn wrap_column(&self) -> Option<usize> {
+        match self.wraps_at_column {
+            Some(true) => Some(self.fixed_wrap_column()),
+            Some(false) => None,
+            None => self.config.wrap_column,
+        }
+    }
+
+    /// The column lines wrap at when they wrap at a fixed one.
+    fn fixed_wrap_column(&self) -> usize {
+        self.config.wrap_column.unwrap_or_else(|| {
+            self.wrap_column
+                .parse()
+                .ok()
+                .filter(|column| *column > 0)
+                .unwrap_or(DEFAULT_WRAP_COLUMN)
+        })
+    }
+
+    fn refresh_breadcrumbs(&mut self) {
+        let (line, _) = self.content.cursor_position();
+
+        self.breadcrumbs = outline::scopes(&self.regions, line, |line| {
+            self.content.line(line).map(|text| text.to_string())
+        });
+    }
+
+    /// Tracks the first visible line after scrolling by `lines`.
+    ///
+    /// The widget keeps its scroll offset to itself, so this follows the
+    /// scroll wheel and keeps the cursor in the estimated viewport.
+    fn scroll(&mut self, lines: i32) {
+        let last_line = self.content.line_count().saturating_sub(1);
+        let (cursor, _) = self.content.cursor_position();
+
+        self.top_line = self.top_line.saturating_add_signed(lines as isize).min(last_line);
+
+        if lines == 0 {
+            if cursor < self.top_line {
+                self.top_line = cursor;
+            } else if cursor >= self.top_line + self.visible_lines {
+                self.top_line = cursor + 1 - self.visible_lines;
+            }
+        }
+    }
+
+    /// Scrolls so that the configured number of lines stays in view above
+    /// and below the cursor, as far as the document goes.
+    fn keep_scroll_off(&mut self) {
+        let margin =
+            usize::from(self.config.scroll_off).min(self.visible_lines.saturating_sub(1) / 2);
+        let (cursor, _) = self.content.cursor_position();
+        let last_top = self.content.line_count().saturating_sub(self.visible_lines);
+
+        let top_line = if cursor < self.top_line + margin {
+            cursor.saturating_sub(margin)
+        } else if cursor + margin >= self.top_line + self.visible_lines {
+            (cursor + margin + 1 - self.visible_lines).min(last_top).max(self.top_line)
+        } else {
+            return;
+        };
+
+        let lines = top_line as i32 - self.top_line as i32;
+
+        if lines != 0 {
+            self.content.edit(text_editor::Action::Scroll { lines });
+            self.scroll(lines);
+        }
+    }
+
+    /// Scrolls by `lines` over the next frames, moving the cursor along
+    /// when paging.
+    ///
+    /// Scrolling further the same way adds up, while anything else starts
+    /// over.
+    fn glide(&mut self, lines: i32, is_paging: bool) {
+        let lines = match self.scrolling {
+            Some(scrolling)
+                if scrolling.is_paging == is_paging
+                    && scrolling.lines.signum() == lines.signum() =>
+            {
+                scrolling.lines + lines
+            }
+            _ => lines,
+        };
+
+        self.scrolling = (lines != 0).then_some(Scrolling { lines, is_paging });
+    }
+
+    /// Whether the end of the document is in view.
+    fn is_at_bottom(&self) -> bool {
+        self.top_line + self.visible_lines >= self.content.line_count()
+    }
+
+    /// Scrolls the view down to the end of the document.
+    fn scroll_to_bottom(&mut self) {
+        let last_top = self.content.line_count().saturating_sub(self.visible_lines);
+        let lines = last_top as i32 - self.top_line as i32;
+
+        if lines > 0 {
+            self.content.edit(text_editor::Action::Scroll { lines });
+            self.scroll(lines);
+        }
+    }
+
+    /// Appends what the followed file grew by, unless following is paused
+    /// because the end of the document is out of view.
+    fn catch_up(&mut self) {
+        if self.follow.is_none()
+            || self.follow_backlog.is_empty()
+            || self.csv_raw.is_some()
+            || !self.is_at_bottom()
+        {
+            return;
+        }
+
+        let text = std::mem::take(&mut self.follow_backlog);
+
+        // Growing is not an edit, and followed files are often read-only
+        self.content
+            .edit(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+        self.content
+            .edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+        self.scroll(0);
+        self.analyze();
+        self.notify_language_server();
+    }
+
+    /// Moves the cursor to a character of a line of the full text.
+    ///
+    /// Everything gets unfolded if the line is folded away.
+    /// Where the cursor and selection are in the file at `path`, which
+    /// collapses the selection.
+    fn place(&mut self, path: PathBuf) -> Place {
+        let visible = self.content.text();
+        let selection = self.content.selection();
+        let offset = self.content.cursor_position().0.saturating_sub(self.top_line);
+
+        let start = self.selected_range(&visible).start;
+        let real = |position: (usize, usize)| (self.folds.real_line(position.0), position.1);
+
+        Place {
+            path,
+            text: self.text(),
+            start: real(scope::position(&visible, start)),
+            end: selection
+                .map(|selection| real(scope::position(&visible, start + selection.len()))),
+            offset,
+        }
+    }
+
+    /// Puts the cursor and selection back where they were before the file
+    /// was read again, following the lines they were on.
+    fn return_to(&mut self, place: Place) {
+        let text = self.text();
+        let follow = |(line, column): (usize, usize)| {
+            (cursor::follow_line(&place.text, &text, line), column)
+        };
+
+        let start = follow(place.start);
+        let end = place.end.map(follow);
+        let is_folded = |line: usize| self.folds.display_line(line).is_none();
+
+        if is_folded(start.0) || end.is_some_and(|(line, _)| is_folded(line)) {
+            self.folds.clear();
+            self.show(&text, 0, 0);
+        }
+
+        let display = |(line, column): (usize, usize)| {
+            (self.folds.display_line(line).unwrap_or(line), column)
+        };
+
+        let (line, column) = display(start);
+        cursor::move_to(&mut self.content, line, column);
+
+        if let Some((line, column)) = end.map(display) {
+            cursor::select_to(&mut self.content, line, column);
+        }
+
+        self.scroll(0);
+
+        // The cursor stays as far down the view as it was
+        let (line, _) = self.content.cursor_position();
+        let last_top = self.content.line_count().saturating_sub(self.visible_lines);
+        let top_line = line.saturating_sub(place.offset).min(last_top);
+        let lines = top_line as i32 - self.top_line as i32;
+
+        if lines != 0 {
+            self.content.edit(text_editor::Action::Scroll { lines });
+            self.scroll(lines);
+        }
+
+        self.refresh_breadcrumbs();
+    }
+
+    fn jump_to(&mut self, line: usize, character: usize) {
+        if self.folds.display_line(line).is_none() {
+            let text = self.text();
+
+            self.folds.clear();
+            self.show(&text, line, 0);
+        }
+
+        let line = self.folds.display_line(line).unwrap_or(0);
+        let column = self
+            .content
+            .line(line)
+            .map_or(0, |text| cursor::column(&text, character));
+
+        cursor::move_to(&mut self.content, line, column);
+        self.scroll(0);
+        self.refresh_breadcrumbs();
+    }
+
+    /// How to back up the files about to be overwritten, if at all.
+    fn backup(&self) -> Option<config::Backup> {
+        self.config
+            .keep_backup
+            .then(|| self.config.backup.clone())
+    }
+
+    /// Where the cursor is, to come back to it later.
+    fn location(&self) -> history::Location {
+        let (line, character) = self.lsp_position();
+
+        history::Location {
+            path: self.path.clone(),
+            line,
+            character,
+        }
+    }
+
+    /// Opens a referenced location, in the open file unless it names another.
+    fn open_reference(&mut self, reference: location::Reference) -> Command<Message> {
+        let line = reference.line - 1;
+        let character = reference.column.map_or(0, |column| column - 1);
+        let lines = reference.end.map(|end| line..end);
+
+        self.history.push(self.location());
+
+        let command = match reference.path {
+            Some(path) => {
+                let path = location::resolve(&path, &self.root());
+                self.go_to(path, line, character)
+            }
+            None => {
+                self.jump_to(line, character);
+                Command::none()
+            }
+        };
+
+        // Either the file is open already, or it will be once it arrives
+        if self.pending_jump.is_some() {
+            self.pending_lines = lines;
+        } else {
+            self.highlighted_lines = lines;
+        }
+
+        command
+    }
+
+    /// Moves to a location of the history.
+    fn navigate(&mut self, location: history::Location) -> Command<Message> {
+        match location.path {
+            Some(path) => self.go_to(path, location.line, location.character),
+            None => {
+                self.jump_to(location.line, location.character);
+                Command::none()
+            }
+        }
+    }
+
+    /// Opens the file `load` reads, unless another is requested meanwhile.
+    fn open(
+        &mut self,
+        load: impl Future<Output = Result<Opened, EditorError>> + Send + 'static,
+    ) -> Command<Message> {
+        self.open_generation += 1;
+
+        let generation = self.open_generation;
+
+        Command::perform(load, move |result| Message::FileOpened(generation, result))
+    }
+
+    /// Reopens the file in `encoding`, at the same place.
+    fn reopen(&mut self, encoding: encoding::Encoding) -> Command<Message> {
+        let Some(path) = self.path.clone() else {
+            return Command::none();
+        };
+
+        self.is_encoding_open = false;
+
+        let (line, character) = self.lsp_position();
+        self.pending_jump = Some((path.clone(), line, character));
+
+        // Decoding the bytes read again, not the decoded text, and
+        // reading them from disk if they changed since
+        match (self.bytes.clone(), self.page) {
+            (_, Some(page)) => self.open(load_page(path, page.start, false, Some(encoding))),
+            (Some(bytes), None) => self.open(async move { decode(path, bytes, Some(encoding)) }),
+            (None, None) => self.open(load_file_as(
+                path,
+                Some(encoding),
+                self.config.max_buffer_size(),
+            )),
+        }
+    }
+
+    /// Moves the cursor to a location, opening its file if needed.
+    fn go_to(&mut self, path: PathBuf, line: usize, character: usize) -> Command<Message> {
+        let is_open = self.path.as_deref().and_then(|path| path.canonicalize().ok())
+            == path.canonicalize().ok();
+
+        if is_open {
+            self.jump_to(line, character);
+
+            Command::none()
+        } else {
+            self.pending_jump = Some((path.clone(), line, character));
+
+            if self.has_unsaved_changes() {
+                self.switch_to = Some(path);
+
+                return Command::none();
+            }
+
+            self.open(load_file(path, self.config.max_buffer_size()))
+        }
+    }
+
+    /// Adds a line to the output panel, forgetting the oldest ones beyond
+    /// the most kept.
+    fn append_output(&mut self, line: output::Line) {
+        let text = if self.output.is_empty() {
+            line.text.clone()
+        } else {
+            format!("\n{}", line.text)
+        };
+
+        self.output.push(line);
+
+        if self.output.len() > output::MAX_LINES {
+            // Dropping a tenth at a time avoids rebuilding it for every line
+            self.output.drain(..output::MAX_LINES / 10);
+
+            let text: Vec<&str> = self.output.iter().map(|line| line.text.as_str()).collect();
+            self.output_content = text_editor::Content::with(&text.join("\n"));
+        } else {
+            self.output_content
+                .edit(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+            self.output_content
+                .edit(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+        }
+    }
+
+    /// Starts, switches or stops the language server for the open file.
+    fn open_in_language_server(&mut self) {
+        let path = self.path.as_deref().and_then(|path| path.canonicalize().ok());
+
+        let server = self
+            .lsp_enabled
+            .then(|| self.config.language_servers.get(self.extension()))
+            .flatten()
+            .cloned();
+
+        let (Some(path), Some(server)) = (path, server) else {
+            if let Some(state) = self.lsp.as_mut() {
+                if let Some(document) = state.document.take() {
+                    state.send(lsp::Request::Close { path: document });
+                }
+            }
+
+            return;
+        };
+
+        let root = lsp::workspace_root(&path);
+
+        let mut state = match self.lsp.take() {
+            Some(state) if state.server == server && state.root == root => state,
+            _ => lsp::State::new(server, root),
+        };
+
+        if state.connection.is_some() && state.document.as_ref() != Some(&path) {
+            if let Some(document) = state.document.take() {
+                state.send(lsp::Request::Close { path: document });
+            }
+
+            state.send(lsp::Request::Open {
+                path: path.clone(),
+                language: state.server.language.clone(),
+                text: self.text(),
+            });
+
+            state.send(lsp::Request::SemanticTokens { path: path.clone() });
+            state.document = Some(path);
+            state.version = 0;
+        }
+
+        self.lsp = Some(state);
+    }
+
+    fn notify_language_server(&mut self) {
+        if self
+            .lsp
+            .as_ref()
+            .is_none_or(|state| state.document.is_none())
+        {
+            return;
+        }
+
+        let text = self.text();
+
+        if let Some(state) = self.lsp.as_mut() {
+            if let Some(path) = state.document.clone() {
+                state.version += 1;
+
+                let version = state.version;
+                state.send(lsp::Request::Change {
+                    path: path.clone(),
+                    version,
+                    text,
+                });
+                state.send(lsp::Request::SemanticTokens { path });
+            }
+        }
+    }
+
+    /// The cursor position in the full text, as a line and character.
+    fn lsp_position(&self) -> (usize, usize) {
+        let (line, column) = self.content.cursor_position();
+
+        let character = self.content.line(line).map_or(0, |text| {
+            text.get(..column).unwrap_or(&*text).chars().count()
+        });
+
+        (self.folds.real_line(line), character)
+    }
+
+    /// Offers the words of the buffer that extend the one being typed.
+    fn complete_word(&mut self) {
+        let prefix = self.word_before_cursor();
+
+        if prefix.chars().count() < MIN_COMPLETION_PREFIX {
+            self.completions.clear();
+            return;
+        }
+
+        let (line, _) = self.content.cursor_position();
+
+        self.completions =
+            complete::words(&self.content.text(), &prefix, line, MAX_COMPLETIONS);
+        self.completion_index = 0;
+    }
+
+    fn request_completions(&mut self) {
+        let (line, character) = self.lsp_position();
+
+        if let Some(state) = self.lsp.as_mut() {
+            if let Some(path) = state.document.clone() {
+                state.send(lsp::Request::Complete {
+                    path,
+                    line,
+                    character,
+                });
+            }
+        }
+    }
+
+    /// The Emmet abbreviation right before the cursor and its expansion.
+    fn abbreviation(&self) -> Option<(String, String)> {
+        let extension = self.extension();
+
+        if !emmet::is_markup(extension) {
+            return None;
+        }
+
+        let (line, column) = self.content.cursor_position();
+        let text = self.content.line(line)?;
+        let before = text.get(..column)?;
+
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| emmet::is_abbreviation(*c))
+            .last()
+            .map_or(before.len(), |(start, _)| start);
+
+        // Right after `<` or `</` this is a tag being typed by hand
+        if start == before.len() || before[..start].ends_with(['<', '/']) {
+            return None;
+        }
+
+        let indentation: String = text.chars().take_while(|c| c.is_whitespace()).collect();
+        let abbreviation = &before[start..];
+        let expansion = emmet::expand(abbreviation, &indentation, extension != "xml")?;
+
+        Some((abbreviation.to_owned(), expansion))
+    }
+
+    /// Replaces `abbreviation` with its `expansion`, leaving the cursor in
+    /// the first empty element.
+    fn expand_abbreviation(&mut self, abbreviation: &str, expansion: String) -> Command<Message> {
+        let (line, column) = self.content.cursor_position();
+        let start = column - abbreviation.len();
+        let caret = expansion.find("></").map(|offset| offset + 1);
+
+        let mut commands = Vec::new();
+
+        for _ in abbreviation.chars() {
+            commands.push(self.edit(text_editor::Action::Edit(
+                text_editor::Edit::Backspace,
+            )));
+        }
+
+        commands.push(self.edit(text_editor::Action::Edit(
+            text_editor::Edit::Paste(Arc::new(expansion.clone())),
+        )));
+
+        if let Some(offset) = caret {
+            let before = &expansion[..offset];
+            let column = match before.rfind('\n') {
+                Some(newline) => offset - newline - 1,
+                None => start + offset,
+            };
+
+            cursor::move_to(&mut self.content, line + before.matches('\n').count(), column);
+        }
+
+        Command::batch(commands)
+    }
+
+    /// The identifier characters right before the cursor.
+    fn word_before_cursor(&self) -> String {
+        let (line, column) = self.content.cursor_position();
+
+        let Some(text) = self.content.line(line) else {
+            return String::new();
+        };
+
+        let before = text.get(..column).unwrap_or(&*text);
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| complete::is_identifier(*c))
+            .last()
+            .map_or(before.len(), |(index, _)| index);
+
+        before[start..].to_owned()
+    }
+
+    /// The theme of the language of the document, or else the one picked.
+    fn theme_choice(&self) -> themes::Choice {
+        self.config
+            .themes
+            .get(self.extension())
+            .and_then(|name| themes::Choice::named(name))
+            .unwrap_or(self.theme)
+    }
+
+    /// Whether the language of the document is one picked to highlight
+    /// with tree-sitter.
+    fn is_tree_sitter(&self) -> bool {
+        let extension = self.extension();
+
+        !self.is_plain
+            && grammar::has_grammar(extension)
+            && self.config.tree_sitter.iter().any(|picked| picked == extension)
+    }
+
+    /// The spans tree-sitter found in the document, if it highlights it.
+    fn tree_sitter_spans(&self) -> Option<Arc<grammar::Spans>> {
+        self.syntax
+            .as_ref()
+            .filter(|syntax| self.is_tree_sitter() && syntax.extension() == self.extension())
+            .map(grammar::Syntax::spans)
+    }
+
+    /// Parses the document with tree-sitter again from the last parse, or
+    /// from scratch when its language changed.
+    fn reparse(&mut self, text: &str) {
+        if !self.is_tree_sitter() {
+            self.syntax = None;
+            return;
+        }
+
+        let extension = self.extension().to_owned();
+
+        match self.syntax.as_mut() {
+            Some(syntax) if syntax.extension() == extension => syntax.edit(text.to_owned()),
+            _ => self.syntax = Some(grammar::Syntax::new(text.to_owned(), &extension)),
+        }
+    }
+
+    /// The colored spans laid over the syntax highlighting.
+    fn overlays(&self) -> highlight::Overlays {
+        let mut overlays = self.brackets.clone();
+
+        if self.is_plain {
+            return overlays;
+        }
+
+        // Under everything else, as it only refines the syntax highlighting
+        let colors = self.theme_choice().colors();
+
+        for token in &self.semantic_tokens {
+            let (Some(line), Some(color)) = (
+                self.folds.display_line(token.line),
+                highlight::semantic(colors, &token.kind),
+            ) else {
+                continue;
+            };
+
+            let Some(text) = self.content.line(line) else {
+                continue;
+            };
+
+            overlays.entry(line).or_default().push((
+                highlight::byte_range(&text, token.character, token.character + token.length),
+                color,
+            ));
+        }
+
+        if let (Some(lines), Some(color)) = (
+            self.highlighted_lines.clone(),
+            self.config.colors.color(config::Highlight::ReferredLines),
+        ) {
+            for line in lines {
+                let Some(line) = self.folds.display_line(line) else {
+                    continue;
+                };
+
+                let length = self.content.line(line).map_or(0, |text| text.len());
+
+                overlays.entry(line).or_default().push((0..length, color));
+            }
+        }
+
+        // Over the semantic tokens, which color comments whole
+        let keywords = self.config.todo_markers.keys().map(String::as_str);
+
+        for marker in todo::find(&self.content.text(), keywords) {
+            let Some(color) = self
+                .config
+                .todo_markers
+                .get(&marker.keyword)
+                .and_then(|color| config::parse_color(color))
+            else {
+                continue;
+            };
+
+            overlays
+                .entry(marker.line)
+                .or_default()
+                .push((marker.range, color));
+        }
+
+        for line in self.conflicts.iter().flat_map(conflict::Conflict::markers) {
+            let Some(line) = self.folds.display_line(line) else {
+                continue;
+            };
+
+            let length = self.content.line(line).map_or(0, |text| text.len());
+
+            overlays
+                .entry(line)
+                .or_default()
+                .push((0..length, Color::from_rgb(0.8, 0.4, 0.9)));
+        }
+
+        if let Some(delimiter) = csv::delimiter(self.extension()) {
+            let fields = |line: &str| {
+                if self.csv_raw.is_some() {
+                    csv::aligned_fields(line)
+                } else {
+                    csv::fields(line, delimiter)
+                }
+            };
+
+            let (line, column) = self.content.cursor_position();
+            let index = self.content.line(line).map_or(0, |text| {
+                fields(&text)
+                    .iter()
+                    .position(|field| column <= field.end)
+                    .unwrap_or(0)
+            });
+
+            for (number, text) in self.content.text().split('\n').enumerate() {
+                if let Some(field) = fields(text).get(index) {
+                    overlays
+                        .entry(number)
+                        .or_default()
+                        .push((field.clone(), Color::from_rgb(0.4, 0.7, 1.0)));
+                }
+            }
+        }
+
+        for diagnostic in &self.diagnostics {
+            let Some(line) = self.folds.display_line(diagnostic.line) else {
+                continue;
+            };
+
+            let Some(text) = self.content.line(line) else {
+                continue;
+            };
+
+            let color = match diagnostic.level {
+                diagnostics::Level::Error => Color::from_rgb(0.9, 0.3, 0.3),
+                diagnostics::Level::Warning => Color::from_rgb(0.9, 0.7, 0.2),
+            };
+
+            let (start, end) = diagnostic.columns;
+
+            overlays
+                .entry(line)
+                .or_default()
+                .push((highlight::byte_range(&text, start, end), color));
+        }
+
+        // Holding Ctrl shows what Ctrl+Click can open, since the widget
+        // cannot tell what the mouse is over
+        if self.modifiers.command() {
+            let visible = self.top_line..self.top_line + self.visible_lines;
+
+            for line in visible {
+                let Some(text) = self.content.line(line) else {
+                    break;
+                };
+
+                for (range, _) in location::find(&text) {
+                    overlays
+                        .entry(line)
+                        .or_default()
+                        .push((range, Color::from_rgb(0.3, 0.6, 1.0)));
+                }
+            }
+        }
+
+        for suspicious in &self.suspicious {
+            let color = match suspicious.kind {
+                unicode::Kind::Bidirectional => Color::from_rgb(0.9, 0.3, 0.3),
+                _ => Color::from_rgb(0.9, 0.2, 0.9),
+            };
+
+            overlays
+                .entry(suspicious.line)
+                .or_default()
+                .push((suspicious.range.clone(), color));
+        }
+
+        if let Some(state) = &self.find {
+            let current = state.current(self.content.cursor_position());
+
+            for (index, found) in state.matches.iter().enumerate() {
+                let highlight = if Some(index) == current {
+                    config::Highlight::CurrentMatch
+                } else {
+                    config::Highlight::FindMatch
+                };
+
+                if let Some(color) = self.config.colors.color(highlight) {
+                    overlays
+                        .entry(found.line)
+                        .or_default()
+                        .push((found.range.clone(), color));
+                }
+            }
+        }
+
+        for (line, start) in self.long_lines() {
+            let length = self.content.line(line).map_or(start, |text| text.len());
+
+            overlays
+                .entry(line)
+                .or_default()
+                .push((start..length, Color::from_rgb(0.9, 0.4, 0.4)));
+        }
+
+        if emmet::is_markup(self.extension()) {
+            let text = self.content.text();
+            let (line, column) = self.content.cursor_position();
+            let offset = scope::offset(&text, line, column);
+
+            // The names of the tag under the cursor and of its partner
+            if let Some(pair) = tag::at(&text, offset, self.extension() != "xml") {
+                for name in [pair.open.name, pair.close.name] {
+                    let (line, start) = scope::position(&text, name.start);
+
+                    overlays.entry(line).or_default().push((
+                        start..start + name.len(),
+                        Color::from_rgb(0.3, 0.8, 0.6),
+                    ));
+                }
+            }
+        }
+
+        overlays
+    }
+
+    fn run_keymap(&mut self, outputs: Vec<Output>) -> Command<Message> {
+        let commands: Vec<_> = outputs
+            .into_iter()
+            .map(|output| match output {
+                Output::Action(action) => self.edit(action),
+                Output::Message(message) => self.update(message),
+            })
+            .collect();
+
+        Command::batch(commands)
+    }
+
+    /// Remembers the query of the find bar in the history.
+    fn remember_find_query(&mut self) {
+        let Some(state) = self.find.as_ref() else {
+            return;
+        };
+
+        if self.searches.record(&state.query) {
+            self.save_query_history();
+        }
+    }
+
+    /// Closes the find bar, leaving the match under the cursor selected to
+    /// edit it.
+    fn close_find(&mut self) {
+        self.remember_find_query();
+
+        let Some(state) = self.find.take() else {
+            return;
+        };
+
+        self.query_input = None;
+
+        let Some(found) = state
+            .current(self.content.cursor_position())
+            .map(|index| state.matches[index].clone())
+        else {
+            return;
+        };
+
+        let text = self.content.text();
+        let matched = text
+            .split('\n')
+            .nth(found.line)
+            .and_then(|line| line.get(found.range.clone()));
+
+        let is_selected = self.content.selection().as_deref() == matched;
+
+        if !is_selected {
+            cursor::move_to(&mut self.content, found.line, found.range.start);
+            cursor::select_to(&mut self.content, found.line, found.range.end);
+        }
+    }
+
+    fn save_query_history(&mut self) {
+        self.session.searches = self.searches.entries().map(str::to_owned).collect();
+        self.session.replacements = self.replacements.entries().map(str::to_owned).collect();
+
+        let _ = self.session.save();
+    }
+
+    /// Selects the match that `pick` chooses relative to the cursor.
+    fn select_match(&mut self, pick: fn(&find::State, (usize, usize)) -> Option<&find::Match>) {
+        let Some(found) = self
+            .find
+            .as_ref()
+            .and_then(|state| pick(state, self.content.cursor_position()))
+            .cloned()
+        else {
+            return;
+        };
+
+        self.history.push(self.location());
+
+        cursor::move_to(&mut self.content, found.line, found.range.start);
+        cursor::select_to(&mut self.content, found.line, found.range.end);
+        self.scroll(0);
+        self.refresh_breadcrumbs();
+    }
+
+    /// The headers of the scopes that started above the viewport.
+    fn sticky_lines(&self) -> Vec<String> {
+        let enclosing: Vec<_> = self
+            .regions
+            .iter()
+            .filter(|region| region.start < self.top_line && self.top_line <= region.end)
+            .collect();
+
+        enclosing[enclosing.len().saturating_sub(MAX_STICKY_LINES)..]
+            .iter()
+            .filter_map(|region| self.content.line(region.start))
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// Unfolds the folds that the next edit could split or delete.
+    fn unfold_edited_lines(&mut self) {
+        let (line, column) = self.content.cursor_position();
+        let selection = self.content.selection();
+        let reach = selection
+            .as_ref()
+            .map_or(0, |selection| selection.matches('\n').count());
+
+        let headers = self
+            .folds
+            .headers(line.saturating_sub(reach)..=line + reach);
+
+        if headers.is_empty() {
+            return;
+        }
+
+        let mut text = self.content.text();
+
+        // The other end of the selection, which mostly ends at the cursor
+        // but may start there
+        let anchor = selection.map(|selection| {
+            let offset = scope::offset(&text, line, column);
+
+            if text[..offset].ends_with(selection.as_str()) {
+                scope::position(&text, offset - selection.len())
+            } else {
+                scope::position(&text, offset + selection.len())
+            }
+        });
+
+        let mut cursor = line;
+        let mut anchor_line = anchor.map_or(line, |(line, _)| line);
+
+        for header in headers.into_iter().rev() {
+            let (unfolded, revealed) = self.folds.unfold(&text, header);
+
+            if header < line {
+                cursor += revealed;
+            }
+
+            if anchor.is_some_and(|(line, _)| header < line) {
+                anchor_line += revealed;
+            }
+
+            text = unfolded;
+        }
+
+        // The edit applies to the selection it was made with
+        match anchor {
+            Some((_, anchor_column)) => {
+                self.show(&text, anchor_line, anchor_column);
+                cursor::select_to(&mut self.content, cursor, column);
+            }
+            None => self.show(&text, cursor, column),
+        }
+    }
+
+    /// The directory that project-wide operations work in.
+    fn root(&self) -> PathBuf {
+        self.path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+    }
+
+    fn find_bar<'a>(&self, state: &'a find::State) -> Element<'a, Message> {
+        let count = match (&state.error, state.current(self.content.cursor_position())) {
+            (Some(_), _) => "Invalid pattern".to_owned(),
+            (None, Some(index)) => format!("{} of {}", index + 1, state.matches.len()),
+            (None, None) => format!("{} matches", state.matches.len()),
+        };
+
+        let options = state.options;
+
+        // The options that are on are highlighted
+        let option = |label: &'static str, description: &'static str, is_on, options| {
+            tooltip(
+                button(text(label).size(14))
+                    .on_press(Message::FindOptionsChanged(options))
+                    .style(if is_on {
+                        theme::Button::Primary
+                    } else {
+                        theme::Button::Secondary
+                    }),
+                description,
+                tooltip::Position::Bottom,
+            )
+        };
+
+        row![
+            text_input("Find", &state.query)
+                .id(find_input())
+                .on_input(Message::FindQueryChanged)
+                .on_submit(Message::FindNext),
+            option(
+                "Aa",
+                "Match case",
+                options.match_case,
+                find::Options {
+                    match_case: !options.match_case,
+                    ..options
+                }
+            ),
+            option(
+                "W",
+                "Whole word",
+                options.whole_word,
+                find::Options {
+                    whole_word: !options.whole_word,
+                    ..options
+                }
+            ),
+            option(
+                ".*",
+                "Regular expression",
+                options.regex,
+                find::Options {
+                    regex: !options.regex,
+                    ..options
+                }
+            ),
+            text(count).size(14),
+            button("Previous").on_press(Message::FindPrevious),
+            button("Next").on_press(Message::FindNext),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn local_history_panel<'a>(&self, state: &'a local_history::State) -> Element<'a, Message> {
+        let snapshots = state.snapshots.iter().enumerate().fold(
+            Column::new(),
+            |snapshots, (index, snapshot)| {
+                let is_selected = state
+                    .selected
+                    .as_ref()
+                    .is_some_and(|(selected, _)| *selected == index);
+
+                snapshots.push(
+                    button(text(snapshot.age()).size(14))
+                        .on_press(Message::SnapshotSelected(index))
+                        .width(Length::Fill)
+                        .padding([2, 5])
+                        .style(if is_selected {
+                            theme::Button::Primary
+                        } else {
+                            theme::Button::Text
+                        }),
+                )
+            },
+        );
+
+        let diff: Element<'_, Message> = match &state.selected {
+            Some(_) => {
+                let lines = state.diff.iter().fold(Column::new(), |lines, (tag, change)| {
+                    let (sign, color) = match tag {
+                        similar::ChangeTag::Delete => ("-", Some(Color::from_rgb(0.9, 0.3, 0.3))),
+                        similar::ChangeTag::Insert => ("+", Some(Color::from_rgb(0.3, 0.8, 0.4))),
+                        similar::ChangeTag::Equal => (" ", None),
+                    };
+
+                    let line = text(format!("{sign} {change}")).size(14).font(self.font);
+
+                    lines.push(match color {
+                        Some(color) => line.style(theme::Text::Color(color)),
+                        None => line,
+                    })
+                });
+
+                column![
+                    scrollable(lines).height(Length::Fixed(200.0)),
+                    button("Restore this version").on_press(Message::RestoreSnapshot),
+                ]
+                .spacing(10)
+                .into()
+            }
+            None => text("Pick a snapshot to compare it with the document")
+                .size(14)
+                .into(),
+        };
+
+        let list: Element<'_, Message> = if state.snapshots.is_empty() {
+            text("No snapshots of this file yet").size(14).into()
+        } else {
+            scrollable(snapshots).height(Length::Fixed(200.0)).into()
+        };
+
+        container(
+            column![
+                text("Local history"),
+                row![container(list).width(Length::Fixed(200.0)), diff].spacing(10),
+            ]
+            .spacing(10),
+        )
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    /// The markers of the open file, and those of the other files below
+    /// its folder.
+    fn todo_panel<'a>(&self, todos: &'a [(PathBuf, todo::Marker)]) -> Element<'a, Message> {
+        let root = self.root();
+        let entry = |label: String, message: Message| {
+            button(text(label).size(14))
+                .on_press(message)
+                .width(Length::Fill)
+                .padding([2, 5])
+                .style(theme::Button::Text)
+        };
+        let keywords = self.config.todo_markers.keys().map(String::as_str);
+
+        let here = todo::find(&self.text(), keywords).into_iter().fold(
+            column![text("In this file").size(14)],
+            |list, marker| {
+                list.push(entry(
+                    format!("{}  {} {}", marker.line + 1, marker.keyword, marker.text),
+                    Message::TodoPicked(None, marker.line),
+                ))
+            },
+        );
+
+        let below = todos.iter().fold(
+            column![text(format!("Below {}", root.display())).size(14)],
+            |list, (path, marker)| {
+                list.push(entry(
+                    format!(
+                        "{}:{}  {} {}",
+                        todo::relative(path, &root),
+                        marker.line + 1,
+                        marker.keyword,
+                        marker.text
+                    ),
+                    Message::TodoPicked(Some(path.clone()), marker.line),
+                ))
+            },
+        );
+
+        let mut list = column![here].spacing(10);
+
+        if self.path.is_some() {
+            list = list.push(below);
+        }
+
+        container(
+            column![
+                row![
+                    text("TODO list").width(Length::Fill),
+                    button("Close")
+                        .on_press(Message::ToggleTodos)
+                        .style(theme::Button::Secondary),
+                ],
+                scrollable(list).height(Length::Fixed(200.0)),
+            ]
+            .spacing(10),
+        )
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    fn comparison_panel<'a>(&self, path: &Path, rows: &'a [compare::Row]) -> Element<'a, Message> {
+        let changed = Color::from_rgb(0.9, 0.7, 0.2);
+        let font = self.font;
+
+        let side = |line: &Option<(usize, String)>, is_changed: bool| {
+            let label = match line {
+                Some((number, text)) => format!("{:>5} {text}", number + 1),
+                None => String::new(),
+            };
+
+            let line = text(label).size(14).font(font).width(Length::Fill);
+
+            if is_changed {
+                line.style(theme::Text::Color(changed))
+            } else {
+                line
+            }
+        };
+
+        // Both sides scroll together, being rows of the same column
+        let lines = rows.iter().fold(Column::new(), |lines, row| {
+            lines.push(
+                row![side(&row.left, row.is_changed), side(&row.right, row.is_changed)]
+                    .spacing(10),
+            )
+        });
+
+        let changes = rows.iter().filter(|row| row.is_changed).count();
+
+        container(
+            column![
+                row![
+                    text(format!("Comparing with {}", path.display())).size(14),
+                    text(format!("{changes} changed lines")).size(14),
+                    horizontal_space(Length::Fill),
+                    button("Close").on_press(Message::CloseComparison),
+                ]
+                .spacing(10),
+                scrollable(lines).height(Length::Fixed(300.0)),
+            ]
+            .spacing(10),
+        )
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    fn context_menu(&self) -> Element<'_, Message> {
+        let has_selection = self.content.selection().is_some();
+
+        let item = |label: String, item: MenuItem, is_enabled: bool| {
+            button(text(label).size(14))
+                .on_press_maybe(is_enabled.then_some(Message::ContextMenuPicked(item)))
+                .width(Length::Fill)
+                .padding([2, 5])
+                .style(theme::Button::Text)
+        };
+
+        let menu = column![
+            item("Cut".to_owned(), MenuItem::Cut, has_selection),
+            item("Copy".to_owned(), MenuItem::Copy, has_selection),
+            item("Paste".to_owned(), MenuItem::Paste, true),
+            item("Select all".to_owned(), MenuItem::SelectAll, true),
+            item(
+                "Go to definition".to_owned(),
+                MenuItem::GoToDefinition,
+                self.lsp.is_some()
+            ),
+            item(
+                "Generate doc comment".to_owned(),
+                MenuItem::GenerateDocComment,
+                self.extension() == "rs"
+            ),
+        ];
+
+        // Transforms only make sense of a selection
+        let menu = if has_selection {
+            transform::Transform::ALL.iter().fold(menu, |menu, transform| {
+                menu.push(item(
+                    transform.to_string(),
+                    MenuItem::Transform(*transform),
+                    true,
+                ))
+            })
+        } else {
+            menu
+        };
+
+        container(menu)
+            .width(Length::Fixed(220.0))
+            .style(theme::Container::Box)
+            .into()
+    }
+
+    /// What tools printed, with the last run of the file and how it ended.
+    fn output_panel(&self) -> Element<'_, Message> {
+        let mut header = row![text("Output")].spacing(10);
+
+        if let Some(state) = &self.run {
+            let status = match state.outcome {
+                Some(outcome) => outcome.to_string(),
+                None => "Running...".to_owned(),
+            };
+
+            header = header.push(text(format!("$ {}", state.invocation)).size(14));
+            header = header.push(text(status).size(14));
+
+            if self.is_dirty {
+                header =
+                    header.push(text("(ran the saved file, not the unsaved changes)").size(14));
+            }
+        }
+
+        header = header.push(horizontal_space(Length::Fill));
+        header = header.push(
+            slider(
+                session::OUTPUT_HEIGHTS,
+                self.session.layout.output_height,
+                Message::OutputHeightChanged,
+            )
+            .step(10.0)
+            .on_release(Message::SaveLayout)
+            .width(Length::Fixed(100.0)),
+        );
+
+        if self.run.is_some() {
+            header = header.push(button("Run again").on_press(Message::Run));
+        }
+
+        if self.run.as_ref().is_some_and(|state| state.outcome.is_none()) {
+            header = header.push(button("Stop").on_press(Message::CancelTask(Task::Run)));
+        }
+
+        header = header.push(
+            button("Clear")
+                .on_press(Message::ClearOutput)
+                .style(theme::Button::Secondary),
+        );
+        header = header.push(
+            button("Close")
+                .on_press(Message::ToggleOutput)
+                .style(theme::Button::Secondary),
+        );
+
+        container(
+            column![
+                header,
+                text_editor(&self.output_content)
+                    .on_edit(Message::OutputAction)
+                    .font(Font::MONOSPACE)
+                    .height(Length::Fixed(self.session.layout.output_height)),
+            ]
+            .spacing(5),
+        )
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    fn settings_panel(&self) -> Element<'_, Message> {
+        let colors = config::Highlight::ALL.into_iter().fold(
+            Column::new().spacing(5),
+            |colors, highlight| {
+                let placeholder = if highlight == config::Highlight::Selection {
+                    "Theme default"
+                } else {
+                    "#rrggbb"
+                };
+
+                let mut entry = row![
+                    text(highlight.label()).width(Length::Fixed(150.0)),
+                    text_input(placeholder, self.config.colors.get(highlight))
+                        .on_input(move |value| Message::HighlightColorChanged(highlight, value))
+                        .width(Length::Fixed(120.0)),
+                ]
+                .spacing(10);
+
+                if !self.config.colors.is_valid(highlight) {
+                    entry = entry.push(
+                        text("Expected #rrggbb or #rrggbbaa")
+                            .size(14)
+                            .style(theme::Text::Color(Color::from_rgb(0.9, 0.3, 0.3))),
+                    );
+                }
+
+                colors.push(entry)
+            },
+        );
+
+        let tree_sitter = grammar::EXTENSIONS.into_iter().fold(
+            row![text("Highlight with tree-sitter")].spacing(20),
+            |tree_sitter, extension| {
+                let name = language::Language::from_extension(extension)
+                    .map_or(extension, |language| language.name);
+
+                tree_sitter.push(checkbox(
+                    name,
+                    self.config.tree_sitter.iter().any(|picked| picked == extension),
+                    move |is_enabled| Message::TreeSitterToggled(extension, is_enabled),
+                ))
+            },
+        );
+
+        let extension = self.extension();
+        let language_theme = checkbox(
+            format!(
+                "Give {} files a theme of their own",
+                language::Language::from_extension(extension)
+                    .map_or(extension, |language| language.name)
+            ),
+            self.config.themes.contains_key(extension),
+            Message::LanguageThemeToggled,
+        );
+
+        let line_height = row![
+            text("Line height").width(Length::Fixed(150.0)),
+            slider(
+                config::LINE_HEIGHTS,
+                self.config.line_height,
+                Message::LineHeightChanged
+            )
+            .step(0.05)
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(format!("{:.2}", self.config.line_height)).size(14),
+        ]
+        .spacing(10);
+
+        let scrolling = row![
+            checkbox(
+                "Smooth scrolling",
+                self.config.smooth_scrolling,
+                Message::SmoothScrollingToggled
+            )
+            .width(Length::Fixed(150.0)),
+            slider(
+                config::SCROLL_SPEEDS,
+                self.config.scroll_speed,
+                Message::ScrollSpeedChanged
+            )
+            .step(0.05)
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(format!("Speed {:.2}", self.config.scroll_speed)).size(14),
+        ]
+        .spacing(10);
+
+        let wheel = row![
+            text("Lines per wheel notch").width(Length::Fixed(150.0)),
+            slider(
+                config::WHEEL_LINES,
+                self.config.wheel_lines,
+                Message::WheelLinesChanged
+            )
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(self.config.wheel_lines.to_string()).size(14),
+        ]
+        .spacing(10);
+
+        let scroll_off = row![
+            text("Lines around the cursor").width(Length::Fixed(150.0)),
+            slider(
+                config::SCROLL_OFFS,
+                self.config.scroll_off,
+                Message::ScrollOffChanged
+            )
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(self.config.scroll_off.to_string()).size(14),
+        ]
+        .spacing(10);
+
+        let font = row![
+            text("Font").width(Length::Fixed(150.0)),
+            pick_list(
+                self.fonts.as_slice(),
+                self.config.font.clone(),
+                Message::FontSelected
+            )
+            .placeholder("Monospace"),
+            button("Default").on_press(Message::DefaultFontSelected),
+        ]
+        .spacing(10);
+
+        let wrap = row![
+            checkbox(
+                "Wrap at column",
+                self.config.wrap_column.is_some(),
+                Message::WrapColumnToggled
+            )
+            .width(Length::Fixed(150.0)),
+            text_input(&DEFAULT_WRAP_COLUMN.to_string(), &self.wrap_column)
+                .on_input(Message::WrapColumnChanged)
+                .width(Length::Fixed(80.0)),
+        ]
+        .spacing(10);
+
+        let max_line_length = row![
+            checkbox(
+                "Flag lines longer than",
+                self.config.max_line_length.is_some(),
+                Message::MaxLineLengthToggled
+            )
+            .width(Length::Fixed(150.0)),
+            text_input(&DEFAULT_MAX_LINE_LENGTH.to_string(), &self.max_line_length)
+                .on_input(Message::MaxLineLengthChanged)
+                .width(Length::Fixed(80.0)),
+        ]
+        .spacing(10);
+
+        let normalize = checkbox(
+            "Turn smart quotes, dashes and non-breaking spaces into ASCII on paste",
+            self.config.normalize_on_paste,
+            Message::NormalizeOnPasteToggled,
+        );
+
+        let on_save = save::Hook::ALL.into_iter().fold(
+            row![text("On save")].spacing(20),
+            |on_save, hook| {
+                on_save.push(checkbox(
+                    hook.label(),
+                    self.config.on_save.contains(&hook),
+                    move |is_enabled| Message::SaveHookToggled(hook, is_enabled),
+                ))
+            },
+        );
+
+        let blank_lines = row![
+            text("Blank lines in a row").width(Length::Fixed(150.0)),
+            slider(
+                config::MAX_BLANK_LINES,
+                self.config.max_blank_lines,
+                Message::MaxBlankLinesChanged
+            )
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(format!("{} at most when collapsing", self.config.max_blank_lines)).size(14),
+        ]
+        .spacing(10);
+
+        // What the open file takes, as read and as text
+        let in_use = self.bytes.as_ref().map_or(0, |bytes| bytes.len()) + self.text().len();
+
+        let max_buffer = row![
+            text("Largest file opened whole").width(Length::Fixed(150.0)),
+            slider(
+                config::MAX_BUFFER_MIB,
+                self.config.max_buffer_mib,
+                Message::MaxBufferChanged
+            )
+            .step(16u16)
+            .on_release(Message::SaveConfig)
+            .width(Length::Fixed(200.0)),
+            text(format!(
+                "{} MiB, bigger ones are shown a page at a time; this file takes {}",
+                self.config.max_buffer_mib,
+                file_size(in_use as u64)
+            ))
+            .size(14),
+        ]
+        .spacing(10);
+
+        let backup = checkbox(
+            format!(
+                "Keep the previous version as a {} file when saving",
+                self.config.backup.suffix
+            ),
+            self.config.keep_backup,
+            Message::KeepBackupToggled,
+        );
+
+        let overwrite = checkbox(
+            "Ask before saving over another file",
+            self.config.ask_before_overwrite,
+            Message::AskBeforeOverwriteToggled,
+        );
+
+        let threshold_input = |threshold: Option<usize>, on_input: fn(String) -> Message| {
+            text_input("Never", &threshold.map_or(String::new(), |lines| lines.to_string()))
+                .on_input(on_input)
+                .width(Length::Fixed(80.0))
+        };
+
+        let confirm = row![
+            text("Ask before replacing in files on"),
+            threshold_input(
+                self.config.confirm.replaced_lines,
+                Message::ConfirmReplacedLinesChanged
+            ),
+            text("lines, or deleting a selection of"),
+            threshold_input(
+                self.config.confirm.deleted_lines,
+                Message::ConfirmDeletedLinesChanged
+            ),
+            text("lines or more"),
+        ]
+        .spacing(10);
+
+        let mouse = row![
+            checkbox(
+                "Middle click pastes the last selection",
+                self.config.middle_click_paste,
+                Message::MiddleClickPasteToggled
+            ),
+            checkbox(
+                "Four clicks select the paragraph",
+                self.config.quadruple_click_paragraph,
+                Message::QuadrupleClickToggled
+            ),
+        ]
+        .spacing(20);
+
+        let directories = checkbox(
+            "Create missing folders when saving",
+            self.config.create_directories,
+            Message::CreateDirectoriesToggled,
+        );
+
+        let scratch = checkbox(
+            "Keep the scratch buffer between runs",
+            self.config.keep_scratch,
+            Message::KeepScratchToggled,
+        );
+
+        let recover = checkbox(
+            "Copy unsaved new files aside to recover them",
+            self.config.recover_new_files,
+            Message::RecoverNewFilesToggled,
+        );
+
+        let reload = row![
+            text("When the file changes on disk").width(Length::Fixed(150.0)),
+            pick_list(
+                config::Reload::ALL,
+                Some(self.config.reload),
+                Message::ReloadPolicySelected
+            ),
+        ]
+        .spacing(10);
+
+        let pause = checkbox(
+            "Stop watching the file and scrolling while the window is in the background",
+            self.config.pause_when_unfocused,
+            Message::PauseWhenUnfocusedToggled,
+        );
+
+        let fixed_directory = self
+            .config
+            .fixed_directory
+            .as_ref()
+            .map(|directory| directory.display().to_string())
+            .unwrap_or_default();
+
+        let mut dialogs = row![
+            text("Open file dialogs in").width(Length::Fixed(150.0)),
+            pick_list(
+                config::DialogDirectory::ALL,
+                Some(self.config.dialog_directory),
+                Message::DialogDirectorySelected
+            ),
+        ]
+        .spacing(10);
+
+        if self.config.dialog_directory == config::DialogDirectory::Fixed {
+            dialogs = dialogs.push(
+                text_input("Folder", &fixed_directory).on_input(Message::FixedDirectoryChanged),
+            );
+        }
+
+        let keymaps = row![
+            checkbox("Vim mode", self.vim.is_some(), Message::VimToggled),
+            checkbox("Emacs mode", self.emacs.is_some(), Message::EmacsToggled),
+        ]
+        .spacing(20);
+
+        // What the project sets wins until it is changed here, which
+        // changes it for every file
+        let title = match &self.config.project {
+            Some(project) => text(format!(
+                "Settings, under those of {}",
+                project.root.join(config::PROJECT_FILE).display()
+            )),
+            None => text("Settings"),
+        };
+
+        container(
+            column![
+                title,
+                colors,
+                tree_sitter,
+                language_theme,
+                line_height,
+                scrolling,
+                wheel,
+                scroll_off,
+                font,
+                wrap,
+                max_line_length,
+                normalize,
+                on_save,
+                blank_lines,
+                max_buffer,
+                backup,
+                overwrite,
+                confirm,
+                directories,
+                scratch,
+                recover,
+                reload,
+                pause,
+                dialogs,
+                mouse,
+                keymaps
+            ]
+            .spacing(10),
+        )
+            .width(Length::Fill)
+            .padding(10)
+            .style(theme::Container::Box)
+            .into()
+    }
+
+    fn replace_panel<'a>(&self, state: &'a replace::State) -> Element<'a, Message> {
+        let inputs = row![
+            text_input("Find", &state.query.pattern)
+                .id(replace_pattern_input())
+                .on_input(Message::ReplacePatternChanged)
+                .on_submit(Message::PreviewReplace),
+            text_input("Replace", &state.query.replacement)
+                .on_input(Message::ReplacementChanged)
+                .on_submit(Message::PreviewReplace),
+            checkbox("Regex", state.query.is_regex, Message::ReplaceRegexToggled),
+            button("Preview").on_press(Message::PreviewReplace),
+        ]
+        .spacing(10);
+
+        let results = state
+            .results
+            .iter()
+            .enumerate()
+            .fold(Column::new().spacing(5), |results, (index, replacement)| {
+                let file = checkbox(
+                    format!(
+                        "{} ({})",
+                        replacement.path.display(),
+                        replacement.changes.len()
+                    ),
+                    replacement.is_included,
+                    move |is_included| Message::ReplaceFileToggled(index, is_included),
+                );
+
+                replacement
+                    .changes
+                    .iter()
+                    .fold(results.push(file), |results, change| {
+                        results.push(
+                            text(format!(
+                                "    {}: {}  →  {}",
+                                change.line + 1,
+                                change.before,
+                                change.after
+                            ))
+                            .size(14),
+                        )
+                    })
+            });
+
+        let apply = if state.is_confirming {
+            row![
+                text(format!(
+                    "Replace in {} files? This writes them to disk.",
+                    state.included().count()
+                )),
+                button("Confirm").on_press(Message::ConfirmReplace),
+                button("Cancel")
+                    .on_press(Message::CancelReplace)
+                    .style(theme::Button::Secondary),
+            ]
+        } else {
+            row![button("Replace All")
+                .on_press_maybe(state.included().next().map(|_| Message::ApplyReplace))]
+        }
+        .spacing(10);
+
+        column![
+            text(format!("Replace in {}", self.root().display())).size(14),
+            inputs,
+            scrollable(results).height(Length::Fixed(200.0)),
+            apply,
+        ]
+        .spacing(10)
+        .padding([0, 0, 10, 0])
+        .into()
+    }
+}
+
+async fn pick_save_path(directory: Option<PathBuf>) -> Result<PathBuf, EditorError> {
+    dialog("Save As...", directory)
+        .save_file()
+        .await
+        .ok_or(EditorError::DialogClosed)
+        .map(|handle| handle.path().to_owned())
+}
+
+/// A file dialog, starting in `directory` if there is one.
+fn dialog(title: &str, directory: Option<PathBuf>) -> rfd::AsyncFileDialog {
+    let dialog = rfd::AsyncFileDialog::new().set_title(title);
+
+    match directory {
+        Some(directory) => dialog.set_directory(directory),
+        None => dialog,
+    }
+}
+
+async fn save_file(
+    path: PathBuf,
+    bytes: Vec<u8>,
+    backup: Option<config::Backup>,
+    create_directories: bool,
+) -> Result<PathBuf, EditorError> {
+    if create_directories {
+        save::create_parents(&path).await.map_err(|error| {
+            EditorError::CreateDirectory(path.parent().unwrap_or(&path).to_path_buf(), error)
+        })?;
+    }
+
+    if let Some(backup) = backup {
+        save::backup(&path, &backup).await.map_err(EditorError::IO)?;
+    }
+
+    save::write(&path, &bytes).await.map_err(EditorError::IO)?;
+    Ok(path)
+}
+
+/// The motion to the end of the document on the side of `motion`.
+fn document_end(motion: text_editor::Motion) -> text_editor::Motion {
+    if matches!(motion, text_editor::Motion::Home) {
+        text_editor::Motion::DocumentStart
+    } else {
+        text_editor::Motion::DocumentEnd
+    }
+}
+
+/// `lines` scrolled by the editor widget, rescaled to `per_notch` lines
+/// per notch of the wheel.
+fn wheel_lines(lines: i32, per_notch: u8) -> i32 {
+    let scaled = lines * i32::from(per_notch) / NOTCH_LINES;
+
+    if scaled == 0 {
+        lines.signum()
+    } else {
+        scaled
+    }
+}
+
+/// Estimates how many lines of text fit in a window of the given height.
+fn visible_lines(height: u32, line_height: f32) -> usize {
+    const CHROME: f32 = 100.0;
+
+    ((height as f32 - CHROME) / (TEXT_SIZE * line_height)).max(1.0) as usize
+}
+
+fn action<'a>(
+    content: Element<'a, Message>,
+    label: &str,
+    on_press: Option<Message>,
+) -> Element<'a, Message> {
+    let is_disabled = on_press.is_none();
+    tooltip(
+        button(container(content).width(30).center_x())
+            .on_press_maybe(on_press)
+            .padding([5, 10])
+            .style(if is_disabled {
+                theme::Button::Secondary
+            } else {
+                theme::Button::Primary
+            }),
+        label,
+        tooltip::Position::FollowCursor,
+    )
+    .style(theme::Container::Box)
+    .into()
+}
+
+enum Icon {
+    New,
+    Open,
+    Save,
+    Replace,
+    Settings,
+}
+
+fn icon<'a>(codepoint: char) -> Element<'a, Message> {
+    const ICON_FONT: Font = Font::with_name("iced-editor-icons");
+
+    text(codepoint).font(ICON_FONT).into()
+}
+
+fn get_icon<'a>(i: Icon) -> Element<'a, Message> {
+    match i {
         Icon::New => icon('\u{E800}'),
         Icon::Open => icon('\u{F114}'),
         Icon::Save => icon('\u{E801}'),
+        Icon::Replace => text("⇄").into(),
+        Icon::Settings => text("⚙").into(),
+    }
+}
+
+/// Which modifiers a binding needs held.
+#[derive(Debug, Clone, Copy)]
+enum Held {
+    /// These and maybe others.
+    AtLeast(keyboard::Modifiers),
+    /// These and no others.
+    Exactly(keyboard::Modifiers),
+}
+
+impl Held {
+    fn matches(self, modifiers: keyboard::Modifiers) -> bool {
+        match self {
+            Self::AtLeast(held) => modifiers.contains(held),
+            Self::Exactly(held) => modifiers == held,
+        }
+    }
+}
+
+/// A key binding: the group and description of what it does, its keys and
+/// its message.
+struct Binding {
+    group: &'static str,
+    label: &'static str,
+    key_code: keyboard::KeyCode,
+    held: Held,
+    message: Message,
+}
+
+impl Binding {
+    /// The keys to press, like `Ctrl+Shift+N`.
+    fn keys(&self) -> String {
+        let modifiers = match self.held {
+            Held::AtLeast(held) | Held::Exactly(held) => held,
+        };
+
+        let key = match self.key_code {
+            keyboard::KeyCode::Comma => ",".to_owned(),
+            keyboard::KeyCode::LBracket => "[".to_owned(),
+            keyboard::KeyCode::RBracket => "]".to_owned(),
+            key_code => format!("{key_code:?}"),
+        };
+
+        [
+            (keyboard::Modifiers::COMMAND, "Ctrl"),
+            (keyboard::Modifiers::ALT, "Alt"),
+            (keyboard::Modifiers::SHIFT, "Shift"),
+        ]
+        .into_iter()
+        .filter(|(modifier, _)| modifiers.contains(*modifier))
+        .map(|(_, name)| name)
+        .chain(std::iter::once(key.as_str()))
+        .collect::<Vec<_>>()
+        .join("+")
+    }
+}
+
+/// The key bindings, the first matching one winning.
+fn bindings() -> Vec<Binding> {
+    use keyboard::{KeyCode, Modifiers};
+
+    let command = Held::AtLeast(Modifiers::COMMAND);
+    let command_shift = Held::AtLeast(Modifiers::COMMAND | Modifiers::SHIFT);
+    let command_alt = Held::AtLeast(Modifiers::COMMAND | Modifiers::ALT);
+    let alt = Held::AtLeast(Modifiers::ALT);
+    let shift = Held::AtLeast(Modifiers::SHIFT);
+    let any = Held::AtLeast(Modifiers::empty());
+    let none = Held::Exactly(Modifiers::empty());
+
+    let binding = |group, label, key_code, held, message| Binding {
+        group,
+        label,
+        key_code,
+        held,
+        message,
+    };
+
+    vec![
+        binding("Edit", "Note on line", KeyCode::N, command_alt, Message::EditNote),
+        binding("File", "New window", KeyCode::N, command_shift, Message::NewWindow),
+        binding("File", "New", KeyCode::N, command, Message::New),
+        binding("File", "Scratch buffer", KeyCode::K, command_shift, Message::OpenScratch),
+        binding("Code", "Organize imports", KeyCode::O, command_alt, Message::OrganizeImports),
+        binding(
+            "Code",
+            "Generate doc comment",
+            KeyCode::D,
+            command_alt,
+            Message::GenerateDocComment,
+        ),
+        binding("File", "Open", KeyCode::O, command, Message::Open),
+        binding("Edit", "Surround selection", KeyCode::S, command_alt, Message::Surround),
+        binding(
+            "Edit",
+            "Change surrounding pair",
+            KeyCode::R,
+            command_alt,
+            Message::ChangeSurrounding,
+        ),
+        binding("File", "Save", KeyCode::S, command, Message::Save),
+        binding("View", "Previous region", KeyCode::F6, shift, Message::CycleFocus(true)),
+        binding("View", "Next region", KeyCode::F6, any, Message::CycleFocus(false)),
+        binding("File", "Previous file in folder", KeyCode::F8, shift, Message::PrevFileInDir),
+        binding("File", "Next file in folder", KeyCode::F8, any, Message::NextFileInDir),
+        binding(
+            "View",
+            "Describe character",
+            KeyCode::I,
+            command_shift,
+            Message::DescribeCharacter,
+        ),
+        binding("Code", "Show hover", KeyCode::I, command, Message::ShowHover),
+        binding("Code", "Go to definition", KeyCode::F12, any, Message::GoToDefinition),
+        binding("Edit", "Go to last edit", KeyCode::Q, command_alt, Message::GoToLastEdit),
+        binding("Code", "Format JSON", KeyCode::F, command_alt, Message::FormatJson),
+        binding("Code", "Minify JSON", KeyCode::M, command_alt, Message::MinifyJson),
+        binding("Find", "Find", KeyCode::F, command, Message::ToggleFind),
+        binding("Find", "Go to", KeyCode::G, command, Message::OpenGoTo),
+        binding("Find", "Older query", KeyCode::Up, none, Message::OlderQuery),
+        binding("Find", "Newer query", KeyCode::Down, none, Message::NewerQuery),
+        binding("Find", "Previous match", KeyCode::F3, shift, Message::FindPrevious),
+        binding("Find", "Next match", KeyCode::F3, any, Message::FindNext),
+        binding("Find", "Previous merge conflict", KeyCode::F7, shift, Message::PreviousConflict),
+        binding("Find", "Next merge conflict", KeyCode::F7, any, Message::NextConflict),
+        binding(
+            "Find",
+            "Replace in files",
+            KeyCode::H,
+            command_shift,
+            Message::ToggleReplaceInFiles,
+        ),
+        binding("Edit", "Sort lines", KeyCode::F9, any, Message::ToggleSort),
+        binding("View", "Settings", KeyCode::Comma, command, Message::ToggleSettings),
+        binding("Edit", "Select line", KeyCode::L, command, Message::SelectLine),
+        binding("Edit", "Transpose characters", KeyCode::T, command, Message::TransposeCharacters),
+        binding("Edit", "Transpose words", KeyCode::T, alt, Message::TransposeWords),
+        binding("Edit", "Indent or complete", KeyCode::Tab, none, Message::Tab),
+        binding("Edit", "Overtype", KeyCode::Insert, none, Message::ToggleOverwrite),
+        binding("Edit", "Cycle pasted text", KeyCode::Y, alt, Message::CyclePaste),
+        binding("Edit", "Increment number", KeyCode::A, command_shift, Message::IncrementNumber),
+        binding("Edit", "Decrement number", KeyCode::X, command_shift, Message::DecrementNumber),
+        binding(
+            "Edit",
+            "Insert Unicode character",
+            KeyCode::U,
+            command_shift,
+            Message::InsertUnicode,
+        ),
+        binding("View", "Fold all", KeyCode::LBracket, command_alt, Message::FoldAll),
+        binding("View", "Unfold all", KeyCode::RBracket, command_alt, Message::UnfoldAll),
+        binding("View", "Fold", KeyCode::LBracket, command_shift, Message::ToggleFold),
+        binding("Code", "Run", KeyCode::F5, any, Message::Run),
+        binding("View", "Output", KeyCode::J, command, Message::ToggleOutput),
+        binding("Help", "Keyboard shortcuts", KeyCode::F1, any, Message::ToggleShortcuts),
+    ]
+}
+
+fn key_binding(key_code: keyboard::KeyCode, modifiers: keyboard::Modifiers) -> Option<Message> {
+    bindings()
+        .into_iter()
+        .find(|binding| binding.key_code == key_code && binding.held.matches(modifiers))
+        .map(|binding| binding.message)
+}
+
+/// Every key binding by group, with what the keymap in use changes.
+fn shortcuts_panel<'a>(is_vim: bool, is_emacs: bool) -> Element<'a, Message> {
+    let bindings = bindings();
+
+    let groups = ["File", "Edit", "Find", "Code", "View", "Help"].into_iter().map(|group| {
+        Element::from(
+            bindings
+                .iter()
+                .filter(|binding| binding.group == group)
+                .fold(column![text(group)].spacing(2), |list, binding| {
+                    list.push(text(format!("{}  {}", binding.keys(), binding.label)).size(14))
+                }),
+        )
+    });
+
+    let mut shortcuts = column![Row::with_children(groups.collect()).spacing(30)].spacing(10);
+
+    if is_vim {
+        shortcuts = shortcuts
+            .push(text("Vim mode: keys typed outside Insert mode are Vim commands").size(14));
+    }
+
+    if is_emacs {
+        shortcuts = shortcuts.push(
+            text("Emacs mode: Ctrl and Alt keys go to the Emacs keymap first").size(14),
+        );
+    }
+
+    shortcuts = shortcuts.push(button("Close").on_press(Message::ToggleShortcuts));
+
+    container(shortcuts)
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+}
+
+/// The templates to start a new file from.
+fn templates_panel(templates: &[template::Template]) -> Element<'_, Message> {
+    let list = templates.iter().enumerate().fold(
+        column![text("New from template")].spacing(5),
+        |list, (index, template)| {
+            list.push(
+                button(text(format!("{} (.{})", template.name, template.extension)).size(14))
+                    .on_press(Message::TemplatePicked(index))
+                    .padding([2, 5])
+                    .style(theme::Button::Text),
+            )
+        },
+    );
+
+    let list = if templates.is_empty() {
+        list.push(text("No templates in the templates directory of the config").size(14))
+    } else {
+        list
+    };
+
+    container(list.push(button("Close").on_press(Message::ToggleTemplates)))
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+}
+
+fn about_panel<'a>() -> Element<'a, Message> {
+    let mut about = column![
+        text(format!("Text Editor {}", env!("CARGO_PKG_VERSION"))),
+        text(format!("Built with iced {}", env!("ICED_VERSION"))).size(14),
+        text(format!("Built on {}", env!("BUILD_DATE"))).size(14),
+    ]
+    .spacing(5);
+
+    let repository = env!("CARGO_PKG_REPOSITORY");
+
+    if !repository.is_empty() {
+        about = about.push(text(repository).size(14));
+    }
+
+    about = about.push(
+        row![
+            button("Copy diagnostics").on_press(Message::CopyDiagnostics),
+            button("Close").on_press(Message::ToggleAbout),
+        ]
+        .spacing(10),
+    );
+
+    container(about)
+        .width(Length::Fill)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+}
+
+/// What to tell about the editor when filing an issue.
+fn diagnostics_report() -> String {
+    format!(
+        "text-editor {}\niced {}\nbuilt {}\n{} {}",
+        env!("CARGO_PKG_VERSION"),
+        env!("ICED_VERSION"),
+        env!("BUILD_DATE"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    )
+}
+
+fn menu_list<'a>(menu: Menu) -> Element<'a, Message> {
+    let items = menu
+        .items()
+        .into_iter()
+        .fold(Column::new(), |items, (label, shortcut, message)| {
+            items.push(
+                button(
+                    row![
+                        text(label).size(14),
+                        horizontal_space(Length::Fill),
+                        text(shortcut).size(14),
+                    ]
+                    .spacing(20),
+                )
+                .on_press(Message::MenuPicked(Box::new(message)))
+                .width(Length::Fill)
+                .padding([2, 5])
+                .style(theme::Button::Text),
+            )
+        });
+
+    container(items)
+        .width(Length::Fixed(300.0))
+        .style(theme::Container::Box)
+        .into()
+}
+
+fn find_input() -> text_input::Id {
+    text_input::Id::new("find")
+}
+
+fn sort_bar(state: &sort::State) -> Element<'_, Message> {
+    let mut bar = row![
+        text("Sort lines"),
+        pick_list(sort::Order::ALL, Some(state.order), Message::SortOrderSelected),
+        text_input("Delimiter", &state.delimiter)
+            .on_input(Message::SortDelimiterChanged)
+            .on_submit(Message::SortLines)
+            .width(Length::Fixed(100.0)),
+    ]
+    .spacing(10);
+
+    if !state.delimiter.is_empty() {
+        bar = bar.push(
+            text_input("Field", &state.field)
+                .on_input(Message::SortFieldChanged)
+                .on_submit(Message::SortLines)
+                .width(Length::Fixed(60.0)),
+        );
+    }
+
+    bar.push(button("Sort").on_press(Message::SortLines)).into()
+}
+
+fn encoding_bar<'a>(
+    current: encoding::Encoding,
+    has_bom: bool,
+    line_ending: encoding::LineEnding,
+) -> Element<'a, Message> {
+    let mut bar = row![
+        text("Reopen with"),
+        pick_list(&encoding::ALL[..], None, Message::ReopenWithEncoding)
+            .placeholder(current.to_string()),
+        text("Save with"),
+        pick_list(
+            &encoding::ALL[..],
+            Some(current),
+            Message::EncodingSelected
+        ),
+        text("Line endings"),
+        pick_list(
+            encoding::LineEnding::ALL,
+            Some(line_ending),
+            Message::LineEndingSelected
+        ),
+    ]
+    .spacing(10);
+
+    if current.has_bom() {
+        bar = bar.push(checkbox("Byte order mark", has_bom, Message::BomToggled));
+    }
+
+    bar.into()
+}
+
+fn conflict_bar<'a>(
+    conflicts: &[conflict::Conflict],
+    current: Option<conflict::Conflict>,
+    can_resolve: bool,
+) -> Element<'a, Message> {
+    let position = current.and_then(|current| {
+        conflicts
+            .iter()
+            .position(|conflict| *conflict == current)
+    });
+
+    let mut bar = match position {
+        Some(index) => row![text(format!("Conflict {} of {}", index + 1, conflicts.len()))],
+        None => row![text(format!("{} merge conflicts", conflicts.len()))],
+    }
+    .spacing(10);
+
+    if current.is_some() && can_resolve {
+        bar = bar
+            .push(
+                button("Accept current")
+                    .on_press(Message::ResolveConflict(conflict::Choice::Current)),
+            )
+            .push(
+                button("Accept incoming")
+                    .on_press(Message::ResolveConflict(conflict::Choice::Incoming)),
+            )
+            .push(button("Accept both").on_press(Message::ResolveConflict(conflict::Choice::Both)));
     }
+
+    bar.push(button("Previous").on_press(Message::PreviousConflict))
+        .push(button("Next").on_press(Message::NextConflict))
+        .into()
+}
+
+fn replace_pattern_input() -> text_input::Id {
+    text_input::Id::new("replace-pattern")
+}
+
+fn go_to_input() -> text_input::Id {
+    text_input::Id::new("go-to")
+}
+
+fn code_point_input() -> text_input::Id {
+    text_input::Id::new("code-point")
+}
+
+fn note_input() -> text_input::Id {
+    text_input::Id::new("note")
+}
+
+fn tag_name_input() -> text_input::Id {
+    text_input::Id::new("tag-name")
+}
+
+fn surround_tag_input() -> text_input::Id {
+    text_input::Id::new("surround-tag")
+}
+
+fn vim_command_input() -> text_input::Id {
+    text_input::Id::new("vim-command")
 }
 
 fn default_file() -> PathBuf {
     PathBuf::from(format!("{}/src/main.rs", env!("CARGO_MANIFEST_DIR")))
 }
 
-async fn pick_file() -> Result<(PathBuf, Arc<String>), EditorError> {
-    let handle = rfd::AsyncFileDialog::new()
-        .set_title("Choose a text file...")
+async fn pick_file(
+    directory: Option<PathBuf>,
+    filters: Vec<config::FileFilter>,
+    max_size: u64,
+) -> Result<Opened, EditorError> {
+    // The first filter is the one picked at first
+    let dialog = filters.iter().fold(
+        dialog("Choose a text file...", directory).add_filter("All files", &["*"]),
+        |dialog, filter| dialog.add_filter(&filter.name, &filter.extensions),
+    );
+
+    let handle = dialog
         .pick_file()
         .await
         .ok_or(EditorError::DialogClosed)?;
 
-    load_file(handle.path().to_owned()).await
+    load_file(handle.path().to_owned(), max_size).await
+}
+
+async fn load_file(path: PathBuf, max_size: u64) -> Result<Opened, EditorError> {
+    load_file_as(path, None, max_size).await
+}
+
+/// Reads a file and decodes it, in `encoding` if given, or only its first
+/// page if it is bigger than `max_size` bytes.
+async fn load_file_as(
+    path: PathBuf,
+    encoding: Option<encoding::Encoding>,
+    max_size: u64,
+) -> Result<Opened, EditorError> {
+    if tokio::fs::metadata(&path)
+        .await
+        .is_ok_and(|metadata| metadata.len() > max_size)
+    {
+        return load_page(path, 0, false, encoding).await;
+    }
+
+    let bytes = tokio::fs::read(&path).await.map_err(|error| {
+        if is_sharing_violation(&error) {
+            EditorError::Locked(path.clone())
+        } else {
+            EditorError::IO(error.kind())
+        }
+    })?;
+
+    decode(path, Arc::new(bytes), encoding)
 }
 
-async fn load_file(path: PathBuf) -> Result<(PathBuf, Arc<String>), EditorError> {
-    let content = tokio::fs::read_to_string(&path)
+/// Reads the page of a file too big to open whole that starts at byte
+/// `start`, or at the start of the next line if `to_line` and a line goes
+/// on there.
+async fn load_page(
+    path: PathBuf,
+    start: u64,
+    to_line: bool,
+    encoding: Option<encoding::Encoding>,
+) -> Result<Opened, EditorError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let error = |error: io::Error| EditorError::IO(error.kind());
+
+    let mut file = tokio::fs::File::open(&path).await.map_err(error)?;
+    let total = file.metadata().await.map_err(error)?.len();
+    let mut bytes = Vec::new();
+
+    // The byte before the page tells whether it starts a line
+    let from = start.saturating_sub(1);
+
+    file.seek(io::SeekFrom::Start(from)).await.map_err(error)?;
+    file.take(PAGE_SIZE + start - from)
+        .read_to_end(&mut bytes)
         .await
-        .map(Arc::new)
-        .map_err(|error| error.kind())
-        .map_err(EditorError::IO)?;
+        .map_err(error)?;
+
+    let mut end = from + bytes.len() as u64;
+
+    // Ending the page after its last whole line, unless it is all one line
+    if end < total {
+        let last = bytes.iter().rposition(|byte| *byte == b'\n');
+
+        if let Some(last) = last.filter(|&last| last as u64 >= start - from) {
+            bytes.truncate(last + 1);
+            end = from + bytes.len() as u64;
+        }
+    }
+
+    // And starting it with its first whole line, going on a line only
+    // when it is longer than the page
+    let skipped = match bytes.iter().position(|byte| *byte == b'\n') {
+        _ if start == 0 => 0,
+        Some(first) if to_line && first + 1 < bytes.len() => first + 1,
+        _ => (start - from) as usize,
+    };
+
+    bytes.drain(..skipped);
+
+    let start = from + skipped as u64;
 
-    Ok((path, content))
+    let mut opened = decode(path, Arc::new(bytes), encoding)?;
+    opened.page = Some(Page { start, end, total });
+
+    Ok(opened)
+}
+
+/// Decodes the `bytes` of a file in `encoding`, or else the one its byte
+/// order mark announces or UTF-8.
+fn decode(
+    path: PathBuf,
+    bytes: Arc<Vec<u8>>,
+    encoding: Option<encoding::Encoding>,
+) -> Result<Opened, EditorError> {
+    let marked = encoding::Encoding::from_bom(&bytes);
+
+    // UTF-16 text is full of NUL bytes
+    let is_binary = encoding.or(marked).is_none() && hex::is_binary(&bytes);
+    let encoding = encoding.or(marked).unwrap_or_default();
+
+    // What is not valid is still worth seeing, to pick the right encoding
+    let (content, is_lossy) = if is_binary {
+        (hex::dump(&bytes), false)
+    } else {
+        match encoding.decode(&bytes) {
+            Ok(content) => (content, false),
+            Err(_) => (encoding.decode_lossy(&bytes), true),
+        }
+    };
+
+    let has_bom = !is_binary && encoding.starts(&bytes);
+    let line_ending = encoding::LineEnding::detect(&content);
+    let has_long_lines = content.lines().any(|line| line.len() > LONG_LINE);
+
+    Ok(Opened {
+        path,
+        content: Arc::new(encoding::normalize(&content)),
+        is_binary,
+        is_lossy,
+        encoding,
+        has_bom,
+        line_ending,
+        has_long_lines,
+        bytes,
+        page: None,
+    })
+}
+
+/// How many lines `text` spans, counting the line it ends on.
+fn line_span(text: &str) -> usize {
+    text.matches('\n').count() + 1
+}
+
+/// The threshold typed in a confirmation setting, empty for never asking,
+/// unless it is no number.
+fn threshold(typed: &str) -> Option<Option<usize>> {
+    if typed.trim().is_empty() {
+        return Some(None);
+    }
+
+    typed.trim().parse().ok().map(Some)
+}
+
+/// The size and modification time of the file at `path`.
+fn on_disk(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// `bytes` in the largest unit that leaves at least one, like `12.3 KiB`.
+fn file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Whether opening a file failed because another process holds it
+/// exclusively, which only happens on Windows.
+fn is_sharing_violation(error: &io::Error) -> bool {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    cfg!(windows)
+        && matches!(
+            error.raw_os_error(),
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+        )
 }
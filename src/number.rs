@@ -0,0 +1,134 @@
+//! Finding the numbers of a line and counting them up or down.
+
+use std::ops::Range;
+
+/// A decimal or `0x` hexadecimal integer of a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    /// The byte range of the number, sign and prefix included.
+    pub range: Range<usize>,
+    value: i128,
+    /// How many digits the number is padded to, when it has leading zeros.
+    width: usize,
+    hex: Option<Hex>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hex {
+    /// The `x` of the prefix, which may be uppercase.
+    marker: char,
+    is_uppercase: bool,
+}
+
+impl Number {
+    /// The text of the number once `delta` is added to it.
+    pub fn add(&self, delta: i128) -> String {
+        let value = self.value.saturating_add(delta);
+
+        match self.hex {
+            Some(hex) => {
+                // Hexadecimal numbers have no sign, like in Vim
+                let value = value.max(0);
+                let digits = if hex.is_uppercase {
+                    format!("{value:0width$X}", width = self.width)
+                } else {
+                    format!("{value:0width$x}", width = self.width)
+                };
+
+                format!("0{}{digits}", hex.marker)
+            }
+            None => {
+                let sign = if value < 0 { "-" } else { "" };
+
+                format!("{sign}{:0width$}", value.unsigned_abs(), width = self.width)
+            }
+        }
+    }
+}
+
+/// The first number of `line` that ends after the byte `column`.
+pub fn find(line: &str, column: usize) -> Option<Number> {
+    let bytes = line.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_digit() {
+            index += 1;
+            continue;
+        }
+
+        let number = parse(line, index);
+        let end = number.range.end;
+
+        if end > column {
+            return Some(number);
+        }
+
+        index = end;
+    }
+
+    None
+}
+
+/// Parses the number whose first digit is at the byte `start` of `line`.
+fn parse(line: &str, start: usize) -> Number {
+    let bytes = line.as_bytes();
+    let is_word = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+
+    let is_hex = bytes[start] == b'0'
+        && matches!(bytes.get(start + 1), Some(b'x' | b'X'))
+        && bytes.get(start + 2).is_some_and(u8::is_ascii_hexdigit);
+
+    if is_hex {
+        let digits_start = start + 2;
+        let end = digits_start
+            + bytes[digits_start..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_hexdigit())
+                .count();
+
+        let digits = &line[digits_start..end];
+        let is_uppercase = digits.bytes().any(|byte| byte.is_ascii_uppercase());
+
+        return Number {
+            range: start..end,
+            value: i128::from_str_radix(digits, 16).unwrap_or(i128::MAX),
+            width: padding(digits),
+            hex: Some(Hex {
+                marker: char::from(bytes[start + 1]),
+                is_uppercase,
+            }),
+        };
+    }
+
+    let end = start
+        + bytes[start..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+
+    let digits = &line[start..end];
+    let value: i128 = digits.parse().unwrap_or(i128::MAX);
+
+    // A minus right before the digits is a sign, unless it is the hyphen of
+    // a word like `utf-8`
+    let is_negative = start > 0
+        && bytes[start - 1] == b'-'
+        && (start < 2 || !is_word(bytes[start - 2]));
+
+    Number {
+        range: if is_negative { start - 1 } else { start }..end,
+        value: if is_negative { -value } else { value },
+        width: padding(digits),
+        hex: None,
+    }
+}
+
+/// The width to keep when the digits are zero-padded.
+fn padding(digits: &str) -> usize {
+    if digits.len() > 1 && digits.starts_with('0') {
+        digits.len()
+    } else {
+        0
+    }
+}
@@ -0,0 +1,60 @@
+//! A lightweight outline of the scopes in a document.
+
+use crate::fold::Region;
+
+/// The longest label shown for a scope.
+const MAX_LABEL_LENGTH: usize = 40;
+
+/// A named scope and the line where it is declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub line: usize,
+    pub label: String,
+}
+
+/// The named scopes enclosing `line`, outermost first.
+///
+/// `header` returns the text of a line of the document.
+pub fn scopes<T: AsRef<str>>(
+    regions: &[Region],
+    line: usize,
+    header: impl Fn(usize) -> Option<T>,
+) -> Vec<Scope> {
+    regions
+        .iter()
+        .filter(|region| region.contains(line))
+        .filter_map(|region| {
+            let label = label(header(region.start)?.as_ref())?;
+
+            Some(Scope {
+                line: region.start,
+                label,
+            })
+        })
+        .collect()
+}
+
+/// Summarizes a scope header, e.g. `pub fn update(&mut self) {` as `fn update`.
+fn label(header: &str) -> Option<String> {
+    let header = header.trim_start();
+    let header = ["pub(crate) ", "pub(super) ", "pub "]
+        .iter()
+        .find_map(|visibility| header.strip_prefix(visibility))
+        .unwrap_or(header);
+
+    let end = header
+        .find(['{', '(', '[', '=', ':'])
+        .unwrap_or(header.len());
+
+    let label = header[..end].split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if label.is_empty() {
+        return None;
+    }
+
+    Some(if label.chars().count() > MAX_LABEL_LENGTH {
+        label.chars().take(MAX_LABEL_LENGTH).chain(Some('…')).collect()
+    } else {
+        label
+    })
+}
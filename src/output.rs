@@ -0,0 +1,76 @@
+//! The output panel, where running files, checking the project, formatting
+//! and searching across files report what they found.
+
+use std::path::{Path, PathBuf};
+
+/// The most lines kept, dropping the oldest beyond it.
+pub const MAX_LINES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub text: String,
+    /// The place in a file the line refers to.
+    pub location: Option<Location>,
+}
+
+/// A place in a file, counting lines and characters from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            location: None,
+        }
+    }
+
+    /// A line of a tool that ran in `directory`, referring to the first
+    /// `path:line` or `path:line:column` in it that names an existing file.
+    pub fn of_tool(text: impl Into<String>, directory: &Path) -> Self {
+        let text = text.into();
+        let location = locate(&text, directory);
+
+        Self { text, location }
+    }
+
+    pub fn at(text: impl Into<String>, location: Location) -> Self {
+        Self {
+            text: text.into(),
+            location: Some(location),
+        }
+    }
+}
+
+/// The first `path:line` or `path:line:column` in `text`, with relative
+/// paths in `directory`, if the file exists.
+fn locate(text: &str, directory: &Path) -> Option<Location> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '(' | ')' | ','))
+        .find_map(|word| {
+            let mut parts = word.split(':');
+            let mut path = parts.next()?.to_owned();
+
+            // Windows paths start with a drive, like `C:\`
+            if path.len() == 1 && word[1..].starts_with(":\\") {
+                path = format!("{path}:{}", parts.next()?);
+            }
+
+            let line: usize = parts.next()?.parse().ok()?;
+            let column: usize = parts
+                .next()
+                .and_then(|column| column.parse().ok())
+                .unwrap_or(1);
+
+            let path = directory.join(path);
+
+            path.is_file().then(|| Location {
+                path,
+                line: line.saturating_sub(1),
+                character: column.saturating_sub(1),
+            })
+        })
+}
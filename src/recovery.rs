@@ -0,0 +1,92 @@
+//! Copies of new files that were never saved, in `recovery` in the
+//! platform data directory, so that they survive the editor going away.
+//!
+//! Having no path, each new file is known by an id made when it is created.
+
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+/// How long copies are kept.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The copy of a new file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buffer {
+    pub id: String,
+    /// When it was last copied.
+    pub time: SystemTime,
+}
+
+impl Buffer {
+    pub fn read(&self) -> io::Result<String> {
+        std::fs::read_to_string(path(&self.id).ok_or(io::ErrorKind::NotFound)?)
+    }
+}
+
+/// An id for a new file that no other one had.
+pub fn id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!("{nanos}-{}", std::process::id())
+}
+
+/// Keeps `text` as the copy of the new file known by `id`.
+pub fn save(id: &str, text: &str) -> io::Result<()> {
+    let path = path(id).ok_or(io::ErrorKind::NotFound)?;
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    std::fs::write(path, text)
+}
+
+/// Forgets the copy of the new file known by `id`, as once it is saved.
+pub fn remove(id: &str) {
+    if let Some(path) = path(id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// The copies left by earlier runs, the latest first, forgetting the ones
+/// too old to keep.
+pub fn list() -> Vec<Buffer> {
+    let Some(entries) = directory().and_then(|directory| std::fs::read_dir(directory).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut buffers: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let time = entry.metadata().ok()?.modified().ok()?;
+
+            if time.elapsed().unwrap_or_default() > MAX_AGE {
+                let _ = std::fs::remove_file(entry.path());
+                return None;
+            }
+
+            Some(Buffer {
+                id: entry.file_name().to_str()?.to_owned(),
+                time,
+            })
+        })
+        .collect();
+
+    buffers.sort_by(|a, b| b.time.cmp(&a.time));
+    buffers
+}
+
+fn path(id: &str) -> Option<PathBuf> {
+    directory().map(|directory| directory.join(id))
+}
+
+fn directory() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join("text-editor").join("recovery"))
+}
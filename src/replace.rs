@@ -0,0 +1,155 @@
+//! Find and replace across every file below a directory.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use regex::{NoExpand, Regex};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::EditorError;
+
+/// Files bigger than this are assumed not to be hand-edited text.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub pattern: String,
+    pub replacement: String,
+    pub is_regex: bool,
+}
+
+impl Query {
+    fn regex(&self) -> Result<Regex, EditorError> {
+        let source = if self.is_regex {
+            Cow::Borrowed(self.pattern.as_str())
+        } else {
+            Cow::Owned(regex::escape(&self.pattern))
+        };
+
+        Regex::new(&source).map_err(|error| EditorError::InvalidPattern(error.to_string()))
+    }
+
+    fn substitute<'a>(&self, regex: &Regex, text: &'a str) -> Cow<'a, str> {
+        if self.is_regex {
+            regex.replace_all(text, self.replacement.as_str())
+        } else {
+            regex.replace_all(text, NoExpand(&self.replacement))
+        }
+    }
+}
+
+/// The pending replacements of a single file.
+#[derive(Debug, Clone)]
+pub struct FileReplacement {
+    pub path: PathBuf,
+    pub changes: Vec<Change>,
+    pub text: Arc<String>,
+    pub is_included: bool,
+}
+
+/// A line as it reads before and after the replacement.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The state of the "Replace in Files" panel.
+#[derive(Debug, Default)]
+pub struct State {
+    pub query: Query,
+    /// The query the results were previewed with.
+    pub previewed: Query,
+    pub results: Vec<FileReplacement>,
+    pub is_confirming: bool,
+}
+
+impl State {
+    pub fn included(&self) -> impl Iterator<Item = &FileReplacement> {
+        self.results
+            .iter()
+            .filter(|replacement| replacement.is_included)
+    }
+}
+
+/// Computes the replacements of `query` for every text file below `root`.
+///
+/// The open buffer is searched instead of its file on disk, so unsaved edits
+/// are part of the preview, and it is left out when it has no text of its
+/// own to replace in, like a hex dump.
+pub async fn search(
+    root: PathBuf,
+    query: Query,
+    open: Option<(PathBuf, Option<String>)>,
+) -> Result<Vec<FileReplacement>, EditorError> {
+    let regex = query.regex()?;
+    let mut results = Vec::new();
+
+    for path in files(&root) {
+        let text = match &open {
+            Some((open_path, Some(text))) if *open_path == path => text.clone(),
+            Some((open_path, None)) if *open_path == path => continue,
+            _ => match tokio::fs::read_to_string(&path).await {
+                Ok(text) => text,
+                Err(_) => continue,
+            },
+        };
+
+        if !regex.is_match(&text) {
+            continue;
+        }
+
+        let changes = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line))
+            .map(|(line, before)| Change {
+                line,
+                before: before.trim().to_owned(),
+                after: query.substitute(&regex, before).trim().to_owned(),
+            })
+            .collect();
+
+        results.push(FileReplacement {
+            text: Arc::new(query.substitute(&regex, &text).into_owned()),
+            path,
+            changes,
+            is_included: true,
+        });
+    }
+
+    Ok(results)
+}
+
+/// `text` with every match of `query` replaced.
+pub fn replace(query: &Query, text: &str) -> Result<String, EditorError> {
+    let regex = query.regex()?;
+
+    Ok(query.substitute(&regex, text).into_owned())
+}
+
+/// The text files below `root`, leaving out hidden ones and build output.
+pub fn files(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_ignored(entry))
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .metadata()
+                    .is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE)
+        })
+        .map(DirEntry::into_path)
+}
+
+fn is_ignored(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+
+    name.starts_with('.') || (entry.file_type().is_dir() && name == "target")
+}
@@ -0,0 +1,178 @@
+//! Running the open file with the command of its language, streaming what
+//! it prints.
+
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use iced::futures::{channel::mpsc, stream, SinkExt, Stream, StreamExt};
+use iced::{subscription, Subscription};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// What stands for the path of the file in a command.
+pub const FILE: &str = "{file}";
+
+/// A program to launch and its arguments, in the directory of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<OsString>,
+    pub directory: PathBuf,
+}
+
+impl Invocation {
+    /// The words of `template`, with [`FILE`] replaced by `file`.
+    ///
+    /// Words are split at whitespace, so the path stays a single argument
+    /// even if it has spaces.
+    pub fn new(template: &str, file: &Path) -> Option<Self> {
+        let mut words = template.split_whitespace().map(|word| {
+            match word.split_once(FILE) {
+                Some((before, after)) => {
+                    let mut argument = OsString::from(before);
+                    argument.push(file);
+                    argument.push(after);
+                    argument
+                }
+                None => OsString::from(word),
+            }
+        });
+
+        let program = words.next()?.to_string_lossy().into_owned();
+
+        Some(Self {
+            program,
+            args: words.collect(),
+            directory: file.parent().map(Path::to_path_buf).unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.program)?;
+
+        for arg in &self.args {
+            write!(f, " {}", arg.to_string_lossy())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The process exited, with its exit code unless a signal ended it.
+    Exited(Option<i32>),
+    /// The process could not be launched or waited on.
+    Failed(io::ErrorKind),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(Some(code)) => write!(f, "Exited with {code}"),
+            Self::Exited(None) => f.write_str("Killed"),
+            Self::Failed(error) => write!(f, "Could not run: {error}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A line printed to the standard output or error, without its line
+    /// ending.
+    Line(String),
+    Finished(Outcome),
+}
+
+/// What a run of the generation printed or how it ended.
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub generation: u64,
+    pub event: Event,
+}
+
+/// The latest run, which prints into the output panel.
+#[derive(Debug, Clone)]
+pub struct State {
+    pub generation: u64,
+    pub invocation: Invocation,
+    pub outcome: Option<Outcome>,
+}
+
+/// Runs `invocation`, reporting every line it prints and then how it
+/// ended.
+///
+/// Dropping the subscription, e.g. because a newer `generation` replaced
+/// it, kills the running process.
+pub fn run(generation: u64, invocation: Invocation) -> Subscription<Output> {
+    subscription::channel(("run", generation), 100, move |mut output| async move {
+        let outcome = match execute(&invocation, generation, &mut output).await {
+            Ok(code) => Outcome::Exited(code),
+            Err(error) => Outcome::Failed(error.kind()),
+        };
+
+        let _ = output
+            .send(Output {
+                generation,
+                event: Event::Finished(outcome),
+            })
+            .await;
+
+        iced::futures::future::pending().await
+    })
+}
+
+async fn execute(
+    invocation: &Invocation,
+    generation: u64,
+    output: &mut mpsc::Sender<Output>,
+) -> io::Result<Option<i32>> {
+    let mut process = tokio::process::Command::new(&invocation.program)
+        .args(&invocation.args)
+        .current_dir(&invocation.directory)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdout = process.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    let stderr = process.stderr.take().ok_or(io::ErrorKind::BrokenPipe)?;
+
+    let mut lines = stream::select(lines(stdout), lines(stderr));
+
+    while let Some(line) = lines.next().await {
+        let _ = output
+            .send(Output {
+                generation,
+                event: Event::Line(line),
+            })
+            .await;
+    }
+
+    Ok(process.wait().await?.code())
+}
+
+/// The lines read from `reader`.
+fn lines(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+) -> impl Stream<Item = String> + Unpin + Send {
+    Box::pin(stream::unfold(BufReader::new(reader), |mut reader| async move {
+        let mut line = Vec::new();
+
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&line);
+
+                Some((line.trim_end_matches(['\r', '\n']).to_owned(), reader))
+            }
+        }
+    }))
+}
@@ -0,0 +1,373 @@
+//! The steps that prepare the text of a document before it is written,
+//! writing it safely, and the backups kept of what it overwrites.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{self, AtomicU64},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{config, imports, json};
+
+/// The number of the next temporary file, so that saves in flight at once
+/// never share one.
+static NEXT_TEMPORARY: AtomicU64 = AtomicU64::new(0);
+
+/// A transformation of the text run on save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    TrimTrailingWhitespace,
+    /// Leaves no more blank lines in a row than the configured maximum.
+    CollapseBlankLines,
+    EnsureFinalNewline,
+    /// Pretty-prints `.json` files, refusing to save invalid ones.
+    FormatJson,
+    /// Sorts the `use` declarations of Rust files, when it can be done
+    /// safely.
+    OrganizeImports,
+}
+
+impl Hook {
+    /// Every hook, in the order they run.
+    pub const ALL: [Self; 5] = [
+        Self::TrimTrailingWhitespace,
+        Self::CollapseBlankLines,
+        Self::EnsureFinalNewline,
+        Self::FormatJson,
+        Self::OrganizeImports,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TrimTrailingWhitespace => "Trim trailing whitespace",
+            Self::CollapseBlankLines => "Collapse blank lines",
+            Self::EnsureFinalNewline => "Ensure a final newline",
+            Self::FormatJson => "Format JSON",
+            Self::OrganizeImports => "Organize imports",
+        }
+    }
+
+    /// The name of the hook on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TrimTrailingWhitespace => "trim",
+            Self::CollapseBlankLines => "blank-lines",
+            Self::EnsureFinalNewline => "final-newline",
+            Self::FormatJson => "format",
+            Self::OrganizeImports => "imports",
+        }
+    }
+
+    /// The hook called `name` on the command line.
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|hook| hook.name() == name)
+    }
+
+    /// Transforms the `text` of a file with `extension`, or explains why it
+    /// should not be saved.
+    pub fn run(
+        self,
+        text: String,
+        extension: &str,
+        config: &config::Config,
+    ) -> Result<String, String> {
+        match self {
+            Self::TrimTrailingWhitespace => Ok(text
+                .split('\n')
+                .map(|line| line.trim_end_matches([' ', '\t']))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            Self::CollapseBlankLines => {
+                Ok(collapse_blank_lines(&text, usize::from(config.max_blank_lines)))
+            }
+            Self::EnsureFinalNewline if !text.is_empty() && !text.ends_with('\n') => {
+                Ok(text + "\n")
+            }
+            Self::FormatJson if extension == "json" => {
+                let formatted = json::pretty(&text).map_err(|error| {
+                    format!(
+                        "Not saved: invalid JSON at line {}, column {}",
+                        error.line + 1,
+                        error.column + 1
+                    )
+                })?;
+
+                Ok(if text.ends_with('\n') {
+                    formatted + "\n"
+                } else {
+                    formatted
+                })
+            }
+            // Declarations it cannot read are no reason not to save
+            Self::OrganizeImports if extension == "rs" => {
+                Ok(imports::organize(&text).unwrap_or(text))
+            }
+            _ => Ok(text),
+        }
+    }
+}
+
+/// Runs the hooks enabled in `config` over `text` in order, stopping at the
+/// first that fails.
+pub fn run(config: &config::Config, text: String, extension: &str) -> Result<String, String> {
+    Hook::ALL
+        .into_iter()
+        .filter(|hook| config.on_save.contains(hook))
+        .try_fold(text, |text, hook| hook.run(text, extension, config))
+}
+
+/// `text` with no more than `max` blank lines in a row, the ones past that
+/// dropped.
+pub fn collapse_blank_lines(text: &str, max: usize) -> String {
+    // The final newline ends the last line rather than starting a blank one
+    let (body, end) = match text.strip_suffix('\n') {
+        Some(body) => (body, "\n"),
+        None => (text, ""),
+    };
+
+    let mut blank = 0;
+
+    body.split('\n')
+        .filter(|line| {
+            if line.trim().is_empty() {
+                blank += 1;
+                blank <= max
+            } else {
+                blank = 0;
+                true
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + end
+}
+
+/// Writes `bytes` to `path` without ever leaving it half written.
+///
+/// The text goes to a temporary file next to it first, which then replaces
+/// it in one rename. Where that cannot be done, like on some network file
+/// systems, the file is written directly instead; but when the text cannot
+/// even be written, as when the disk is full, the file is left as it was.
+pub async fn write(path: &Path, bytes: &[u8]) -> Result<(), io::ErrorKind> {
+    // Renaming over a symbolic link would replace the link itself
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .unwrap_or_else(|_| path.to_path_buf());
+
+    let temporary = temporary_path(&path);
+    let replaced = replace(&path, &temporary, bytes).await;
+
+    if replaced.is_err() {
+        let _ = tokio::fs::remove_file(&temporary).await;
+    }
+
+    match replaced {
+        Ok(()) => Ok(()),
+        Err(Failure::Write(error)) => Err(error.kind()),
+        Err(Failure::Replace) => tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|error| error.kind()),
+    }
+}
+
+/// How replacing a file with a temporary one failed.
+enum Failure {
+    /// The temporary file could not be written, and the file itself would
+    /// not be either.
+    Write(io::Error),
+    /// The temporary file could not take the place of the file.
+    Replace,
+}
+
+async fn replace(path: &Path, temporary: &Path, bytes: &[u8]) -> Result<(), Failure> {
+    let metadata = tokio::fs::metadata(path).await.ok();
+
+    let mut file = create_temporary(temporary, metadata.as_ref())
+        .await
+        .map_err(Failure::Write)?;
+    file.write_all(bytes).await.map_err(Failure::Write)?;
+    file.sync_all().await.map_err(Failure::Write)?;
+    drop(file);
+
+    // The new file has to end up the same as the one it replaces, or else
+    // writing over the old one in place is better
+    if let Some(metadata) = metadata {
+        tokio::fs::set_permissions(temporary, metadata.permissions())
+            .await
+            .map_err(Failure::Write)?;
+        preserve_owner(temporary, &metadata).map_err(|_| Failure::Replace)?;
+    }
+
+    tokio::fs::rename(temporary, path)
+        .await
+        .map_err(|_| Failure::Replace)
+}
+
+/// Creates the file the text is written to before it replaces the one with
+/// `metadata`, which no one can read that could not read the other.
+#[cfg(unix)]
+async fn create_temporary(
+    temporary: &Path,
+    metadata: Option<&std::fs::Metadata>,
+) -> io::Result<tokio::fs::File> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A new file gets the permissions of the umask, like any other
+    let mode = metadata.map_or(0o666, |metadata| metadata.permissions().mode() & 0o777);
+
+    // One left over would keep the permissions it was created with
+    let _ = tokio::fs::remove_file(temporary).await;
+
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(temporary)
+        .await
+}
+
+#[cfg(not(unix))]
+async fn create_temporary(
+    temporary: &Path,
+    _metadata: Option<&std::fs::Metadata>,
+) -> io::Result<tokio::fs::File> {
+    tokio::fs::File::create(temporary).await
+}
+
+/// Gives `file` the owner and group of the file it is about to replace.
+///
+/// Only the superuser may give files away, so this fails when saving a file
+/// owned by someone else.
+#[cfg(unix)]
+fn preserve_owner(file: &Path, metadata: &std::fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let current = std::fs::metadata(file)?;
+
+    if (current.uid(), current.gid()) == (metadata.uid(), metadata.gid()) {
+        return Ok(());
+    }
+
+    std::os::unix::fs::chown(file, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_file: &Path, _metadata: &std::fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+/// Writes `bytes` to `path` as the superuser, asking for a password with
+/// `pkexec`.
+///
+/// The file is written in place, keeping its owner and permissions.
+#[cfg(unix)]
+pub async fn write_elevated(path: &Path, bytes: &[u8]) -> Result<(), io::ErrorKind> {
+    use std::process::Stdio;
+
+    let mut process = tokio::process::Command::new("pkexec")
+        .arg("tee")
+        .arg("--")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| error.kind())?;
+
+    let mut stdin = process.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    stdin
+        .write_all(bytes)
+        .await
+        .map_err(|error| error.kind())?;
+    drop(stdin);
+
+    let status = process.wait().await.map_err(|error| error.kind())?;
+
+    // Dismissing the password prompt fails like any other refusal
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::ErrorKind::PermissionDenied)
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn write_elevated(_path: &Path, _bytes: &[u8]) -> Result<(), io::ErrorKind> {
+    Err(io::ErrorKind::Unsupported)
+}
+
+/// Creates the directories leading to `path` that do not exist yet.
+pub async fn create_parents(path: &Path) -> Result<(), io::ErrorKind> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|error| error.kind()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the file at `path` exists but cannot be written to.
+pub fn is_read_only(path: &Path) -> bool {
+    match std::fs::OpenOptions::new().write(true).open(path) {
+        Ok(_) => false,
+        Err(error) => error.kind() == io::ErrorKind::PermissionDenied,
+    }
+}
+
+/// A hidden file in the same directory, so that renaming it stays on the
+/// same file system.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        NEXT_TEMPORARY.fetch_add(1, atomic::Ordering::Relaxed)
+    ));
+
+    path.with_file_name(name)
+}
+
+/// Copies the file at `path` aside before it gets overwritten.
+///
+/// Files that do not exist yet have nothing to back up.
+pub async fn backup(path: &Path, backup: &config::Backup) -> Result<(), io::ErrorKind> {
+    if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+        return Ok(());
+    }
+
+    let target = backup_path(path, backup).await;
+
+    tokio::fs::copy(path, target)
+        .await
+        .map(|_| ())
+        .map_err(|error| error.kind())
+}
+
+async fn backup_path(path: &Path, backup: &config::Backup) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(&backup.suffix);
+
+    if !backup.numbered {
+        return PathBuf::from(name);
+    }
+
+    let mut number = 1;
+
+    loop {
+        let mut numbered = name.clone();
+        numbered.push(format!(".{number}"));
+
+        let numbered = PathBuf::from(numbered);
+
+        if !tokio::fs::try_exists(&numbered).await.unwrap_or(false) {
+            return numbered;
+        }
+
+        number += 1;
+    }
+}
@@ -0,0 +1,219 @@
+//! Growing a selection to the syntactic unit around it.
+//!
+//! Units are guessed from the text alone: words, string literals, the
+//! inside and then the whole of bracket pairs, lines and the document.
+
+use std::ops::Range;
+
+use crate::{complete, fold};
+
+/// The smallest unit of `text` that strictly contains the byte `range`.
+pub fn expand(text: &str, range: Range<usize>) -> Option<Range<usize>> {
+    let mut candidates = Vec::new();
+
+    candidates.extend(word(text, &range));
+    candidates.extend(strings(text, &range));
+
+    for pair in brackets(text, &range) {
+        let inside = pair.start + 1..pair.end - 1;
+
+        candidates.push(trim(text, inside.clone()));
+        candidates.push(inside);
+        candidates.push(pair);
+    }
+
+    candidates.extend(lines(text, &range));
+    candidates.push(0..text.len());
+
+    candidates
+        .into_iter()
+        .filter(|candidate| {
+            candidate.start <= range.start
+                && range.end <= candidate.end
+                && candidate.len() > range.len()
+        })
+        .min_by_key(Range::len)
+}
+
+/// The innermost bracket pair or string literal around the byte `offset`
+/// of `text`, delimiters included.
+pub fn pair(text: &str, offset: usize) -> Option<Range<usize>> {
+    let range = offset..offset;
+
+    // The strings come without and then with their quotes
+    let strings = strings(text, &range)
+        .into_iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|string| string.start < offset && offset < string.end);
+
+    brackets(text, &range)
+        .into_iter()
+        .chain(strings)
+        .min_by_key(Range::len)
+}
+
+/// Converts a line and byte column of `text` into a byte offset.
+pub fn offset(text: &str, line: usize, column: usize) -> usize {
+    let start: usize = text
+        .split('\n')
+        .take(line)
+        .map(|line| line.len() + 1)
+        .sum();
+
+    (start + column).min(text.len())
+}
+
+/// Converts a byte offset of `text` into a line and byte column.
+pub fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+
+    match before.rfind('\n') {
+        Some(newline) => (before.matches('\n').count(), offset - newline - 1),
+        None => (0, offset),
+    }
+}
+
+/// The line of `text` at the byte `offset`, with its newline.
+pub fn line(text: &str, offset: usize) -> Range<usize> {
+    let start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let end = text[offset..]
+        .find('\n')
+        .map_or(text.len(), |newline| offset + newline + 1);
+
+    start..end
+}
+
+/// The run of non-blank lines of `text` around the byte `offset`, or of
+/// blank ones if it is on one, with the newline of the last.
+pub fn paragraph(text: &str, offset: usize) -> Range<usize> {
+    let is_blank = |range: &Range<usize>| text[range.clone()].trim().is_empty();
+
+    let mut paragraph = line(text, offset);
+    let blank = is_blank(&paragraph);
+
+    while paragraph.start > 0 {
+        let previous = line(text, paragraph.start - 1);
+
+        if is_blank(&previous) != blank {
+            break;
+        }
+
+        paragraph.start = previous.start;
+    }
+
+    while paragraph.end < text.len() {
+        let next = line(text, paragraph.end);
+
+        if is_blank(&next) != blank {
+            break;
+        }
+
+        paragraph.end = next.end;
+    }
+
+    paragraph
+}
+
+fn word(text: &str, range: &Range<usize>) -> Option<Range<usize>> {
+    let is_word = complete::is_identifier;
+
+    let start = text[..range.start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(range.start, |(offset, _)| offset);
+
+    let end = range.end
+        + text[range.end..]
+            .chars()
+            .take_while(|c| is_word(*c))
+            .map(char::len_utf8)
+            .sum::<usize>();
+
+    let is_inside_word = text[range.clone()].chars().all(is_word);
+
+    (is_inside_word && end > start).then_some(start..end)
+}
+
+/// The string literals of the line around `range`, with and without their
+/// quotes.
+fn strings(text: &str, range: &Range<usize>) -> Vec<Range<usize>> {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = text[range.start..]
+        .find('\n')
+        .map_or(text.len(), |newline| range.start + newline);
+
+    let line = &text[line_start..line_end];
+    let mut strings = Vec::new();
+    let mut open: Option<(usize, char)> = None;
+    let mut chars = line.char_indices();
+
+    while let Some((offset, c)) = chars.next() {
+        match (open, c) {
+            (Some(_), '\\') => {
+                let _ = chars.next();
+            }
+            (Some((start, quote)), c) if c == quote => {
+                let start = line_start + start;
+                let end = line_start + offset + 1;
+
+                strings.push(start + 1..end - 1);
+                strings.push(start..end);
+                open = None;
+            }
+            (None, '"' | '\'' | '`') => open = Some((offset, c)),
+            _ => {}
+        }
+    }
+
+    strings
+}
+
+/// The bracket pairs that enclose `range`, brackets included.
+fn brackets(text: &str, range: &Range<usize>) -> Vec<Range<usize>> {
+    let mut pairs = Vec::new();
+    let mut open = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split('\n') {
+        for (offset, bracket) in fold::brackets(line) {
+            let offset = line_start + offset;
+
+            match bracket {
+                '{' | '[' | '(' => open.push(offset),
+                _ => {
+                    if let Some(start) = open.pop() {
+                        if start < range.start && range.end <= offset {
+                            pairs.push(start..offset + 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        line_start += line.len() + 1;
+    }
+
+    pairs
+}
+
+/// The lines around `range`, without and then with their indentation.
+fn lines(text: &str, range: &Range<usize>) -> [Range<usize>; 2] {
+    let start = text[..range.start].rfind('\n').map_or(0, |newline| newline + 1);
+    let end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |newline| range.end + newline);
+
+    [trim(text, start..end), start..end]
+}
+
+/// Shrinks `range` to leave out the whitespace at both of its ends.
+fn trim(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = range.end - (slice.len() - slice.trim_end().len());
+
+    start..end.max(start)
+}
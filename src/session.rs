@@ -0,0 +1,253 @@
+//! State kept between runs, in `session.toml` in the platform data directory.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The most files whose preferences are remembered.
+const CAPACITY: usize = 500;
+
+/// The heights the output panel can be given.
+pub const OUTPUT_HEIGHTS: RangeInclusive<f32> = 100.0..=600.0;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    /// The folded regions of each file, by path.
+    pub folds: BTreeMap<String, Vec<Anchor>>,
+    /// The notes on the lines of each file, by path.
+    pub notes: BTreeMap<String, Vec<Note>>,
+    /// How each file was set up to be shown, by path.
+    pub documents: BTreeMap<String, Document>,
+    /// The queries found before, the latest first.
+    pub searches: Vec<String>,
+    /// The replacements of queries, the latest first.
+    pub replacements: Vec<String>,
+    /// The directory of the file opened or saved last.
+    pub last_directory: Option<PathBuf>,
+    /// What the scratch buffer held when it was last left.
+    pub scratch: String,
+    pub layout: Layout,
+    /// The languages of files, as remembered before they were kept with
+    /// the rest of their preferences.
+    #[serde(skip_serializing)]
+    languages: BTreeMap<String, String>,
+    #[serde(skip_serializing)]
+    stick_to_bottom: BTreeSet<String>,
+    /// The session as it was last read or written, to tell the changes made
+    /// here from those other windows saved meanwhile.
+    #[serde(skip)]
+    base: Option<Box<Session>>,
+}
+
+/// Which panels are open and how big they are.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Layout {
+    pub is_output_open: bool,
+    pub output_height: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            is_output_open: false,
+            output_height: 200.0,
+        }
+    }
+}
+
+/// The preferences of a file, as they were last set for it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Document {
+    /// The extension of the language it is highlighted as, when it is not
+    /// its own.
+    pub language: Option<String>,
+    /// Whether its lines wrap at the fixed column, unlike the others.
+    pub wraps_at_column: Option<bool>,
+    /// Whether editing it is turned off.
+    pub is_read_only: bool,
+    /// Whether highlighting it is turned off.
+    pub is_plain: bool,
+    /// Whether its view sticks to the bottom as it grows.
+    pub sticks_to_bottom: bool,
+    /// When the preferences were last set, in seconds since the epoch.
+    pub used: u64,
+}
+
+/// Where a fold started, remembered by its line and the text on it, so that
+/// it can be found again after the lines above it change.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Anchor {
+    pub line: usize,
+    pub text: String,
+}
+
+/// Words about a line of a file, kept here rather than in the file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Note {
+    pub text: String,
+    pub anchor: Anchor,
+}
+
+impl Anchor {
+    /// The line of `lines` closest to the remembered one with the same text.
+    pub fn find(&self, lines: &[&str]) -> Option<usize> {
+        let text = self.text.trim();
+
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.trim() == text)
+            .map(|(number, _)| number)
+            .min_by_key(|number| number.abs_diff(self.line))
+    }
+}
+
+impl Session {
+    /// Reads the session of the last run, if there was one.
+    pub fn load() -> Self {
+        let mut session = Self::read();
+        session.base = Some(Box::new(session.clone()));
+
+        session
+    }
+
+    /// The session as it is on disk now.
+    fn read() -> Self {
+        let mut session: Self = path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+
+        for (key, language) in std::mem::take(&mut session.languages) {
+            session.documents.entry(key).or_default().language = Some(language);
+        }
+
+        for key in std::mem::take(&mut session.stick_to_bottom) {
+            session.documents.entry(key).or_default().sticks_to_bottom = true;
+        }
+
+        session
+    }
+
+    /// The preferences of the file with `key`, or the defaults.
+    pub fn document(&self, key: &str) -> Document {
+        self.documents.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Remembers the preferences of the file with `key`, forgetting those
+    /// of the files set up the longest ago beyond the capacity.
+    pub fn remember(&mut self, key: String, document: Document) {
+        let document = Document { used: 0, ..document };
+
+        if document == Document::default() {
+            self.documents.remove(&key);
+            return;
+        }
+
+        let used = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.documents.insert(key, Document { used, ..document });
+        self.forget_oldest();
+    }
+
+    fn forget_oldest(&mut self) {
+        while self.documents.len() > CAPACITY {
+            let oldest = self
+                .documents
+                .iter()
+                .min_by_key(|(_, document)| document.used)
+                .map(|(key, _)| key.clone());
+
+            match oldest {
+                Some(key) => self.documents.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Writes the session, over what other windows saved since it was read
+    /// only where it was changed here.
+    pub fn save(&mut self) -> Result<(), io::ErrorKind> {
+        let path = path().ok_or(io::ErrorKind::NotFound)?;
+        let base = self.base.take().map(|base| *base).unwrap_or_default();
+        let saved = Self::read();
+
+        let mut session = Self {
+            folds: merge(&base.folds, &self.folds, saved.folds),
+            notes: merge(&base.notes, &self.notes, saved.notes),
+            documents: merge(&base.documents, &self.documents, saved.documents),
+            searches: pick(&base.searches, &self.searches, saved.searches),
+            replacements: pick(&base.replacements, &self.replacements, saved.replacements),
+            last_directory: pick(&base.last_directory, &self.last_directory, saved.last_directory),
+            scratch: pick(&base.scratch, &self.scratch, saved.scratch),
+            layout: pick(&base.layout, &self.layout, saved.layout),
+            ..Self::default()
+        };
+        session.forget_oldest();
+        session.base = Some(Box::new(session.clone()));
+        *self = session;
+
+        let text = toml::to_string_pretty(self).map_err(|_| io::ErrorKind::InvalidData)?;
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|error| error.kind())?;
+        }
+
+        std::fs::write(path, text).map_err(|error| error.kind())
+    }
+}
+
+/// The entries of `saved`, but those changed here from `base` as they are
+/// in `ours`.
+fn merge<T: Clone + PartialEq>(
+    base: &BTreeMap<String, T>,
+    ours: &BTreeMap<String, T>,
+    mut saved: BTreeMap<String, T>,
+) -> BTreeMap<String, T> {
+    for key in base.keys().chain(ours.keys()) {
+        match (base.get(key), ours.get(key)) {
+            (before, after) if before == after => {}
+            (_, Some(value)) => {
+                let _ = saved.insert(key.clone(), value.clone());
+            }
+            (_, None) => {
+                let _ = saved.remove(key);
+            }
+        }
+    }
+
+    saved
+}
+
+/// `ours`, if it was changed here from `base`, or else `saved`.
+fn pick<T: Clone + PartialEq>(base: &T, ours: &T, saved: T) -> T {
+    if ours == base {
+        saved
+    } else {
+        ours.clone()
+    }
+}
+
+/// The key of a file in the session.
+pub fn key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join("text-editor").join("session.toml"))
+}
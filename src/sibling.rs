@@ -0,0 +1,62 @@
+//! Flipping through the other files of the directory of a file.
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::hex;
+
+/// Which way to flip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Next,
+    Previous,
+}
+
+/// The text file after or before the one at `path` in its directory, in
+/// the order of their names, wrapping around at the ends.
+pub fn find(path: &Path, direction: Direction) -> Option<PathBuf> {
+    let name = path.file_name()?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| entry.path())
+        .collect();
+
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+    // The open file may be gone, so it is placed among the others by name
+    let position = files.partition_point(|file| file.file_name() < Some(name));
+    let is_listed = files.get(position).and_then(|file| file.file_name()) == Some(name);
+    let count = files.len();
+    let skipped = usize::from(is_listed);
+
+    let order: Vec<usize> = match direction {
+        Direction::Next => (skipped..count).map(|step| (position + step) % count).collect(),
+        Direction::Previous => (1..=count - skipped)
+            .map(|step| (position + count - step) % count)
+            .collect(),
+    };
+
+    order
+        .into_iter()
+        .map(|index| &files[index])
+        .find(|file| is_text(file))
+        .cloned()
+}
+
+/// Whether the start of the file at `path` reads like text.
+fn is_text(path: &Path) -> bool {
+    let mut start = Vec::new();
+
+    std::fs::File::open(path)
+        .and_then(|file| file.take(8000).read_to_end(&mut start))
+        .is_ok_and(|_| !hex::is_binary(&start))
+}
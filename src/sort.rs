@@ -0,0 +1,91 @@
+//! Sorting lines as text or numbers, by the whole line or one of its fields.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    #[default]
+    Text,
+    /// By the number the key starts with, so that `2` comes before `10`.
+    Numeric,
+}
+
+impl Order {
+    pub const ALL: &'static [Self] = &[Self::Text, Self::Numeric];
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "Text",
+            Self::Numeric => "Numeric",
+        })
+    }
+}
+
+/// The sort options, as typed in the sort bar.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub order: Order,
+    /// The field separator, where `\t` stands for a tab. Empty to sort by
+    /// the whole line.
+    pub delimiter: String,
+    /// The field to sort by, counting from 1.
+    pub field: String,
+}
+
+impl State {
+    /// The field to sort by, unless it is not a positive number.
+    pub fn field(&self) -> Option<usize> {
+        self.field
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|field| *field > 0)
+    }
+
+    /// Sorts `lines` in place, keeping equal ones in their order.
+    pub fn sort(&self, lines: &mut [&str]) {
+        let delimiter = self.delimiter.replace("\\t", "\t");
+        let field = self.field().unwrap_or(1);
+
+        let key = |line: &str| -> String {
+            if delimiter.is_empty() {
+                line.to_owned()
+            } else {
+                line.split(delimiter.as_str())
+                    .nth(field - 1)
+                    .unwrap_or_default()
+                    .to_owned()
+            }
+        };
+
+        match self.order {
+            Order::Text => lines.sort_by_cached_key(|line| key(line)),
+            Order::Numeric => lines.sort_by(|a, b| compare_numbers(&key(a), &key(b))),
+        }
+    }
+}
+
+/// Orders keys by their leading number, putting those without one last.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    match (leading_number(a), leading_number(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn leading_number(key: &str) -> Option<f64> {
+    let key = key.trim_start();
+    let end = key
+        .char_indices()
+        .find(|(index, c)| {
+            !(c.is_ascii_digit() || *c == '.' || (*index == 0 && matches!(c, '-' | '+')))
+        })
+        .map_or(key.len(), |(index, _)| index);
+
+    key[..end].parse().ok()
+}
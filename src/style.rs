@@ -0,0 +1,46 @@
+//! Widget styles that depart from the built-in theme.
+
+use iced::widget::text_editor::{Appearance, StyleSheet};
+use iced::{theme, Color, Theme};
+
+/// The default look of the editor, with a custom selection color.
+pub struct Editor {
+    pub selection: Option<Color>,
+}
+
+impl StyleSheet for Editor {
+    type Style = Theme;
+
+    fn active(&self, style: &Theme) -> Appearance {
+        style.active(&theme::TextEditor::Default)
+    }
+
+    fn focused(&self, style: &Theme) -> Appearance {
+        style.focused(&theme::TextEditor::Default)
+    }
+
+    fn hovered(&self, style: &Theme) -> Appearance {
+        style.hovered(&theme::TextEditor::Default)
+    }
+
+    fn disabled(&self, style: &Theme) -> Appearance {
+        style.disabled(&theme::TextEditor::Default)
+    }
+
+    fn placeholder_color(&self, style: &Theme) -> Color {
+        style.placeholder_color(&theme::TextEditor::Default)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.value_color(&theme::TextEditor::Default)
+    }
+
+    fn disabled_color(&self, style: &Theme) -> Color {
+        style.disabled_color(&theme::TextEditor::Default)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        self.selection
+            .unwrap_or_else(|| style.selection_color(&theme::TextEditor::Default))
+    }
+}
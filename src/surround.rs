@@ -0,0 +1,71 @@
+//! Wrapping the selection in a pair of delimiters, picked by the character
+//! typed after asking to surround it.
+
+use std::{collections::BTreeMap, ops::Range};
+
+/// The character that asks for the name of a tag to wrap in, instead.
+pub const TAG: char = '<';
+
+/// The pairs of delimiters of the characters that stand for them, either
+/// side of a bracket picking the same pair.
+pub fn defaults() -> BTreeMap<String, (String, String)> {
+    [
+        ("(", "(", ")"),
+        (")", "(", ")"),
+        ("[", "[", "]"),
+        ("]", "[", "]"),
+        ("{", "{", "}"),
+        ("}", "{", "}"),
+        ("\"", "\"", "\""),
+        ("'", "'", "'"),
+        ("`", "`", "`"),
+        ("*", "*", "*"),
+        ("_", "_", "_"),
+        ("~", "~~", "~~"),
+    ]
+    .into_iter()
+    .map(|(key, open, close)| (key.to_owned(), (open.to_owned(), close.to_owned())))
+    .collect()
+}
+
+/// The delimiters `c` stands for in `pairs`, or `c` itself on both sides.
+pub fn pair(pairs: &BTreeMap<String, (String, String)>, c: char) -> (String, String) {
+    pairs
+        .get(c.encode_utf8(&mut [0; 4]) as &str)
+        .cloned()
+        .unwrap_or_else(|| (c.to_string(), c.to_string()))
+}
+
+/// The tags of an element opened by `tag`, like `a href="#"`, which only
+/// the name closes.
+pub fn tag(tag: &str) -> (String, String) {
+    let tag = tag.trim();
+    let name = tag.split_whitespace().next().unwrap_or(tag);
+
+    (format!("<{tag}>"), format!("</{name}>"))
+}
+
+/// `text` with the byte `range` wrapped in `open` and `close`.
+pub fn wrap(text: &str, range: Range<usize>, open: &str, close: &str) -> String {
+    [
+        &text[..range.start],
+        open,
+        &text[range.clone()],
+        close,
+        &text[range.end..],
+    ]
+    .concat()
+}
+
+/// `text` with `open` and `close` in place of the first and last bytes of
+/// `pair`, which are its delimiters.
+pub fn replace(text: &str, pair: Range<usize>, open: &str, close: &str) -> String {
+    [
+        &text[..pair.start],
+        open,
+        &text[pair.start + 1..pair.end - 1],
+        close,
+        &text[pair.end..],
+    ]
+    .concat()
+}
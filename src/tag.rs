@@ -0,0 +1,181 @@
+//! Matching the opening and closing tags of markup, and renaming both at
+//! once.
+
+use std::ops::Range;
+
+use crate::emmet;
+
+/// An opening or closing tag, as byte ranges of the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    /// From `<` to `>`.
+    pub range: Range<usize>,
+    pub name: Range<usize>,
+}
+
+/// An opening tag and the closing tag that matches it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    pub open: Tag,
+    pub close: Tag,
+}
+
+impl Pair {
+    /// The bytes between the two tags.
+    pub fn contents(&self) -> Range<usize> {
+        self.open.range.end..self.close.range.start
+    }
+}
+
+/// The pair with a tag whose name the byte `offset` of `text` is in or
+/// right after.
+pub fn at(text: &str, offset: usize, is_html: bool) -> Option<Pair> {
+    let touches = |tag: &Tag| tag.name.start <= offset && offset <= tag.name.end;
+
+    pairs(text, is_html)
+        .into_iter()
+        .find(|pair| touches(&pair.open) || touches(&pair.close))
+}
+
+/// The innermost pair whose contents hold the byte `offset` of `text`.
+pub fn enclosing(text: &str, offset: usize, is_html: bool) -> Option<Pair> {
+    pairs(text, is_html)
+        .into_iter()
+        .filter(|pair| pair.contents().start <= offset && offset <= pair.contents().end)
+        .max_by_key(|pair| pair.open.range.start)
+}
+
+/// `text` with both tags of `pair` named `name`.
+pub fn rename(text: &str, pair: &Pair, name: &str) -> String {
+    [
+        &text[..pair.open.name.start],
+        name,
+        &text[pair.open.name.end..pair.close.name.start],
+        name,
+        &text[pair.close.name.end..],
+    ]
+    .concat()
+}
+
+/// Every pair of tags of `text`, by where they open.
+///
+/// A closing tag closes the latest opening tag with its name, along with
+/// the ones left open after it, and one that closes nothing is skipped.
+fn pairs(text: &str, is_html: bool) -> Vec<Pair> {
+    let mut pairs = Vec::new();
+    let mut open: Vec<Tag> = Vec::new();
+
+    let name = |tag: &Tag| {
+        let name = &text[tag.name.clone()];
+
+        // Only XML tells case apart
+        if is_html {
+            name.to_ascii_lowercase()
+        } else {
+            name.to_owned()
+        }
+    };
+
+    for (tag, is_closing) in tags(text, is_html) {
+        if !is_closing {
+            open.push(tag);
+            continue;
+        }
+
+        let closed = name(&tag);
+
+        if let Some(index) = open.iter().rposition(|opening| name(opening) == closed) {
+            let opening = open.remove(index);
+            open.truncate(index);
+
+            pairs.push(Pair {
+                open: opening,
+                close: tag,
+            });
+        }
+    }
+
+    pairs.sort_by_key(|pair| pair.open.range.start);
+    pairs
+}
+
+/// The tags of `text` that open or close an element with contents, and
+/// whether they close it.
+fn tags(text: &str, is_html: bool) -> Vec<(Tag, bool)> {
+    let mut tags = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = text[position..].find('<').map(|found| position + found) {
+        let rest = &text[start..];
+
+        let skipped = if rest.starts_with("<!--") {
+            Some(rest.find("-->").map_or(text.len(), |end| start + end + 3))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(rest.find('>').map_or(text.len(), |end| start + end + 1))
+        } else {
+            None
+        };
+
+        if let Some(next) = skipped {
+            position = next;
+            continue;
+        }
+
+        let is_closing = rest[1..].starts_with('/');
+        let name_start = start + 1 + usize::from(is_closing);
+        let name_end = text[name_start..]
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')))
+            .map_or(text.len(), |end| name_start + end);
+
+        let Some(end) = tag_end(text, name_end).filter(|_| name_end > name_start) else {
+            position = start + 1;
+            continue;
+        };
+
+        let name = text[name_start..name_end].to_ascii_lowercase();
+        let is_void = is_html && emmet::VOID.contains(&name.as_str());
+        let is_empty = text[..end].ends_with("/>") || (is_void && !is_closing);
+
+        if !is_empty {
+            tags.push((
+                Tag {
+                    range: start..end,
+                    name: name_start..name_end,
+                },
+                is_closing,
+            ));
+        }
+
+        position = end;
+
+        // Scripts and styles hold code, whose `<` are not tags
+        if is_html && !is_closing && matches!(name.as_str(), "script" | "style") {
+            let closing = format!("</{name}");
+
+            if let Some(found) = text[end..].to_ascii_lowercase().find(&closing) {
+                position = end + found;
+            }
+        }
+    }
+
+    tags
+}
+
+/// The byte after the `>` closing a tag whose name ends at `from`, unless
+/// another tag starts first.
+fn tag_end(text: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+
+    for (offset, c) in text[from..].char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(from + offset + 1),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+
+    None
+}
@@ -0,0 +1,83 @@
+//! Templates that new files start from, in `templates` in the platform
+//! config directory.
+//!
+//! Every file there is a template, named after its stem and highlighted as
+//! the language of its extension.
+
+use std::path::{Path, PathBuf};
+
+/// The templates written the first time templates are listed.
+const DEFAULTS: [(&str, &str); 3] = [
+    ("main.rs", "fn main() {\n    \n}\n"),
+    (
+        "page.html",
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         \x20   <meta charset=\"utf-8\">\n\
+         \x20   <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         \x20   <title></title>\n\
+         </head>\n\
+         <body>\n\
+         \x20   \n\
+         </body>\n\
+         </html>\n",
+    ),
+    (
+        "script.py",
+        "def main():\n    pass\n\n\nif __name__ == \"__main__\":\n    main()\n",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    /// The extension of the language of the file.
+    pub extension: String,
+    pub path: PathBuf,
+}
+
+impl Template {
+    pub fn read(&self) -> std::io::Result<String> {
+        std::fs::read_to_string(&self.path)
+    }
+}
+
+/// The templates, by name, writing the default ones out first if there is
+/// no directory of templates yet.
+pub fn list() -> Vec<Template> {
+    let Some(directory) = directory() else {
+        return Vec::new();
+    };
+
+    if !directory.exists() && std::fs::create_dir_all(&directory).is_ok() {
+        for (name, text) in DEFAULTS {
+            let _ = std::fs::write(directory.join(name), text);
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(&directory) else {
+        return Vec::new();
+    };
+
+    let mut templates: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|entry| template(&entry.path()))
+        .collect();
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+fn template(path: &Path) -> Option<Template> {
+    Some(Template {
+        name: path.file_stem()?.to_str()?.to_owned(),
+        extension: path.extension()?.to_str()?.to_owned(),
+        path: path.to_path_buf(),
+    })
+}
+
+fn directory() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join("text-editor").join("templates"))
+}
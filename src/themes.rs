@@ -0,0 +1,228 @@
+//! The themes to pick from: the syntax themes that ship with the
+//! highlighter, plus presets tuned for legibility.
+//!
+//! The presets have no syntax theme of their own. They highlight with
+//! Base16 Ocean and then swap each of its colors for the one their
+//! palette chose for the same role.
+
+use std::fmt;
+
+use iced::highlighter;
+use iced::{theme, Color, Theme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Syntax(highlighter::Theme),
+    HighContrastDark,
+    HighContrastLight,
+    Deuteranopia,
+}
+
+impl Choice {
+    pub const ALL: &'static [Self] = &[
+        Self::Syntax(highlighter::Theme::SolarizedDark),
+        Self::Syntax(highlighter::Theme::Base16Mocha),
+        Self::Syntax(highlighter::Theme::Base16Ocean),
+        Self::Syntax(highlighter::Theme::Base16Eighties),
+        Self::Syntax(highlighter::Theme::InspiredGitHub),
+        Self::HighContrastDark,
+        Self::HighContrastLight,
+        Self::Deuteranopia,
+    ];
+
+    /// The theme named `name`, as the picker shows it.
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|choice| choice.to_string() == name)
+            .copied()
+    }
+
+    /// The syntax theme that does the highlighting.
+    pub fn syntax(self) -> highlighter::Theme {
+        match self {
+            Self::Syntax(theme) => theme,
+            _ => highlighter::Theme::Base16Ocean,
+        }
+    }
+
+    /// The colors that replace the ones of the syntax theme, if any.
+    pub fn palette(self) -> Option<&'static Palette> {
+        match self {
+            Self::Syntax(_) => None,
+            Self::HighContrastDark => Some(&HIGH_CONTRAST_DARK),
+            Self::HighContrastLight => Some(&HIGH_CONTRAST_LIGHT),
+            Self::Deuteranopia => Some(&DEUTERANOPIA),
+        }
+    }
+
+    /// The colors of the highlighting of tree-sitter, which knows no syntax
+    /// theme: the palette of a preset, or else the colors of Base16 Ocean
+    /// or of InspiredGitHub, whichever is as dark as the syntax theme.
+    pub fn colors(self) -> &'static Palette {
+        match self.palette() {
+            Some(palette) => palette,
+            None if self.syntax().is_dark() => &BASE16_OCEAN,
+            None => &INSPIRED_GITHUB,
+        }
+    }
+
+    /// The theme of the widgets around the editor.
+    pub fn theme(self) -> Theme {
+        match self.palette() {
+            Some(palette) => Theme::custom(palette.widgets()),
+            None if self.syntax().is_dark() => Theme::Dark,
+            None => Theme::Light,
+        }
+    }
+}
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(theme) => write!(f, "{theme}"),
+            Self::HighContrastDark => write!(f, "High Contrast Dark"),
+            Self::HighContrastLight => write!(f, "High Contrast Light"),
+            Self::Deuteranopia => write!(f, "Deuteranopia Friendly"),
+        }
+    }
+}
+
+/// The colors of a preset, by the role they play.
+#[derive(Debug, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub text: Color,
+    pub primary: Color,
+    pub success: Color,
+    pub danger: Color,
+    pub comment: Color,
+    pub keyword: Color,
+    pub string: Color,
+    pub number: Color,
+    pub variable: Color,
+    pub kind: Color,
+    pub function: Color,
+    pub escape: Color,
+}
+
+impl Palette {
+    /// Swaps a color of Base16 Ocean for the one of the same role.
+    pub fn remap(&self, color: Color) -> Color {
+        match color.into_rgba8() {
+            [0x65, 0x73, 0x7e, _] => self.comment,
+            [0xb4, 0x8e, 0xad, _] => self.keyword,
+            [0xa3, 0xbe, 0x8c, _] => self.string,
+            [0xd0, 0x87, 0x70, _] => self.number,
+            [0xbf, 0x61, 0x6a, _] => self.variable,
+            [0xeb, 0xcb, 0x8b, _] => self.kind,
+            [0x8f, 0xa1, 0xb3, _] => self.function,
+            [0x96, 0xb5, 0xb4, _] => self.escape,
+            _ => self.text,
+        }
+    }
+
+    fn widgets(&self) -> theme::Palette {
+        theme::Palette {
+            background: self.background,
+            text: self.text,
+            primary: self.primary,
+            success: self.success,
+            danger: self.danger,
+        }
+    }
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color {
+        r: ((hex >> 16) & 0xff) as f32 / 255.0,
+        g: ((hex >> 8) & 0xff) as f32 / 255.0,
+        b: (hex & 0xff) as f32 / 255.0,
+        a: 1.0,
+    }
+}
+
+/// The colors of the syntax theme, which the presets swap out.
+static BASE16_OCEAN: Palette = Palette {
+    background: rgb(0x2b303b),
+    text: rgb(0xc0c5ce),
+    primary: rgb(0x8fa1b3),
+    success: rgb(0xa3be8c),
+    danger: rgb(0xbf616a),
+    comment: rgb(0x65737e),
+    keyword: rgb(0xb48ead),
+    string: rgb(0xa3be8c),
+    number: rgb(0xd08770),
+    variable: rgb(0xbf616a),
+    kind: rgb(0xebcb8b),
+    function: rgb(0x8fa1b3),
+    escape: rgb(0x96b5b4),
+};
+
+static INSPIRED_GITHUB: Palette = Palette {
+    background: rgb(0xffffff),
+    text: rgb(0x323232),
+    primary: rgb(0x183691),
+    success: rgb(0x55a532),
+    danger: rgb(0xbd2c00),
+    comment: rgb(0x969896),
+    keyword: rgb(0xa71d5d),
+    string: rgb(0x183691),
+    number: rgb(0x0086b3),
+    variable: rgb(0xed6a43),
+    kind: rgb(0x795da3),
+    function: rgb(0x795da3),
+    escape: rgb(0x0086b3),
+};
+
+/// Every color is at least 7:1 against black.
+static HIGH_CONTRAST_DARK: Palette = Palette {
+    background: rgb(0x000000),
+    text: rgb(0xffffff),
+    primary: rgb(0x4da3ff),
+    success: rgb(0x5cff7a),
+    danger: rgb(0xff6b6b),
+    comment: rgb(0xbdbdbd),
+    keyword: rgb(0xff9bf5),
+    string: rgb(0xa8ff8a),
+    number: rgb(0xffbd6e),
+    variable: rgb(0xff9d9d),
+    kind: rgb(0xffe45c),
+    function: rgb(0x8fd3ff),
+    escape: rgb(0x7ff5e0),
+};
+
+/// Every color is at least 7:1 against white.
+static HIGH_CONTRAST_LIGHT: Palette = Palette {
+    background: rgb(0xffffff),
+    text: rgb(0x000000),
+    primary: rgb(0x0040a8),
+    success: rgb(0x005c17),
+    danger: rgb(0xa30000),
+    comment: rgb(0x4a4a4a),
+    keyword: rgb(0x6a00a0),
+    string: rgb(0x00570f),
+    number: rgb(0x803300),
+    variable: rgb(0x9c0000),
+    kind: rgb(0x5e4600),
+    function: rgb(0x0030a0),
+    escape: rgb(0x005757),
+};
+
+/// Built on the Okabe-Ito colors, which stay apart without telling red
+/// from green.
+static DEUTERANOPIA: Palette = Palette {
+    background: rgb(0x1c1e24),
+    text: rgb(0xe8e8e8),
+    primary: rgb(0x0072b2),
+    success: rgb(0x56b4e9),
+    danger: rgb(0xe69f00),
+    comment: rgb(0x8f8f8f),
+    keyword: rgb(0x56b4e9),
+    string: rgb(0xe69f00),
+    number: rgb(0xf0e442),
+    variable: rgb(0xcc79a7),
+    kind: rgb(0xf5d37a),
+    function: rgb(0xa8c8ff),
+    escape: rgb(0xff8c52),
+};
@@ -0,0 +1,118 @@
+//! Markers like `TODO` and `FIXME` left in comments, to color them and to
+//! list them.
+
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::replace;
+
+/// What starts a comment in the languages the editor knows, as far as
+/// telling a marker from a word of the code goes.
+const COMMENT_STARTS: [&str; 5] = ["//", "#", "/*", "--", ";"];
+
+/// A marker and what follows it on its line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub line: usize,
+    /// The bytes of the keyword in the line.
+    pub range: Range<usize>,
+    pub keyword: String,
+    pub text: String,
+}
+
+/// The keywords marked by default, with their colors.
+pub fn defaults() -> BTreeMap<String, String> {
+    [
+        ("TODO", "#61afef"),
+        ("FIXME", "#e06c75"),
+        ("HACK", "#d19a66"),
+        ("XXX", "#c678dd"),
+    ]
+    .into_iter()
+    .map(|(keyword, color)| (keyword.to_owned(), color.to_owned()))
+    .collect()
+}
+
+/// The markers of `text` whose keyword is one of `keywords`.
+///
+/// A keyword only marks when it is a whole word after the start of a
+/// comment on its line, or on a line that goes on a block comment.
+pub fn find<'a>(text: &str, keywords: impl IntoIterator<Item = &'a str> + Clone) -> Vec<Marker> {
+    let mut markers = Vec::new();
+
+    for (number, line) in text.split('\n').enumerate() {
+        let comment = COMMENT_STARTS
+            .iter()
+            .filter_map(|start| line.find(start))
+            .min()
+            .or_else(|| line.trim_start().starts_with('*').then_some(0));
+
+        let Some(comment) = comment else {
+            continue;
+        };
+
+        for keyword in keywords.clone() {
+            let found = line[comment..]
+                .match_indices(keyword)
+                .map(|(offset, _)| comment + offset)
+                .find(|&start| is_word(line, start..start + keyword.len()));
+
+            if let Some(start) = found {
+                let rest = &line[start + keyword.len()..];
+                let text = rest
+                    .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
+                    .trim_end_matches("*/")
+                    .trim();
+
+                markers.push(Marker {
+                    line: number,
+                    range: start..start + keyword.len(),
+                    keyword: keyword.to_owned(),
+                    text: text.to_owned(),
+                });
+            }
+        }
+    }
+
+    markers.sort_by_key(|marker| (marker.line, marker.range.start));
+    markers
+}
+
+/// The markers of every text file below `root`, other than `skipped`.
+pub async fn search(
+    root: PathBuf,
+    keywords: Vec<String>,
+    skipped: Option<PathBuf>,
+) -> Vec<(PathBuf, Marker)> {
+    let mut found = Vec::new();
+
+    for path in replace::files(&root) {
+        if skipped.as_deref() == Some(path.as_path()) {
+            continue;
+        }
+
+        let Ok(text) = tokio::fs::read_to_string(&path).await else {
+            continue;
+        };
+
+        let markers = find(&text, keywords.iter().map(String::as_str));
+
+        found.extend(markers.into_iter().map(|marker| (path.clone(), marker)));
+    }
+
+    found
+}
+
+/// `path` as it reads from `root`.
+pub fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+fn is_word(line: &str, range: Range<usize>) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    !line[..range.start].ends_with(is_word_char) && !line[range.end..].starts_with(is_word_char)
+}
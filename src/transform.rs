@@ -0,0 +1,135 @@
+//! Conversions of the selected text.
+
+use std::fmt;
+
+use base64::Engine as _;
+
+use crate::calculate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Base64Encode,
+    Base64Decode,
+    HexEncode,
+    HexDecode,
+    UrlEncode,
+    UrlDecode,
+    /// Appends the result of an arithmetic expression.
+    Calculate,
+    /// Replaces an arithmetic expression with its result.
+    Evaluate,
+}
+
+impl Transform {
+    pub const ALL: &'static [Self] = &[
+        Self::Base64Encode,
+        Self::Base64Decode,
+        Self::HexEncode,
+        Self::HexDecode,
+        Self::UrlEncode,
+        Self::UrlDecode,
+        Self::Calculate,
+        Self::Evaluate,
+    ];
+
+    /// Converts `text`, or explains why it cannot be.
+    pub fn apply(self, text: &str) -> Result<String, String> {
+        match self {
+            Self::Base64Encode => Ok(base64::engine::general_purpose::STANDARD.encode(text)),
+            Self::Base64Decode => base64::engine::general_purpose::STANDARD
+                .decode(text.trim())
+                .map_err(|error| format!("Invalid base64: {error}"))
+                .and_then(utf8),
+            Self::HexEncode => Ok(text.bytes().map(|byte| format!("{byte:02x}")).collect()),
+            Self::HexDecode => hex_decode(text).and_then(utf8),
+            Self::UrlEncode => Ok(url_encode(text)),
+            Self::UrlDecode => url_decode(text).and_then(utf8),
+            Self::Calculate => {
+                let result = calculate::evaluate(text)?;
+
+                Ok(format!("{} = {}", text.trim_end(), calculate::format(result)))
+            }
+            Self::Evaluate => calculate::evaluate(text).map(calculate::format),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Base64Encode => "Base64 encode",
+            Self::Base64Decode => "Base64 decode",
+            Self::HexEncode => "Hex encode",
+            Self::HexDecode => "Hex decode",
+            Self::UrlEncode => "URL encode",
+            Self::UrlDecode => "URL decode",
+            Self::Calculate => "Calculate",
+            Self::Evaluate => "Replace with result",
+        })
+    }
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(bytes).map_err(|_| "The decoded bytes are not UTF-8".to_owned())
+}
+
+/// Decodes pairs of hex digits, ignoring the whitespace between them.
+fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+
+    if digits.len() % 2 != 0 {
+        return Err("Invalid hex: odd number of digits".to_owned());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("Invalid hex: {}", String::from_utf8_lossy(pair)))
+        })
+        .collect()
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn url_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    encoded
+}
+
+/// Decodes percent escapes, and `+` as a space like in query strings.
+fn url_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let escape = [bytes.next(), bytes.next()];
+
+                let value = match escape {
+                    [Some(high), Some(low)] => std::str::from_utf8(&[high, low])
+                        .ok()
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok()),
+                    _ => None,
+                };
+
+                decoded.push(value.ok_or("Invalid URL encoding: broken % escape")?);
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+    }
+
+    Ok(decoded)
+}
@@ -0,0 +1,90 @@
+//! Swapping neighboring characters and words of a line, like readline.
+
+use std::ops::Range;
+
+use crate::complete;
+
+/// A swap: the byte range of the line it replaces and what replaces it.
+///
+/// The cursor ends up at the end of the replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Swaps the characters before and under the byte `column` of `line`, or
+/// the two before it at the end of the line.
+pub fn characters(line: &str, column: usize) -> Option<Swap> {
+    let before: Vec<(usize, char)> = line[..column].char_indices().collect();
+    let under = line[column..].chars().next();
+
+    let (start, first, second) = match (before.as_slice(), under) {
+        ([.., (start, first)], Some(second)) => (*start, *first, second),
+        ([.., (start, first), (_, second)], None) => (*start, *first, *second),
+        _ => return None,
+    };
+
+    Some(Swap {
+        range: start..start + first.len_utf8() + second.len_utf8(),
+        text: [second, first].into_iter().collect(),
+    })
+}
+
+/// Swaps the word before the byte `column` of `line` with the one after,
+/// or the word it is in with the next one.
+///
+/// Before the first word the first two words are swapped, and past the
+/// last one the last two.
+pub fn words(line: &str, column: usize) -> Option<Swap> {
+    let words = words_of(line);
+
+    let next = words
+        .iter()
+        .position(|word| word.end > column)
+        .map(|index| {
+            if words[index].start < column {
+                index + 1
+            } else {
+                index
+            }
+        })
+        .filter(|index| *index < words.len())
+        .unwrap_or(words.len().saturating_sub(1))
+        .max(1);
+
+    let left = words.get(next - 1)?;
+    let right = words.get(next)?;
+
+    Some(Swap {
+        range: left.start..right.end,
+        text: [
+            &line[right.clone()],
+            &line[left.end..right.start],
+            &line[left.clone()],
+        ]
+        .concat(),
+    })
+}
+
+fn words_of(line: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (offset, c) in line.char_indices() {
+        match (start, complete::is_identifier(c)) {
+            (None, true) => start = Some(offset),
+            (Some(word), false) => {
+                words.push(word..offset);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(word) = start {
+        words.push(word..line.len());
+    }
+
+    words
+}
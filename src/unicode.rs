@@ -0,0 +1,176 @@
+//! Characters that look like ASCII but are not, and looking up others by
+//! code point.
+
+use std::ops::Range;
+
+/// Parses a hexadecimal code point, like `2603` or `U+2603`.
+pub fn parse_code_point(input: &str) -> Option<char> {
+    let input = input.trim();
+    let digits = input
+        .strip_prefix("U+")
+        .or_else(|| input.strip_prefix("u+"))
+        .unwrap_or(input);
+
+    u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+}
+
+/// The code point and name of `c`, like `U+2603 SNOWMAN`.
+pub fn describe(c: char) -> String {
+    match unicode_names2::name(c) {
+        Some(name) => format!("U+{:04X} {name}", u32::from(c)),
+        None => format!("U+{:04X}", u32::from(c)),
+    }
+}
+
+/// Replaces typographic quotes, dashes and spaces with plain ASCII.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '‘' | '’' | '‚' | '‛' | '′' => normalized.push('\''),
+            '“' | '”' | '„' | '‟' | '″' => normalized.push('"'),
+            '‐' | '‑' | '‒' | '–' | '−' => normalized.push('-'),
+            '—' | '―' => normalized.push_str("--"),
+            '…' => normalized.push_str("..."),
+            '\u{a0}' | '\u{2007}' | '\u{202f}' => normalized.push(' '),
+            c => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Zero-width characters, which show as nothing at all.
+    Invisible,
+    /// Controls that reorder how text is displayed, as in Trojan Source.
+    Bidirectional,
+    /// Spaces other than the plain one.
+    Space,
+    /// Letters of another script that look like Latin ones, inside an
+    /// otherwise Latin word.
+    Homoglyph,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suspicious {
+    pub line: usize,
+    /// The bytes of the character in the line.
+    pub range: Range<usize>,
+    pub kind: Kind,
+}
+
+/// Finds the suspicious characters of `text`.
+pub fn scan(text: &str) -> Vec<Suspicious> {
+    let mut found = Vec::new();
+
+    for (number, line) in text.split('\n').enumerate() {
+        for (offset, c) in line.char_indices() {
+            let kind = match classify(c) {
+                Some((Kind::Homoglyph, _)) if !in_latin_word(line, offset) => continue,
+                Some((kind, _)) => kind,
+                None => continue,
+            };
+
+            found.push(Suspicious {
+                line: number,
+                range: offset..offset + c.len_utf8(),
+                kind,
+            });
+        }
+    }
+
+    found
+}
+
+/// Removes the invisible characters of `text` and replaces the others
+/// that `scan` finds with what they pass for.
+pub fn clean(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            line.char_indices()
+                .filter_map(|(offset, c)| match classify(c) {
+                    Some((Kind::Homoglyph, _)) if !in_latin_word(line, offset) => Some(c),
+                    Some((_, replacement)) => replacement,
+                    None => Some(c),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What kind of suspicious character `c` is, and what it should be.
+fn classify(c: char) -> Option<(Kind, Option<char>)> {
+    let kind = match c {
+        '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' | '\u{ad}' => {
+            (Kind::Invisible, None)
+        }
+        '\u{200e}' | '\u{200f}' | '\u{61c}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => {
+            (Kind::Bidirectional, None)
+        }
+        '\u{a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}' => {
+            (Kind::Space, Some(' '))
+        }
+        c => (Kind::Homoglyph, Some(homoglyph(c)?)),
+    };
+
+    Some(kind)
+}
+
+/// The Latin letter that a Cyrillic or Greek one looks like.
+fn homoglyph(c: char) -> Option<char> {
+    let latin = match c {
+        'а' | 'α' => 'a',
+        'е' => 'e',
+        'о' | 'ο' => 'o',
+        'р' | 'ρ' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'ԁ' => 'd',
+        'ν' => 'v',
+        'А' | 'Α' => 'A',
+        'В' | 'Β' => 'B',
+        'Е' | 'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Н' | 'Η' => 'H',
+        'І' | 'Ι' => 'I',
+        'Ј' => 'J',
+        'К' | 'Κ' => 'K',
+        'М' | 'Μ' => 'M',
+        'Ν' => 'N',
+        'О' | 'Ο' => 'O',
+        'Р' | 'Ρ' => 'P',
+        'С' => 'C',
+        'Ѕ' => 'S',
+        'Т' | 'Τ' => 'T',
+        'Υ' => 'Y',
+        'Х' | 'Χ' => 'X',
+        _ => return None,
+    };
+
+    Some(latin)
+}
+
+/// Whether the word around `offset` also has ASCII letters.
+fn in_latin_word(line: &str, offset: usize) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let start = line[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(offset, |(start, _)| start);
+
+    line[start..]
+        .chars()
+        .take_while(|c| is_word(*c))
+        .any(|c| c.is_ascii_alphabetic())
+}
@@ -0,0 +1,274 @@
+//! Vim keybindings, translated into the actions and messages of the editor.
+//!
+//! In normal and visual mode the typed characters never reach the buffer;
+//! they arrive here instead, either from the editor widget or, once Escape
+//! has unfocused it, straight from the keyboard.
+
+use std::sync::Arc;
+
+use iced::widget::text_editor::{Action, Content, Edit, Motion};
+
+use crate::{Message, Output};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl Mode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Normal => "-- NORMAL --",
+            Self::Insert => "-- INSERT --",
+            Self::Visual => "-- VISUAL --",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+enum Register {
+    #[default]
+    Empty,
+    Characters(String),
+    Lines(String),
+}
+
+#[derive(Debug)]
+pub struct State {
+    pub mode: Mode,
+    /// The command being typed after `:`.
+    pub command: Option<String>,
+    /// What the status bar tells about the last command.
+    pub message: Option<String>,
+    /// Whether to quit once the pending save succeeds, as `:wq` asks.
+    pub quit_on_save: bool,
+    /// The first half of a two-key command, like `d` of `dd`.
+    pending: Option<char>,
+    count: Option<usize>,
+    register: Register,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Normal,
+            command: None,
+            message: None,
+            quit_on_save: false,
+            pending: None,
+            count: None,
+            register: Register::Empty,
+        }
+    }
+}
+
+impl State {
+    /// Handles a typed character.
+    pub fn key(&mut self, c: char, content: &Content) -> Vec<Output> {
+        self.message = None;
+
+        match self.mode {
+            Mode::Insert => match c {
+                '\r' | '\n' => vec![edit(Edit::Enter)],
+                '\u{8}' => vec![edit(Edit::Backspace)],
+                c if c.is_control() => Vec::new(),
+                c => vec![edit(Edit::Insert(c))],
+            },
+            Mode::Normal | Mode::Visual => self.command(c, content),
+        }
+    }
+
+    /// Goes back to normal mode.
+    pub fn escape(&mut self) -> Vec<Output> {
+        let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+
+        self.pending = None;
+        self.count = None;
+
+        match mode {
+            // Stepping back onto the last inserted character, or onto the
+            // start of the selection, which also drops it
+            Mode::Insert | Mode::Visual => vec![motion(Motion::Left)],
+            Mode::Normal => Vec::new(),
+        }
+    }
+
+    fn command(&mut self, c: char, content: &Content) -> Vec<Output> {
+        if let Some(pending) = self.pending.take() {
+            return self.sequence(pending, c, content);
+        }
+
+        if let Some(digit) = c.to_digit(10).filter(|digit| *digit > 0 || self.count.is_some()) {
+            self.count = Some(self.count.unwrap_or(0) * 10 + digit as usize);
+            return Vec::new();
+        }
+
+        let count = self.count.take().unwrap_or(1);
+        let is_visual = self.mode == Mode::Visual;
+
+        let repeat = |motion: Motion| {
+            let action = if is_visual {
+                Action::Select(motion)
+            } else {
+                Action::Move(motion)
+            };
+
+            (0..count).map(|_| Output::Action(action.clone())).collect()
+        };
+
+        match c {
+            'h' => repeat(Motion::Left),
+            'j' | '\r' => repeat(Motion::Down),
+            'k' => repeat(Motion::Up),
+            'l' | ' ' => repeat(Motion::Right),
+            'w' => repeat(Motion::WordRight),
+            'b' => repeat(Motion::WordLeft),
+            '0' => repeat(Motion::Home),
+            '$' => repeat(Motion::End),
+            'G' => {
+                let mut outputs = repeat(Motion::DocumentEnd);
+                outputs.extend(repeat(Motion::Home));
+                outputs
+            }
+            'g' | 'd' | 'y' if !is_visual => {
+                self.pending = Some(c);
+                Vec::new()
+            }
+            'y' => {
+                self.register = Register::Characters(content.selection().unwrap_or_default());
+                self.mode = Mode::Normal;
+                vec![motion(Motion::Left)]
+            }
+            'd' | 'x' if is_visual => {
+                self.register = Register::Characters(content.selection().unwrap_or_default());
+                self.mode = Mode::Normal;
+                vec![edit(Edit::Delete)]
+            }
+            'x' => {
+                let (line, column) = content.cursor_position();
+                let character = content
+                    .line(line)
+                    .and_then(|text| text.get(column..)?.chars().next());
+
+                let Some(character) = character else {
+                    return Vec::new();
+                };
+
+                self.register = Register::Characters(character.to_string());
+                vec![select(Motion::Right), edit(Edit::Delete)]
+            }
+            'p' => self.put(true),
+            'P' => self.put(false),
+            'v' if is_visual => self.escape(),
+            'v' => {
+                self.mode = Mode::Visual;
+                Vec::new()
+            }
+            'i' => self.insert(Vec::new()),
+            'a' => self.insert(vec![motion(Motion::Right)]),
+            'A' => self.insert(vec![motion(Motion::End)]),
+            'I' => self.insert(vec![motion(Motion::Home)]),
+            'o' => self.insert(vec![motion(Motion::End), edit(Edit::Enter)]),
+            'O' => self.insert(vec![
+                motion(Motion::Home),
+                edit(Edit::Enter),
+                motion(Motion::Up),
+            ]),
+            '/' => vec![Output::Message(Message::OpenFind)],
+            'n' => vec![Output::Message(Message::FindNext)],
+            'N' => vec![Output::Message(Message::FindPrevious)],
+            ':' => {
+                self.command = Some(String::new());
+                vec![Output::Message(Message::VimCommandOpened)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn sequence(&mut self, first: char, second: char, content: &Content) -> Vec<Output> {
+        let (line, _) = content.cursor_position();
+        let text = content.line(line).map(|text| text.to_string()).unwrap_or_default();
+
+        match (first, second) {
+            ('g', 'g') => vec![motion(Motion::DocumentStart)],
+            ('y', 'y') => {
+                self.register = Register::Lines(text);
+                Vec::new()
+            }
+            ('d', 'd') => {
+                self.register = Register::Lines(text);
+
+                if line + 1 < content.line_count() {
+                    vec![
+                        motion(Motion::Home),
+                        select(Motion::Down),
+                        select(Motion::Home),
+                        edit(Edit::Delete),
+                    ]
+                } else if line > 0 {
+                    vec![
+                        motion(Motion::End),
+                        select(Motion::Up),
+                        select(Motion::End),
+                        edit(Edit::Delete),
+                        motion(Motion::Home),
+                    ]
+                } else {
+                    vec![motion(Motion::Home), select(Motion::End), edit(Edit::Delete)]
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Puts the register after the cursor, or before it.
+    fn put(&self, after: bool) -> Vec<Output> {
+        match &self.register {
+            Register::Empty => Vec::new(),
+            Register::Characters(text) => {
+                let mut outputs = Vec::new();
+
+                if after {
+                    outputs.push(motion(Motion::Right));
+                }
+
+                outputs.push(paste(text.clone()));
+                outputs
+            }
+            Register::Lines(text) if after => vec![
+                motion(Motion::End),
+                edit(Edit::Enter),
+                paste(text.clone()),
+                motion(Motion::Home),
+            ],
+            Register::Lines(text) => vec![
+                motion(Motion::Home),
+                paste(format!("{text}\n")),
+                motion(Motion::Up),
+            ],
+        }
+    }
+
+    fn insert(&mut self, outputs: Vec<Output>) -> Vec<Output> {
+        self.mode = Mode::Insert;
+        outputs
+    }
+}
+
+fn motion(motion: Motion) -> Output {
+    Output::Action(Action::Move(motion))
+}
+
+fn select(motion: Motion) -> Output {
+    Output::Action(Action::Select(motion))
+}
+
+fn edit(edit: Edit) -> Output {
+    Output::Action(Action::Edit(edit))
+}
+
+fn paste(text: String) -> Output {
+    edit(Edit::Paste(Arc::new(text)))
+}
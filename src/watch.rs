@@ -0,0 +1,153 @@
+//! Noticing when the open file changes on disk, like when a checkout
+//! replaces or removes it, or grows, like a log being written.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use iced::{subscription, Subscription};
+
+/// How often the file is looked at.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change of the file being watched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub generation: u64,
+    pub kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Modified,
+    Removed,
+    /// The file exists again after being removed.
+    Restored,
+}
+
+/// The changes of the file at `path` from how it is when watching starts.
+///
+/// A new `generation` starts over, like after the editor itself saved it.
+pub fn changes(generation: u64, path: PathBuf) -> Subscription<Change> {
+    enum State {
+        Starting(PathBuf),
+        Watching(PathBuf, Option<SystemTime>),
+    }
+
+    subscription::unfold(
+        ("watch", generation, path.clone()),
+        State::Starting(path),
+        move |state| async move {
+            let (path, last) = match state {
+                State::Starting(path) => {
+                    let modified = modified(&path).await;
+
+                    (path, modified)
+                }
+                State::Watching(path, last) => (path, last),
+            };
+
+            loop {
+                tokio::time::sleep(INTERVAL).await;
+
+                let now = modified(&path).await;
+
+                let kind = match (last, now) {
+                    (Some(_), None) => Kind::Removed,
+                    (None, Some(_)) => Kind::Restored,
+                    (Some(last), Some(now)) if last != now => Kind::Modified,
+                    _ => continue,
+                };
+
+                return (Change { generation, kind }, State::Watching(path, now));
+            }
+        },
+    )
+}
+
+/// When the file at `path` was last modified, or `None` if it does not
+/// exist.
+async fn modified(path: &Path) -> Option<SystemTime> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+
+    // Without modification times, existing is all that can be told
+    Some(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+/// The text appended to the file at `path` after its first `offset` bytes,
+/// each time it grows.
+///
+/// A file that shrinks is assumed to have been started over, and is followed
+/// from its new end.
+pub fn growth(path: PathBuf, offset: u64) -> Subscription<String> {
+    struct State {
+        path: PathBuf,
+        offset: u64,
+        /// The start of a character cut off by the end of the last read.
+        partial: Vec<u8>,
+    }
+
+    subscription::unfold(
+        ("follow", path.clone(), offset),
+        State {
+            path,
+            offset,
+            partial: Vec::new(),
+        },
+        |mut state| async move {
+            loop {
+                tokio::time::sleep(INTERVAL).await;
+
+                let Ok(length) = tokio::fs::metadata(&state.path).await.map(|file| file.len())
+                else {
+                    continue;
+                };
+
+                if length < state.offset {
+                    state.offset = length;
+                    state.partial.clear();
+                }
+
+                if length == state.offset {
+                    continue;
+                }
+
+                let Ok(bytes) = read_from(&state.path, state.offset).await else {
+                    continue;
+                };
+
+                state.offset += bytes.len() as u64;
+                state.partial.extend(bytes);
+
+                let valid = match std::str::from_utf8(&state.partial) {
+                    Ok(text) => text.len(),
+                    Err(error) if error.error_len().is_none() => error.valid_up_to(),
+                    // Bytes that are not text at all are not worth waiting on
+                    Err(_) => state.partial.len(),
+                };
+
+                let rest = state.partial.split_off(valid);
+                let text = String::from_utf8_lossy(&state.partial).into_owned();
+                state.partial = rest;
+
+                if !text.is_empty() {
+                    return (text, state);
+                }
+            }
+        },
+    )
+}
+
+async fn read_from(path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(io::SeekFrom::Start(offset)).await?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).await?;
+
+    Ok(bytes)
+}